@@ -0,0 +1,691 @@
+//! A small stand-in for BlueZ on the session bus, to try the manager without
+//! Bluetooth hardware or root:
+//!
+//! ```sh
+//! dbus-run-session -- cargo run --example mock_bluez -- cargo run
+//! ```
+//!
+//! It takes the name `org.bluez`, then runs the given command with
+//! `BLUETOOTH_MANAGER_BUS=session` and exits with its status. Without a
+//! command it keeps serving, for `dbus-send` or a shell on the same bus.
+//!
+//! hci0 is powered on, with paired headphones that report their battery
+//! level through Battery1 and a GATT Battery Service. hci1 is powered off.
+//! Discovery finds a speaker and a keyboard that refuses connections until
+//! it is paired. Pairing asks the registered agent to confirm a passkey.
+
+use std::{
+    collections::HashMap,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::runtime::Handle;
+use zbus::{
+    fdo, interface,
+    object_server::SignalContext,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+    Connection, DBusError,
+};
+
+const BUS_ENV_VAR: &str = "BLUETOOTH_MANAGER_BUS";
+/// Passkey the agent is asked to confirm when pairing.
+const PASSKEY: u32 = 123_456;
+/// Time between StartDiscovery and each device showing up.
+const FIND_DELAY: Duration = Duration::from_millis(500);
+
+const A2DP_SINK_UUID: &str = "0000110b-0000-1000-8000-00805f9b34fb";
+const AVRCP_UUID: &str = "0000110e-0000-1000-8000-00805f9b34fb";
+const HFP_HANDSFREE_UUID: &str = "0000111e-0000-1000-8000-00805f9b34fb";
+const HID_UUID: &str = "00001124-0000-1000-8000-00805f9b34fb";
+const BATTERY_SERVICE_UUID: &str = "0000180f-0000-1000-8000-00805f9b34fb";
+const BATTERY_LEVEL_UUID: &str = "00002a19-0000-1000-8000-00805f9b34fb";
+
+/// The agent registered through AgentManager1: its bus name and path.
+type Agent = Arc<Mutex<Option<(String, OwnedObjectPath)>>>;
+
+/// Errors under BlueZ's own names, so the manager shows what it would
+/// show against the real daemon.
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.bluez.Error")]
+enum BluezError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Failed(String),
+    NotReady(String),
+    InvalidArguments(String),
+    DoesNotExist(String),
+    NotConnected(String),
+    AuthenticationRejected(String),
+}
+
+struct Adapter {
+    name: &'static str,
+    address: &'static str,
+    alias: String,
+    powered: bool,
+    discoverable: bool,
+    pairable: bool,
+    discoverable_timeout: u32,
+    discovering: bool,
+    runtime: Handle, // zbus runs handlers on its own executor
+    agent: Agent,
+}
+
+impl Adapter {
+    fn new(name: &'static str, address: &'static str, powered: bool, agent: &Agent) -> Adapter {
+        Adapter {
+            name,
+            address,
+            alias: format!("mock-{}", name),
+            powered,
+            discoverable: false,
+            pairable: true,
+            discoverable_timeout: 180,
+            discovering: false,
+            runtime: Handle::current(),
+            agent: agent.clone(),
+        }
+    }
+}
+
+#[interface(name = "org.bluez.Adapter1")]
+impl Adapter {
+    async fn start_discovery(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        if !self.powered {
+            return Err(BluezError::NotReady("Resource Not Ready".to_string()));
+        }
+        self.discovering = true;
+        self.discovering_changed(&context).await?;
+
+        let connection = connection.clone();
+        let devices = nearby_devices(self.name, &self.agent);
+        let adapter = self.name;
+        self.runtime.spawn(async move {
+            for device in devices {
+                tokio::time::sleep(FIND_DELAY).await;
+                let path = device_path(adapter, &device.address);
+                // Already known devices keep their object.
+                let _ = connection.object_server().at(path, device).await;
+            }
+        });
+        Ok(())
+    }
+
+    async fn stop_discovery(
+        &mut self,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        if !self.discovering {
+            return Err(BluezError::Failed("No discovery started".to_string()));
+        }
+        self.discovering = false;
+        self.discovering_changed(&context).await?;
+        Ok(())
+    }
+
+    /// Checked like BlueZ does, but devices are found whatever the filter.
+    async fn set_discovery_filter(
+        &self,
+        filter: HashMap<String, OwnedValue>,
+    ) -> Result<(), BluezError> {
+        if filter.contains_key("RSSI") && filter.contains_key("Pathloss") {
+            return Err(BluezError::InvalidArguments(
+                "RSSI and Pathloss cannot be combined".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn remove_device(
+        &self,
+        device: ObjectPath<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> Result<(), BluezError> {
+        let server = connection.object_server();
+        let _ = server.remove::<Battery, _>(&device).await;
+        match server.remove::<Device, _>(&device).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err(BluezError::DoesNotExist("Does Not Exist".to_string())),
+        }
+    }
+
+    #[zbus(property)]
+    fn address(&self) -> &str {
+        self.address
+    }
+
+    #[zbus(property)]
+    fn name(&self) -> String {
+        format!("mock-{}", self.name)
+    }
+
+    #[zbus(property)]
+    fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    #[zbus(property)]
+    fn set_alias(&mut self, alias: String) {
+        self.alias = alias;
+    }
+
+    #[zbus(property)]
+    fn powered(&self) -> bool {
+        self.powered
+    }
+
+    #[zbus(property)]
+    fn set_powered(&mut self, powered: bool) {
+        self.powered = powered;
+    }
+
+    #[zbus(property)]
+    fn discovering(&self) -> bool {
+        self.discovering
+    }
+
+    #[zbus(property)]
+    fn discoverable(&self) -> bool {
+        self.discoverable
+    }
+
+    #[zbus(property)]
+    fn set_discoverable(&mut self, discoverable: bool) {
+        self.discoverable = discoverable;
+    }
+
+    #[zbus(property)]
+    fn pairable(&self) -> bool {
+        self.pairable
+    }
+
+    #[zbus(property)]
+    fn set_pairable(&mut self, pairable: bool) {
+        self.pairable = pairable;
+    }
+
+    #[zbus(property)]
+    fn discoverable_timeout(&self) -> u32 {
+        self.discoverable_timeout
+    }
+
+    #[zbus(property)]
+    fn set_discoverable_timeout(&mut self, seconds: u32) {
+        self.discoverable_timeout = seconds;
+    }
+}
+
+struct Device {
+    address: String,
+    name: String,
+    alias: Option<String>,
+    icon: &'static str,
+    uuids: Vec<String>,
+    rssi: Option<i16>, // only devices found by discovery have one
+    connected: bool,
+    paired: bool,
+    trusted: bool,
+    blocked: bool,
+    needs_pairing: bool, // refuses Connect until paired
+    agent: Agent,
+}
+
+impl Device {
+    fn new(address: &str, name: &str, icon: &'static str, uuids: &[&str], agent: &Agent) -> Device {
+        Device {
+            address: address.to_string(),
+            name: name.to_string(),
+            alias: None,
+            icon,
+            uuids: uuids.iter().map(|uuid| uuid.to_string()).collect(),
+            rssi: None,
+            connected: false,
+            paired: false,
+            trusted: false,
+            blocked: false,
+            needs_pairing: false,
+            agent: agent.clone(),
+        }
+    }
+
+    /// Asks the registered agent to confirm the passkey, as BlueZ does for
+    /// two devices with a display and yes/no input.
+    async fn confirm_passkey(
+        &self,
+        connection: &Connection,
+        device: &ObjectPath<'_>,
+    ) -> Result<(), BluezError> {
+        let agent = self.agent.lock().unwrap().clone();
+        let Some((owner, path)) = agent else {
+            return Ok(());
+        };
+        connection
+            .call_method(
+                Some(owner.as_str()),
+                &path,
+                Some("org.bluez.Agent1"),
+                "RequestConfirmation",
+                &(device, PASSKEY),
+            )
+            .await
+            .map_err(|_| {
+                BluezError::AuthenticationRejected("Authentication Rejected".to_string())
+            })?;
+        Ok(())
+    }
+}
+
+#[interface(name = "org.bluez.Device1")]
+impl Device {
+    async fn connect(
+        &mut self,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        if self.blocked {
+            return Err(BluezError::Failed("Device is blocked".to_string()));
+        }
+        if self.needs_pairing && !self.paired {
+            return Err(BluezError::Failed("br-connection-refused".to_string()));
+        }
+        self.connected = true;
+        self.connected_changed(&context).await?;
+        Ok(())
+    }
+
+    async fn disconnect(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        let transport = transport_path(context.path());
+        let _ = connection
+            .object_server()
+            .remove::<Transport, _>(transport)
+            .await;
+        self.connected = false;
+        self.connected_changed(&context).await?;
+        Ok(())
+    }
+
+    async fn pair(
+        &mut self,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        self.confirm_passkey(connection, context.path()).await?;
+        self.paired = true;
+        self.paired_changed(&context).await?;
+        Ok(())
+    }
+
+    /// Sets up audio for one profile. Like a headset, the device has one
+    /// media transport at a time.
+    async fn connect_profile(
+        &mut self,
+        uuid: String,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        let uuid = uuid.to_ascii_lowercase();
+        if !self.uuids.contains(&uuid) {
+            return Err(BluezError::InvalidArguments(
+                "Invalid arguments in method call".to_string(),
+            ));
+        }
+        let server = connection.object_server();
+        let transport = transport_path(context.path());
+        let _ = server.remove::<Transport, _>(transport.as_str()).await;
+        server.at(transport, Transport { uuid }).await?;
+        self.connected = true;
+        self.connected_changed(&context).await?;
+        Ok(())
+    }
+
+    async fn disconnect_profile(
+        &self,
+        uuid: String,
+        #[zbus(connection)] connection: &Connection,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        let server = connection.object_server();
+        let transport = transport_path(context.path());
+        let active = match server.interface::<_, Transport>(transport.as_str()).await {
+            Ok(interface) => interface.get().await.uuid == uuid.to_ascii_lowercase(),
+            Err(_) => false,
+        };
+        if !active {
+            return Err(BluezError::NotConnected("Not Connected".to_string()));
+        }
+        server.remove::<Transport, _>(transport).await?;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    #[zbus(property)]
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[zbus(property)]
+    fn alias(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+
+    /// An empty alias goes back to the name, as in BlueZ.
+    #[zbus(property)]
+    fn set_alias(&mut self, alias: String) {
+        self.alias = (!alias.is_empty()).then_some(alias);
+    }
+
+    #[zbus(property)]
+    fn icon(&self) -> &str {
+        self.icon
+    }
+
+    #[zbus(property, name = "UUIDs")]
+    fn uuids(&self) -> Vec<String> {
+        self.uuids.clone()
+    }
+
+    /// Left out of GetAll, and so of GetManagedObjects and InterfacesAdded,
+    /// until the device is in range, as in BlueZ.
+    #[zbus(property, name = "RSSI")]
+    fn rssi(&self) -> fdo::Result<i16> {
+        self.rssi
+            .ok_or_else(|| fdo::Error::InvalidArgs("No such property 'RSSI'".to_string()))
+    }
+
+    #[zbus(property)]
+    fn connected(&self) -> bool {
+        self.connected
+    }
+
+    #[zbus(property)]
+    fn paired(&self) -> bool {
+        self.paired
+    }
+
+    #[zbus(property)]
+    fn trusted(&self) -> bool {
+        self.trusted
+    }
+
+    #[zbus(property)]
+    fn set_trusted(&mut self, trusted: bool) {
+        self.trusted = trusted;
+    }
+
+    #[zbus(property)]
+    fn blocked(&self) -> bool {
+        self.blocked
+    }
+
+    #[zbus(property)]
+    fn set_blocked(&mut self, blocked: bool) {
+        self.blocked = blocked;
+    }
+}
+
+struct Battery {
+    percentage: u8,
+}
+
+#[interface(name = "org.bluez.Battery1")]
+impl Battery {
+    #[zbus(property)]
+    fn percentage(&self) -> u8 {
+        self.percentage
+    }
+}
+
+/// Present while audio is set up for the profile `uuid`.
+struct Transport {
+    uuid: String,
+}
+
+#[interface(name = "org.bluez.MediaTransport1")]
+impl Transport {
+    #[zbus(property, name = "UUID")]
+    fn uuid(&self) -> &str {
+        &self.uuid
+    }
+}
+
+struct GattService {
+    uuid: &'static str,
+}
+
+#[interface(name = "org.bluez.GattService1")]
+impl GattService {
+    #[zbus(property, name = "UUID")]
+    fn uuid(&self) -> &str {
+        self.uuid
+    }
+
+    #[zbus(property)]
+    fn primary(&self) -> bool {
+        true
+    }
+}
+
+struct GattCharacteristic {
+    uuid: &'static str,
+    value: Vec<u8>,
+    notifying: bool,
+}
+
+#[interface(name = "org.bluez.GattCharacteristic1")]
+impl GattCharacteristic {
+    async fn read_value(&self, _options: HashMap<String, OwnedValue>) -> Vec<u8> {
+        self.value.clone()
+    }
+
+    /// Notifies the new value back while notifications are on.
+    async fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _options: HashMap<String, OwnedValue>,
+        #[zbus(signal_context)] context: SignalContext<'_>,
+    ) -> Result<(), BluezError> {
+        self.value = value;
+        if self.notifying {
+            self.value_changed(&context).await?;
+        }
+        Ok(())
+    }
+
+    async fn start_notify(&mut self) {
+        self.notifying = true;
+    }
+
+    async fn stop_notify(&mut self) {
+        self.notifying = false;
+    }
+
+    #[zbus(property, name = "UUID")]
+    fn uuid(&self) -> &str {
+        self.uuid
+    }
+
+    #[zbus(property)]
+    fn flags(&self) -> Vec<&str> {
+        vec!["read", "write", "notify"]
+    }
+
+    #[zbus(property)]
+    fn value(&self) -> Vec<u8> {
+        self.value.clone()
+    }
+
+    #[zbus(property)]
+    fn notifying(&self) -> bool {
+        self.notifying
+    }
+}
+
+struct AgentManager {
+    agent: Agent,
+}
+
+#[interface(name = "org.bluez.AgentManager1")]
+impl AgentManager {
+    async fn register_agent(
+        &self,
+        agent: OwnedObjectPath,
+        _capability: String,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+    ) -> fdo::Result<()> {
+        let owner = header
+            .sender()
+            .ok_or_else(|| fdo::Error::Failed("No sender".to_string()))?;
+        *self.agent.lock().unwrap() = Some((owner.to_string(), agent));
+        Ok(())
+    }
+
+    async fn request_default_agent(&self, _agent: OwnedObjectPath) {}
+
+    async fn unregister_agent(&self, _agent: OwnedObjectPath) {
+        *self.agent.lock().unwrap() = None;
+    }
+}
+
+fn device_path(adapter: &str, address: &str) -> String {
+    format!("/org/bluez/{}/dev_{}", adapter, address.replace(':', "_"))
+}
+
+fn transport_path(device: &ObjectPath<'_>) -> String {
+    format!("{}/fd0", device)
+}
+
+/// What discovery on `adapter` finds; hci1 is further away.
+fn nearby_devices(adapter: &str, agent: &Agent) -> Vec<Device> {
+    let mut speaker = Device::new(
+        "11:22:33:44:55:66",
+        "JBL Flip 5",
+        "audio-card",
+        &[A2DP_SINK_UUID, AVRCP_UUID, HFP_HANDSFREE_UUID],
+        agent,
+    );
+    let mut keyboard = Device::new(
+        "AA:BB:CC:DD:EE:FF",
+        "MX Keys",
+        "input-keyboard",
+        &[HID_UUID],
+        agent,
+    );
+    keyboard.needs_pairing = true;
+
+    if adapter == "hci0" {
+        speaker.rssi = Some(-60);
+        keyboard.rssi = Some(-45);
+        vec![speaker, keyboard]
+    } else {
+        speaker.rssi = Some(-80);
+        vec![speaker]
+    }
+}
+
+async fn serve(connection: &Connection) -> zbus::Result<()> {
+    let agent = Agent::default();
+    let server = connection.object_server();
+    server.at("/", fdo::ObjectManager).await?;
+    server
+        .at(
+            "/org/bluez",
+            AgentManager {
+                agent: agent.clone(),
+            },
+        )
+        .await?;
+    server
+        .at(
+            "/org/bluez/hci0",
+            Adapter::new("hci0", "00:1A:7D:DA:71:13", true, &agent),
+        )
+        .await?;
+    server
+        .at(
+            "/org/bluez/hci1",
+            Adapter::new("hci1", "5C:F3:70:8B:12:04", false, &agent),
+        )
+        .await?;
+
+    let mut headphones = Device::new(
+        "00:1B:66:A1:B2:C3",
+        "WH-1000XM4",
+        "audio-headset",
+        &[
+            A2DP_SINK_UUID,
+            AVRCP_UUID,
+            HFP_HANDSFREE_UUID,
+            BATTERY_SERVICE_UUID,
+        ],
+        &agent,
+    );
+    headphones.paired = true;
+    headphones.trusted = true;
+    let path = device_path("hci0", &headphones.address);
+    server.at(path.as_str(), headphones).await?;
+    server.at(path.as_str(), Battery { percentage: 64 }).await?;
+    let service = format!("{}/service0010", path);
+    server
+        .at(
+            service.as_str(),
+            GattService {
+                uuid: BATTERY_SERVICE_UUID,
+            },
+        )
+        .await?;
+    let characteristic = GattCharacteristic {
+        uuid: BATTERY_LEVEL_UUID,
+        value: vec![64],
+        notifying: false,
+    };
+    server
+        .at(format!("{}/char0011", service), characteristic)
+        .await?;
+
+    connection.request_name("org.bluez").await
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let connection = match Connection::session().await {
+        Ok(connection) => connection,
+        Err(err) => {
+            eprintln!("mock_bluez: no session bus: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = serve(&connection).await {
+        eprintln!("mock_bluez: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    let mut args = std::env::args().skip(1);
+    let Some(program) = args.next() else {
+        std::future::pending::<()>().await;
+        return ExitCode::SUCCESS;
+    };
+    let status = tokio::process::Command::new(&program)
+        .args(args)
+        .env(BUS_ENV_VAR, "session")
+        .status()
+        .await;
+    match status {
+        Ok(status) => ExitCode::from(status.code().unwrap_or(1) as u8),
+        Err(err) => {
+            eprintln!("mock_bluez: cannot run {}: {}", program, err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use crate::bluez::{device_location, BluezEvent};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+use zbus::{interface, zvariant::OwnedObjectPath, DBusError};
+
+pub const AGENT_PATH: &str = "/org/bluez/agent/bluetooth_manager";
+pub const AGENT_CAPABILITY: &str = "KeyboardDisplay";
+
+/// What BlueZ is asking the user during pairing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentPrompt {
+    RequestPinCode,
+    RequestPasskey,
+    DisplayPinCode(String),
+    DisplayPasskey { passkey: u32, entered: u16 },
+    RequestConfirmation(u32),
+    RequestAuthorization,
+    AuthorizeService(String),
+    AuthorizePush { name: String, size: Option<u64> }, // incoming OBEX file
+}
+
+impl AgentPrompt {
+    /// Prompts where the user types a PIN or passkey.
+    pub fn needs_input(&self) -> bool {
+        matches!(self, AgentPrompt::RequestPinCode | AgentPrompt::RequestPasskey)
+    }
+
+    /// Prompts that only show a code and expect no answer.
+    pub fn is_display_only(&self) -> bool {
+        matches!(
+            self,
+            AgentPrompt::DisplayPinCode(_) | AgentPrompt::DisplayPasskey { .. }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum AgentReply {
+    Accept,
+    Reject,
+    PinCode(String),
+    Passkey(u32),
+}
+
+/// Numbers the requests of both agents, so a `Cancel` closes its own dialog.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A pending agent call; the D-Bus method returns once `respond` is called
+/// or the request is dropped.
+#[derive(Debug)]
+pub struct AgentRequest {
+    pub id: u64,
+    pub adapter: String,
+    pub address: String,
+    pub prompt: AgentPrompt,
+    reply: Option<oneshot::Sender<AgentReply>>,
+}
+
+impl AgentRequest {
+    /// Creates a request that expects an answer; the reply arrives on the receiver.
+    pub fn new(
+        adapter: String,
+        address: String,
+        prompt: AgentPrompt,
+    ) -> (AgentRequest, oneshot::Receiver<AgentReply>) {
+        let (sender, receiver) = oneshot::channel();
+        let mut request = AgentRequest::notice(adapter, address, prompt);
+        request.reply = Some(sender);
+        (request, receiver)
+    }
+
+    /// Creates a request that only shows something and takes no answer.
+    pub fn notice(adapter: String, address: String, prompt: AgentPrompt) -> AgentRequest {
+        AgentRequest {
+            id: NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed),
+            adapter,
+            address,
+            prompt,
+            reply: None,
+        }
+    }
+
+    /// Whether `newer` is this notice again with new details, like
+    /// DisplayPasskey each time a digit is typed on the remote keyboard.
+    pub fn is_updated_by(&self, newer: &AgentRequest) -> bool {
+        self.prompt.is_display_only()
+            && self.adapter == newer.adapter
+            && self.address == newer.address
+            && std::mem::discriminant(&self.prompt) == std::mem::discriminant(&newer.prompt)
+    }
+
+    pub fn respond(mut self, reply: AgentReply) {
+        if let Some(sender) = self.reply.take() {
+            let _ = sender.send(reply);
+        }
+    }
+}
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.bluez.Error")]
+pub enum AgentError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Rejected(String),
+    Canceled(String),
+}
+
+/// The request an agent last sent to the TUI, for its `Cancel` to close.
+/// BlueZ and obexd have at most one request out per agent.
+#[derive(Debug, Clone, Default)]
+pub struct CurrentRequest(Arc<Mutex<Option<u64>>>);
+
+impl CurrentRequest {
+    pub fn start(&self, request: &AgentRequest) {
+        *self.0.lock().unwrap() = Some(request.id);
+    }
+
+    /// Forgets the request once it is answered, unless a newer one started.
+    pub fn finish(&self, request_id: u64) {
+        let mut current = self.0.lock().unwrap();
+        if *current == Some(request_id) {
+            *current = None;
+        }
+    }
+
+    /// Tells the TUI to drop the request, if one is still out.
+    pub fn cancel(&self, events: &UnboundedSender<BluezEvent>) {
+        if let Some(id) = self.0.lock().unwrap().take() {
+            let _ = events.send(BluezEvent::AgentCanceled(id));
+        }
+    }
+}
+
+/// `org.bluez.Agent1` implementation that forwards every request to the TUI.
+pub struct PairingAgent {
+    events: UnboundedSender<BluezEvent>,
+    current: CurrentRequest,
+}
+
+impl PairingAgent {
+    pub fn new(events: UnboundedSender<BluezEvent>) -> PairingAgent {
+        PairingAgent {
+            events,
+            current: CurrentRequest::default(),
+        }
+    }
+
+    fn notify(&self, device: &OwnedObjectPath, prompt: AgentPrompt) {
+        let (adapter, address) = device_location(device).unwrap_or_default();
+        let request = AgentRequest::notice(adapter, address, prompt);
+        self.current.start(&request);
+        let _ = self.events.send(BluezEvent::AgentRequest(request));
+    }
+
+    async fn ask(
+        &self,
+        device: &OwnedObjectPath,
+        prompt: AgentPrompt,
+    ) -> Result<AgentReply, AgentError> {
+        let (adapter, address) = device_location(device).unwrap_or_default();
+        let (request, receiver) = AgentRequest::new(adapter, address, prompt);
+        let id = request.id;
+        self.current.start(&request);
+
+        self.events
+            .send(BluezEvent::AgentRequest(request))
+            .map_err(|_| AgentError::Canceled("Bluetooth manager is shutting down".to_string()))?;
+
+        let reply = receiver.await;
+        self.current.finish(id);
+        match reply {
+            Ok(AgentReply::Reject) => Err(AgentError::Rejected("Rejected by user".to_string())),
+            Ok(reply) => Ok(reply),
+            Err(_) => Err(AgentError::Canceled("Request canceled".to_string())),
+        }
+    }
+}
+
+#[interface(name = "org.bluez.Agent1")]
+impl PairingAgent {
+    async fn release(&self) {
+        self.current.cancel(&self.events);
+    }
+
+    async fn request_pin_code(&self, device: OwnedObjectPath) -> Result<String, AgentError> {
+        match self.ask(&device, AgentPrompt::RequestPinCode).await? {
+            AgentReply::PinCode(pin) => Ok(pin),
+            _ => Err(AgentError::Rejected("No PIN code entered".to_string())),
+        }
+    }
+
+    async fn display_pin_code(&self, device: OwnedObjectPath, pincode: String) {
+        self.notify(&device, AgentPrompt::DisplayPinCode(pincode));
+    }
+
+    async fn request_passkey(&self, device: OwnedObjectPath) -> Result<u32, AgentError> {
+        match self.ask(&device, AgentPrompt::RequestPasskey).await? {
+            AgentReply::Passkey(passkey) => Ok(passkey),
+            _ => Err(AgentError::Rejected("No passkey entered".to_string())),
+        }
+    }
+
+    async fn display_passkey(&self, device: OwnedObjectPath, passkey: u32, entered: u16) {
+        self.notify(&device, AgentPrompt::DisplayPasskey { passkey, entered });
+    }
+
+    async fn request_confirmation(
+        &self,
+        device: OwnedObjectPath,
+        passkey: u32,
+    ) -> Result<(), AgentError> {
+        self.ask(&device, AgentPrompt::RequestConfirmation(passkey))
+            .await
+            .map(|_| ())
+    }
+
+    async fn request_authorization(&self, device: OwnedObjectPath) -> Result<(), AgentError> {
+        self.ask(&device, AgentPrompt::RequestAuthorization)
+            .await
+            .map(|_| ())
+    }
+
+    async fn authorize_service(
+        &self,
+        device: OwnedObjectPath,
+        uuid: String,
+    ) -> Result<(), AgentError> {
+        self.ask(&device, AgentPrompt::AuthorizeService(uuid))
+            .await
+            .map(|_| ())
+    }
+
+    async fn cancel(&self) {
+        self.current.cancel(&self.events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn request(prompt: AgentPrompt) -> AgentRequest {
+        AgentRequest::notice("hci0".to_string(), "AA:BB:CC:DD:EE:FF".to_string(), prompt)
+    }
+
+    #[test]
+    fn cancel_names_the_request_still_out() {
+        let (events, mut received) = mpsc::unbounded_channel();
+        let current = CurrentRequest::default();
+        let answered = request(AgentPrompt::RequestAuthorization);
+        let waiting = request(AgentPrompt::RequestPinCode);
+        assert_ne!(answered.id, waiting.id);
+
+        current.start(&answered);
+        current.finish(answered.id);
+        current.cancel(&events);
+        assert!(received.try_recv().is_err());
+
+        current.start(&waiting);
+        // A late answer to an older request leaves the newer one out.
+        current.finish(answered.id);
+        current.cancel(&events);
+        assert!(
+            matches!(received.try_recv(), Ok(BluezEvent::AgentCanceled(id)) if id == waiting.id)
+        );
+        current.cancel(&events);
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn only_notices_are_updated_in_place() {
+        let passkey = |entered| AgentPrompt::DisplayPasskey {
+            passkey: 123_456,
+            entered,
+        };
+        assert!(request(passkey(0)).is_updated_by(&request(passkey(2))));
+        assert!(!request(passkey(0))
+            .is_updated_by(&request(AgentPrompt::DisplayPinCode("0000".to_string()))));
+        let confirm = AgentPrompt::RequestConfirmation(123_456);
+        assert!(!request(confirm.clone()).is_updated_by(&request(confirm)));
+    }
+}
@@ -1,13 +1,42 @@
-#[derive(Debug, Clone)]
+use crate::agent::{AgentPrompt, AgentReply, AgentRequest};
+use crate::backend::{spawn_device_operation, BackendResult, BluetoothBackend};
+use crate::bluez::{BluezEvent, DeviceOperation};
+use crate::config::Config;
+use crate::device_type::DeviceType;
+use crate::discovery_filter::{DiscoveryFilter, FilterField};
+use crate::event_log::{EventLog, UtcTime, LogLevel, DEFAULT_LOG_SIZE};
+use crate::file_picker::FilePicker;
+use crate::gatt::{gatt_label, GattAttribute, GattBrowser, GattKind};
+use crate::keymap::{Action, Keymap};
+use crate::known_devices::{unix_now, KnownDevices};
+use crate::notifications::DesktopNotifier;
+use crate::obex::{FileTransfer, TransferDirection, TransferStatus};
+use crate::rfkill::{self, BlockState, Rfkill, RfkillSwitch};
+use crate::theme::Theme;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+#[derive(Debug, Clone, Serialize)]
 pub struct BluetoothDevice {
     pub address: String,
+    pub adapter: String, // hciN this device was seen on
     pub name: String,
     pub alias: Option<String>,
     pub connected: bool,
     pub paired: bool,
     pub trusted: bool,
-    pub device_type: String,
+    pub blocked: bool, // BlueZ refuses its connections
+    pub device_type: DeviceType,
     pub rssi: Option<i16>,
+    pub battery: Option<u8>, // percentage from org.bluez.Battery1
+    pub uuids: Vec<String>, // profiles and services the device offers
+    pub active_profiles: Vec<String>, // profile UUIDs with an open audio transport
     pub known: bool, // Whether this device was previously paired/known
 }
 
@@ -15,27 +44,37 @@ impl BluetoothDevice {
     pub fn new(address: String, name: String) -> Self {
         Self {
             address,
+            adapter: String::new(),
             name,
             alias: None,
             connected: false,
             paired: false,
             trusted: false,
-            device_type: "Unknown".to_string(),
+            blocked: false,
+            device_type: DeviceType::Unknown,
             rssi: None,
+            battery: None,
+            uuids: vec![],
+            active_profiles: vec![],
             known: false,
         }
     }
 
-    pub fn new_known(address: String, name: String, device_type: String) -> Self {
+    pub fn new_known(address: String, name: String, device_type: DeviceType) -> Self {
         Self {
             address,
+            adapter: String::new(),
             name,
             alias: None,
             connected: false,
             paired: true,
             trusted: true,
+            blocked: false,
             device_type,
             rssi: None,
+            battery: None,
+            uuids: vec![],
+            active_profiles: vec![],
             known: true,
         }
     }
@@ -43,16 +82,159 @@ impl BluetoothDevice {
     pub fn display_name(&self) -> String {
         self.alias.as_ref().unwrap_or(&self.name).clone()
     }
+
+    pub fn is(&self, adapter: &str, address: &str) -> bool {
+        self.adapter == adapter && self.address == address
+    }
+
+    /// Devices that never sent a name, for which BlueZ falls back to the
+    /// address (`AA:BB:..` as Name, `AA-BB-..` as Alias).
+    pub fn is_unnamed(&self) -> bool {
+        let name = self.display_name();
+        name == self.address || name == self.address.replace(':', "-")
+    }
+
+    /// Case-insensitive match of `query` against name, alias and address;
+    /// the address also matches without its colons.
+    pub fn matches_search(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let address = self.address.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self
+                .alias
+                .as_ref()
+                .is_some_and(|alias| alias.to_lowercase().contains(&query))
+            || address.contains(&query)
+            || address.replace(':', "").contains(&query)
+    }
+}
+
+/// State of a local adapter as reported by `org.bluez.Adapter1`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BluetoothAdapter {
+    pub name: String, // hciN
+    pub address: String,
+    pub alias: String,
+    pub powered: bool,
+    pub discoverable: bool,
+    pub pairable: bool,
+    pub discoverable_timeout: u32, // seconds, 0 = never
+    pub discovering: bool,
 }
 
+/// Step used by the Settings view to change the discoverable timeout.
+pub const DISCOVERABLE_TIMEOUT_STEP: u32 = 60;
+
+/// Battery percentage below which a connected device triggers a warning.
+pub const LOW_BATTERY_THRESHOLD: u8 = 20;
+
+/// RSSI samples kept per device for the signal history.
+pub const RSSI_HISTORY_LEN: usize = 120;
+
+/// Ticks between two reads of the rfkill switches.
+const RFKILL_POLL_TICKS: u64 = 8;
+
+/// Minimum time between two automatic connection attempts to one device.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+const SPINNER_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
 pub struct App {
     pub devices: Vec<BluetoothDevice>,
-    pub selected_index: usize,
+    pub selected_index: usize, // index into visible_devices()
+    pub table_offset: usize,   // first device row shown; kept by the UI
+    pub table_rows: usize,     // device rows that fit in the table, from the last frame
+    pub type_filter: Option<DeviceType>,
+    pub sort_mode: SortMode,
+    pub search_query: String,
+    pub searching: bool, // typing into the search box
+    pub hide_unnamed: bool,
+    pub only_known: bool,
+    pub only_connected: bool,
+    pub only_blocked: bool,
     pub scanning: bool,
     pub bluetooth_enabled: bool,
+    pub rfkill: Rfkill,
+    pub rfkill_switches: Vec<RfkillSwitch>, // Bluetooth switches, refreshed every few ticks
+    pub unblock_prompt: bool, // asking whether to soft-unblock before powering on
+    pub adapters: Vec<BluetoothAdapter>,
+    pub selected_adapter: Option<String>, // hciN used for scanning and adapter settings
+    pub discovery_filter: DiscoveryFilter, // applied before every scan
+    pub discovery_filter_path: Option<PathBuf>, // None keeps changes for this run only
+    pub filter_field: FilterField, // cursor in the Settings view
+    pub filter_edit: Option<FilterField>, // typed field being edited
+    pub filter_input: String,
     pub current_view: View,
+    pub details_device: Option<(String, String)>, // (adapter, address) the details view acts on
     pub status_message: String,
     pub error_message: Option<String>,
+    pub agent_request: Option<AgentRequest>, // shown in the dialog
+    pub agent_queue: VecDeque<AgentRequest>, // waiting for the dialog, oldest first
+    pub agent_input: String,
+    pub alias_edit: Option<AliasTarget>,
+    pub alias_input: String,
+    pub profile_index: usize, // cursor in the selected device's UUIDs
+    pub gatt: Option<GattBrowser>, // Some while the GATT view is open
+    pub file_picker: Option<FilePicker>, // Some while choosing a file to send
+    pub transfers: Vec<FileTransfer>, // latest OBEX transfer of each device
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub event_log: EventLog,
+    pub log_index: Option<usize>, // entry under the cursor; None follows the newest
+    pub log_offset: usize,        // first entry shown; kept by the UI
+    pub log_rows: usize,          // entries that fit in the log view, from the last frame
+    pub desktop_notifications: bool,
+    pub notify_level: LogLevel, // least important level sent to the desktop
+    pub tick_count: u64,
+    pub known_devices: KnownDevices,
+    pub auto_reconnect: bool, // off for one-shot CLI commands
+    rssi_history: HashMap<(String, String), VecDeque<i16>>, // (adapter, address) -> oldest first
+    last_seen: HashMap<(String, String), u64>, // seconds since the Unix epoch
+    reconnect_attempts: HashMap<(String, String), Instant>, // (adapter, address) -> last try
+    auto_connecting: HashSet<(String, String)>, // operations started by auto-reconnect
+    backend: Option<Arc<dyn BluetoothBackend>>,
+    backend_events: Option<UnboundedSender<BluezEvent>>,
+    notifier: Option<DesktopNotifier>,
+}
+
+/// What the inline alias editor is renaming.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AliasTarget {
+    Device { adapter: String, address: String },
+    Adapter(String), // hciN
+}
+
+/// Order of the device table.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortMode {
+    #[default]
+    Discovery, // order in which devices were found
+    Rssi,      // strongest signal first
+    Name,
+    LastSeen, // most recently in range first
+    ConnectedFirst,
+}
+
+impl SortMode {
+    pub fn next(self) -> SortMode {
+        match self {
+            SortMode::Discovery => SortMode::Rssi,
+            SortMode::Rssi => SortMode::Name,
+            SortMode::Name => SortMode::LastSeen,
+            SortMode::LastSeen => SortMode::ConnectedFirst,
+            SortMode::ConnectedFirst => SortMode::Discovery,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortMode::Discovery => "discovery order",
+            SortMode::Rssi => "signal strength",
+            SortMode::Name => "name",
+            SortMode::LastSeen => "last seen",
+            SortMode::ConnectedFirst => "connected first",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -60,138 +242,2568 @@ pub enum View {
     DeviceList,
     DeviceDetails,
     Settings,
+    Gatt, // services of the device opened from DeviceDetails
+    Log,
 }
 
 impl App {
     pub fn new() -> App {
-        let mut app = App {
+        App {
             devices: vec![],
             selected_index: 0,
+            table_offset: 0,
+            table_rows: 0,
+            type_filter: None,
+            sort_mode: SortMode::default(),
+            search_query: String::new(),
+            searching: false,
+            hide_unnamed: false,
+            only_known: false,
+            only_connected: false,
+            only_blocked: false,
             scanning: false,
-            bluetooth_enabled: true,
+            bluetooth_enabled: false,
+            rfkill: Rfkill::disabled(),
+            rfkill_switches: vec![],
+            unblock_prompt: false,
+            adapters: vec![],
+            selected_adapter: None,
+            discovery_filter: DiscoveryFilter::default(),
+            discovery_filter_path: None,
+            filter_field: FilterField::default(),
+            filter_edit: None,
+            filter_input: String::new(),
             current_view: View::DeviceList,
-            status_message: "Ready - Press 's' to start scanning".to_string(),
+            details_device: None,
+            status_message: ready_message(&Keymap::default()),
             error_message: None,
+            agent_request: None,
+            agent_queue: VecDeque::new(),
+            agent_input: String::new(),
+            alias_edit: None,
+            alias_input: String::new(),
+            profile_index: 0,
+            gatt: None,
+            file_picker: None,
+            transfers: vec![],
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            event_log: EventLog::new(DEFAULT_LOG_SIZE),
+            log_index: None,
+            log_offset: 0,
+            log_rows: 0,
+            desktop_notifications: false,
+            notify_level: LogLevel::Warning,
+            tick_count: 0,
+            known_devices: KnownDevices::in_memory(),
+            auto_reconnect: true,
+            rssi_history: HashMap::new(),
+            last_seen: HashMap::new(),
+            reconnect_attempts: HashMap::new(),
+            auto_connecting: HashSet::new(),
+            backend: None,
+            backend_events: None,
+            notifier: None,
+        }
+    }
+
+    /// Adds an event to the log, and to the desktop when notifications are
+    /// on and the event is important enough.
+    pub fn log(&mut self, level: LogLevel, message: impl Into<String>) {
+        // Keep the cursor on the same entry when the oldest one is dropped
+        if self.event_log.push(level, message.into()) {
+            self.log_index = self.log_index.map(|index| index.saturating_sub(1));
+            self.log_offset = self.log_offset.saturating_sub(1);
+        }
+        if let (true, Some(notifier), Some(entry)) = (
+            self.desktop_notifications && level >= self.notify_level,
+            &self.notifier,
+            self.event_log.entries().back(),
+        ) {
+            notifier.show(entry);
+        }
+    }
+
+    /// Shows `message` in the status bar as an error and logs it.
+    pub fn report_error(&mut self, message: String) {
+        self.log(LogLevel::Error, message.clone());
+        self.error_message = Some(message);
+    }
+
+    /// Loads the devices the backend already knows and starts following its
+    /// changes. The returned receiver yields the events to pass to `handle_bluez_event`.
+    pub async fn attach_backend(
+        &mut self,
+        backend: Arc<dyn BluetoothBackend>,
+    ) -> UnboundedReceiver<BluezEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        if let Err(err) = backend.watch(sender.clone()).await {
+            self.report_error(format!("Failed to watch Bluetooth changes: {}", err));
+        }
+
+        self.backend = Some(backend);
+        self.backend_events = Some(sender);
+        self.refresh_rfkill();
+        self.refresh_adapters().await;
+        self.refresh_devices().await;
+        receiver
+    }
+
+    /// Makes this process BlueZ's default pairing agent. Only the TUI and
+    /// `pair` do this, so other commands leave a running TUI's agent alone.
+    pub async fn register_agent(&mut self) {
+        let (Some(backend), Some(events)) = (&self.backend, &self.backend_events) else {
+            return;
+        };
+        if let Err(err) = backend.register_agent(events.clone()).await {
+            self.report_error(format!("Failed to register pairing agent: {}", err));
+        }
+    }
+
+    pub fn current_adapter(&self) -> Option<&BluetoothAdapter> {
+        let name = self.selected_adapter.as_ref()?;
+        self.adapters.iter().find(|a| &a.name == name)
+    }
+
+    /// Makes the next adapter the one used for scanning and adapter settings.
+    /// A scan on the previous adapter is stopped first; nothing else would
+    /// stop it once the adapter is no longer selected.
+    pub async fn select_next_adapter(&mut self) {
+        if self.adapters.is_empty() {
+            return;
+        }
+        let stopped_scan = self.scanning;
+        if stopped_scan {
+            self.toggle_scanning().await;
+        }
+
+        let next = self
+            .adapters
+            .iter()
+            .position(|a| Some(&a.name) == self.selected_adapter.as_ref())
+            .map(|index| (index + 1) % self.adapters.len())
+            .unwrap_or(0);
+        let adapter = self.adapters[next].clone();
+        self.status_message = format!("Using adapter {} ({})", adapter.name, adapter.address);
+        if stopped_scan {
+            self.status_message = format!("Scan stopped. {}", self.status_message);
+        }
+        self.selected_adapter = Some(adapter.name);
+        self.sync_adapter_state();
+    }
+
+    /// Makes `name` the adapter used for scanning and adapter settings.
+    /// Returns false when there is no such adapter.
+    pub fn select_adapter(&mut self, name: &str) -> bool {
+        if !self.adapters.iter().any(|a| a.name == name) {
+            return false;
+        }
+        self.selected_adapter = Some(name.to_string());
+        self.sync_adapter_state();
+        true
+    }
+
+    /// The backend and the selected adapter name, or an error if either is missing.
+    fn adapter_target(&mut self) -> Option<(Arc<dyn BluetoothBackend>, String)> {
+        let Some(client) = self.backend.clone() else {
+            self.report_error("BlueZ is not available".to_string());
+            return None;
         };
-        
-        app
+        let Some(adapter) = self.selected_adapter.clone() else {
+            self.report_error("No Bluetooth adapter found".to_string());
+            return None;
+        };
+        Some((client, adapter))
     }
 
-    pub fn toggle_scanning(&mut self) {
+    pub async fn toggle_scanning(&mut self) {
         self.scanning = !self.scanning;
         if self.scanning {
-            self.status_message = "Scanning for nearby devices...".to_string();
-            self.scan_for_devices();
+            self.status_message = if self.discovery_filter.is_empty() {
+                "Scanning for nearby devices...".to_string()
+            } else {
+                format!(
+                    "Scanning for nearby devices (filter: {})...",
+                    self.discovery_filter.summary()
+                )
+            };
+            self.scan_for_devices().await;
         } else {
+            if let Some((client, adapter)) = self.adapter_target() {
+                if let Err(err) = client.stop_discovery(&adapter).await {
+                    self.report_error(format!("Failed to stop scan: {}", err));
+                }
+            }
             self.status_message = "Scan stopped".to_string();
         }
     }
 
-    pub fn scan_for_devices(&mut self) {
-        // TODO: Replace with real Bluetooth scanning
-        // This would integrate with BlueZ via D-Bus
-        // For now, this is a placeholder for real device discovery
-        
-        self.status_message = "No devices found nearby. Try moving closer to Bluetooth devices.".to_string();
-        
-        // In a real implementation, this would:
-        // 1. Call BlueZ D-Bus methods to start discovery
-        // 2. Listen for DeviceAdded signals
-        // 3. Update the device list with real nearby devices
-        // 4. Get real RSSI values and device information
+    pub async fn scan_for_devices(&mut self) {
+        let Some((client, adapter)) = self.adapter_target() else {
+            self.scanning = false;
+            return;
+        };
+
+        if let Err(err) = client.set_discovery_filter(&adapter, &self.discovery_filter).await {
+            self.scanning = false;
+            self.report_error(format!("Failed to set discovery filter: {}", err));
+            return;
+        }
+        if let Err(err) = client.start_discovery(&adapter).await {
+            self.scanning = false;
+            self.report_error(format!("Failed to start scan: {}", err));
+            return;
+        }
+
+        self.error_message = None;
+        self.refresh_devices().await;
+        if self.devices.is_empty() {
+            self.status_message = "No devices found nearby yet. Try moving closer to Bluetooth devices.".to_string();
+        }
+    }
+
+    async fn refresh_devices(&mut self) {
+        let Some(client) = &self.backend else { return };
+
+        match client.devices().await {
+            Ok(devices) => {
+                for device in devices {
+                    self.upsert_device(device);
+                }
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to list devices: {}", err));
+            }
+        }
     }
 
-    pub fn select_next(&mut self) {
-        if !self.devices.is_empty() {
-            self.selected_index = (self.selected_index + 1) % self.devices.len();
+    async fn refresh_adapters(&mut self) {
+        let Some(client) = &self.backend else { return };
+
+        match client.adapters().await {
+            Ok(adapters) => {
+                if adapters.is_empty() {
+                    self.report_error("No Bluetooth adapter found".to_string());
+                }
+                let powered_on: Vec<String> = adapters
+                    .iter()
+                    .filter(|a| self.was_powered_off(a))
+                    .map(|a| a.name.clone())
+                    .collect();
+                self.adapters = adapters;
+                self.sync_adapter_state();
+                for name in powered_on {
+                    self.reconnect_on_adapter(&name);
+                }
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to list adapters: {}", err));
+            }
         }
     }
 
-    pub fn select_previous(&mut self) {
-        if !self.devices.is_empty() {
-            self.selected_index = if self.selected_index == 0 {
-                self.devices.len() - 1
+    fn apply_adapter(&mut self, adapter: BluetoothAdapter) {
+        let powered_on = self.was_powered_off(&adapter);
+        let powered_off = !adapter.powered
+            && self
+                .adapters
+                .iter()
+                .any(|a| a.name == adapter.name && a.powered);
+        let name = adapter.name.clone();
+        if powered_on || powered_off {
+            let state = if powered_on { "on" } else { "off" };
+            self.log(LogLevel::Info, format!("Adapter {} powered {}", name, state));
+        }
+        match self.adapters.iter_mut().find(|a| a.name == adapter.name) {
+            Some(existing) => *existing = adapter,
+            None => {
+                self.adapters.push(adapter);
+                self.adapters.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+        }
+        self.sync_adapter_state();
+        if powered_on {
+            self.reconnect_on_adapter(&name);
+        }
+    }
+
+    /// Whether `adapter` is powered now but was seen powered off before.
+    fn was_powered_off(&self, adapter: &BluetoothAdapter) -> bool {
+        adapter.powered
+            && self
+                .adapters
+                .iter()
+                .any(|a| a.name == adapter.name && !a.powered)
+    }
+
+    fn forget_adapter(&mut self, name: &str) {
+        self.adapters.retain(|a| a.name != name);
+        let removed: Vec<String> = self
+            .devices
+            .iter()
+            .filter(|d| d.adapter == name)
+            .map(|d| d.address.clone())
+            .collect();
+        for address in removed {
+            self.forget_device(name, &address);
+        }
+        self.sync_adapter_state();
+    }
+
+    /// Keeps the selection pointing at an existing adapter and mirrors its
+    /// power and discovery state into the header flags.
+    fn sync_adapter_state(&mut self) {
+        if self.current_adapter().is_none() {
+            self.selected_adapter = self.adapters.first().map(|a| a.name.clone());
+        }
+
+        let (powered, discovering) = self
+            .current_adapter()
+            .map(|a| (a.powered, a.discovering))
+            .unwrap_or((false, false));
+        self.bluetooth_enabled = powered;
+        self.scanning = discovering;
+    }
+
+    /// Applies one event reported by BlueZ.
+    pub async fn handle_bluez_event(&mut self, event: BluezEvent) {
+        match event {
+            BluezEvent::DeviceAdded(device) => {
+                if self.scanning {
+                    self.status_message = format!("Found {}", device.display_name());
+                }
+                self.log(
+                    LogLevel::Info,
+                    format!("Found {} ({})", device.display_name(), device.address),
+                );
+                let (adapter, address) = (device.adapter.clone(), device.address.clone());
+                self.upsert_device(device);
+                self.reconnect_in_range(&adapter, &address);
+            }
+            BluezEvent::DeviceChanged(device) => {
+                let (adapter, address) = (device.adapter.clone(), device.address.clone());
+                self.upsert_device(device);
+                self.reconnect_in_range(&adapter, &address);
+            }
+            BluezEvent::DevicePropertiesChanged {
+                adapter,
+                address,
+                changes,
+            } => {
+                // Devices arrive whole through DeviceAdded first.
+                let Some(mut device) = self
+                    .devices
+                    .iter()
+                    .find(|d| d.is(&adapter, &address))
+                    .cloned()
+                else {
+                    return;
+                };
+                changes.apply_to(&mut device);
+                self.upsert_device(device);
+                self.reconnect_in_range(&adapter, &address);
+            }
+            BluezEvent::DeviceRemoved { adapter, address } => {
+                let name = self.device_name(&adapter, &address);
+                self.log(LogLevel::Info, format!("Lost {} ({})", name, address));
+                self.forget_device(&adapter, &address);
+            }
+            BluezEvent::AdapterChanged(adapter) => {
+                self.apply_adapter(adapter);
+            }
+            BluezEvent::AdapterRemoved(name) => {
+                self.log(LogLevel::Warning, format!("Adapter {} removed", name));
+                self.forget_adapter(&name);
+            }
+            BluezEvent::OperationFinished {
+                adapter,
+                address,
+                operation,
+                result,
+            } => {
+                self.finish_device_operation(&adapter, &address, operation, result)
+                    .await;
+            }
+            BluezEvent::AgentRequest(request) => {
+                self.show_agent_request(request).await;
+            }
+            BluezEvent::AgentCanceled(id) => {
+                self.cancel_agent_request(id);
+            }
+            BluezEvent::GattValueChanged { path, value } => {
+                if let Some(browser) = &mut self.gatt {
+                    browser.value_changed(&path, value);
+                }
+            }
+            BluezEvent::TransferChanged(transfer) => {
+                self.update_transfer(transfer);
+            }
+        }
+    }
+
+    fn upsert_device(&mut self, mut device: BluetoothDevice) {
+        // Before anything the sort depends on changes
+        let selected = self.selected_key();
+        self.apply_known_device(&mut device);
+        self.record_signal(&device);
+        let previous_battery = self
+            .devices
+            .iter()
+            .find(|d| d.is(&device.adapter, &device.address))
+            .and_then(|d| d.battery);
+        self.warn_low_battery(&device, previous_battery);
+        self.log_state_changes(&device);
+
+        match self
+            .devices
+            .iter_mut()
+            .find(|d| d.is(&device.adapter, &device.address))
+        {
+            Some(existing) => *existing = device,
+            None => self.devices.push(device),
+        }
+        self.reselect(selected);
+    }
+
+    /// Logs connections and pairings of a device already in the list.
+    fn log_state_changes(&mut self, device: &BluetoothDevice) {
+        let Some(previous) = self
+            .devices
+            .iter()
+            .find(|d| d.is(&device.adapter, &device.address))
+        else {
+            return;
+        };
+        let (was_connected, was_paired) = (previous.connected, previous.paired);
+        let name = device.display_name();
+        if device.connected != was_connected {
+            let message = if device.connected {
+                format!("Connected to {}", name)
             } else {
-                self.selected_index - 1
+                format!("Disconnected from {}", name)
             };
+            self.log(LogLevel::Info, message);
+        }
+        if device.paired && !was_paired {
+            self.log(LogLevel::Info, format!("Paired with {}", name));
         }
     }
 
-    pub fn get_selected_device(&self) -> Option<&BluetoothDevice> {
-        self.devices.get(self.selected_index)
+    /// Adds a new RSSI reading to the device's history and notes that it
+    /// was in range now. Updates of other properties (battery, connected,
+    /// ...) repeat the last reading and are not a new sample.
+    fn record_signal(&mut self, device: &BluetoothDevice) {
+        let key = (device.adapter.clone(), device.address.clone());
+        if device.connected || device.rssi.is_some() {
+            self.last_seen.insert(key.clone(), unix_now());
+        }
+        let Some(rssi) = device.rssi else { return };
+        let previous = self
+            .devices
+            .iter()
+            .find(|d| d.is(&device.adapter, &device.address))
+            .and_then(|d| d.rssi);
+        if previous == Some(rssi) {
+            return;
+        }
+
+        let history = self.rssi_history.entry(key).or_default();
+        if history.len() == RSSI_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(rssi);
+    }
+
+    pub fn rssi_history(&self, device: &BluetoothDevice) -> Option<&VecDeque<i16>> {
+        self.rssi_history
+            .get(&(device.adapter.clone(), device.address.clone()))
+    }
+
+    /// When the device was last in range, this session or in an earlier one.
+    pub fn last_seen(&self, device: &BluetoothDevice) -> Option<u64> {
+        self.last_seen
+            .get(&(device.adapter.clone(), device.address.clone()))
+            .copied()
+            .or_else(|| {
+                self.known_devices
+                    .get(&device.adapter, &device.address)
+                    .and_then(|known| known.last_seen)
+            })
+    }
+
+    fn selected_key(&self) -> Option<(String, String)> {
+        self.get_selected_device()
+            .map(|d| (d.adapter.clone(), d.address.clone()))
+    }
+
+    /// Moves the cursor back onto a device after the list order changed.
+    fn reselect(&mut self, selected: Option<(String, String)>) {
+        let Some((adapter, address)) = selected else { return };
+        if let Some(index) = self
+            .visible_devices()
+            .iter()
+            .position(|d| d.is(&adapter, &address))
+        {
+            self.selected_index = index;
+        }
     }
 
-    pub fn toggle_bluetooth(&mut self) {
-        self.bluetooth_enabled = !self.bluetooth_enabled;
-        if self.bluetooth_enabled {
-            self.status_message = "Bluetooth enabled".to_string();
+    /// Updates the store from `device` and fills in what only the store
+    /// knows: that it is known, and its alias when BlueZ has none.
+    fn apply_known_device(&mut self, device: &mut BluetoothDevice) {
+        if self.known_devices.remember(device, unix_now()) {
+            self.save_known_devices();
+        }
+        if let Some(known) = self.known_devices.get(&device.adapter, &device.address) {
+            device.known = true;
+            if device.alias.is_none() {
+                device.alias = known.alias.clone();
+            }
+        }
+    }
+
+    fn save_known_devices(&mut self) {
+        if let Err(err) = self.known_devices.save() {
+            self.report_error(format!("Failed to save known devices: {}", err));
+        }
+    }
+
+    pub fn auto_connect_enabled(&self, device: &BluetoothDevice) -> bool {
+        self.known_devices
+            .get(&device.adapter, &device.address)
+            .is_some_and(|known| known.auto_connect)
+    }
+
+    pub fn toggle_auto_connect(&mut self) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+
+        let enabled = !self.auto_connect_enabled(&device);
+        self.known_devices.set_auto_connect(&device, enabled);
+        self.save_known_devices();
+        let selected = self.selected_key();
+        if let Some(existing) = self
+            .devices
+            .iter_mut()
+            .find(|d| d.is(&device.adapter, &device.address))
+        {
+            existing.known = true;
+        }
+        self.reselect(selected);
+        self.status_message = if enabled {
+            format!("{} will be connected automatically", device.display_name())
         } else {
-            self.status_message = "Bluetooth disabled".to_string();
+            format!("Auto-connect disabled for {}", device.display_name())
+        };
+    }
+
+    /// Reconnects an auto-connect device that just showed up in a scan.
+    fn reconnect_in_range(&mut self, adapter: &str, address: &str) {
+        let in_range = self
+            .devices
+            .iter()
+            .any(|d| d.is(adapter, address) && d.rssi.is_some());
+        if in_range {
+            self.auto_reconnect(adapter, address);
         }
     }
 
-    pub fn connect_device(&mut self) {
-        if let Some(device) = self.devices.get_mut(self.selected_index) {
-            if !device.connected {
-                // TODO: Implement real Bluetooth connection
-                device.connected = true;
-                self.status_message = format!("Connected to {}", device.display_name());
-            } else {
-                // TODO: Implement real Bluetooth disconnection
-                device.connected = false;
-                self.status_message = format!("Disconnected from {}", device.display_name());
+    /// Reconnects every auto-connect device of an adapter that was just powered on.
+    fn reconnect_on_adapter(&mut self, adapter: &str) {
+        let addresses: Vec<String> = self
+            .known_devices
+            .devices()
+            .iter()
+            .filter(|known| known.adapter == adapter && known.auto_connect)
+            .map(|known| known.address.clone())
+            .collect();
+        for address in addresses {
+            self.auto_reconnect(adapter, &address);
+        }
+    }
+
+    /// Starts connecting a disconnected auto-connect device in the
+    /// background, with its preferred profile when it has one. Attempts are
+    /// spaced by `RECONNECT_INTERVAL` so an unreachable device is not retried
+    /// on every RSSI update.
+    fn auto_reconnect(&mut self, adapter: &str, address: &str) {
+        let Some(device) = self.devices.iter().find(|d| d.is(adapter, address)) else { return };
+        let Some(known) = self.known_devices.get(adapter, address) else { return };
+        let powered = self.adapters.iter().any(|a| a.name == adapter && a.powered);
+        if !self.auto_reconnect || device.connected || device.blocked || !known.auto_connect || !powered {
+            return;
+        }
+
+        let key = (adapter.to_string(), address.to_string());
+        if self.auto_connecting.contains(&key)
+            || self
+                .reconnect_attempts
+                .get(&key)
+                .is_some_and(|last| last.elapsed() < RECONNECT_INTERVAL)
+        {
+            return;
+        }
+        let (Some(backend), Some(events)) = (&self.backend, &self.backend_events) else { return };
+
+        let operation = match &known.preferred_profile {
+            Some(uuid) if device.uuids.contains(uuid) => DeviceOperation::ConnectProfile(uuid.clone()),
+            _ => DeviceOperation::Connect,
+        };
+        self.status_message = format!("Auto-connecting {}...", device.display_name());
+        spawn_device_operation(
+            Arc::clone(backend),
+            key.0.clone(),
+            key.1.clone(),
+            operation,
+            events.clone(),
+        );
+        self.reconnect_attempts.insert(key.clone(), Instant::now());
+        self.auto_connecting.insert(key);
+    }
+
+    /// Warns once when a connected device's battery drops below the threshold.
+    fn warn_low_battery(&mut self, device: &BluetoothDevice, previous: Option<u8>) {
+        let Some(level) = device.battery else { return };
+        let was_low = previous.is_some_and(|p| p < LOW_BATTERY_THRESHOLD);
+        if device.connected && level < LOW_BATTERY_THRESHOLD && !was_low {
+            self.status_message = format!("Low battery: {} is at {}%", device.display_name(), level);
+            self.log(LogLevel::Warning, self.status_message.clone());
+        }
+    }
+
+    fn forget_device(&mut self, adapter: &str, address: &str) {
+        let visible_index = self
+            .visible_devices()
+            .iter()
+            .position(|d| d.is(adapter, address));
+        self.devices.retain(|d| !d.is(adapter, address));
+        self.rssi_history
+            .remove(&(adapter.to_string(), address.to_string()));
+        if self.gatt.as_ref().is_some_and(|b| b.adapter == adapter && b.address == address) {
+            self.gatt = None;
+        }
+        // Nothing is left to act on; the neighbouring row must not take its place.
+        if self
+            .details_device
+            .as_ref()
+            .is_some_and(|(a, d)| a == adapter && d == address)
+        {
+            self.details_device = None;
+            self.file_picker = None;
+            self.current_view = View::DeviceList;
+        }
+        if matches!(&self.alias_edit, Some(AliasTarget::Device { adapter: a, address: d })
+            if a == adapter && d == address)
+        {
+            self.cancel_alias_edit();
+        }
+
+        let visible = self.visible_devices().len();
+        if visible_index.is_some_and(|index| index < self.selected_index)
+            || (self.selected_index >= visible && visible > 0)
+        {
+            self.selected_index -= 1;
+        }
+    }
+
+    /// Devices shown in the list: those matching the type filter, the
+    /// search and the toggles, in the order of the sort mode.
+    pub fn visible_devices(&self) -> Vec<&BluetoothDevice> {
+        let mut devices: Vec<&BluetoothDevice> = self
+            .devices
+            .iter()
+            .filter(|d| self.type_filter.is_none_or(|kind| d.device_type == kind))
+            .filter(|d| self.search_query.is_empty() || d.matches_search(&self.search_query))
+            .filter(|d| !(self.hide_unnamed && d.is_unnamed()))
+            .filter(|d| !self.only_known || d.known)
+            .filter(|d| !self.only_connected || d.connected)
+            .filter(|d| !self.only_blocked || d.blocked)
+            .collect();
+
+        // Stable sorts, so ties keep the discovery order.
+        match self.sort_mode {
+            SortMode::Discovery => {}
+            SortMode::Rssi => devices.sort_by_key(|d| std::cmp::Reverse(d.rssi)),
+            SortMode::Name => devices.sort_by_key(|d| d.display_name().to_lowercase()),
+            SortMode::LastSeen => devices.sort_by_key(|d| std::cmp::Reverse(self.last_seen(d))),
+            SortMode::ConnectedFirst => devices.sort_by_key(|d| (!d.connected, !d.paired)),
+        }
+        devices
+    }
+
+    /// Whether anything hides devices from the list.
+    pub fn is_filtered(&self) -> bool {
+        self.type_filter.is_some()
+            || !self.search_query.is_empty()
+            || self.hide_unnamed
+            || self.only_known
+            || self.only_connected
+            || self.only_blocked
+    }
+
+    pub fn start_search(&mut self) {
+        self.searching = true;
+        self.status_message = "Search by name, alias or address (Enter - Keep, ESC - Clear)".to_string();
+    }
+
+    pub fn push_search_char(&mut self, c: char) {
+        let selected = self.selected_key();
+        self.search_query.push(c);
+        self.refilter(selected);
+    }
+
+    pub fn pop_search_char(&mut self) {
+        let selected = self.selected_key();
+        self.search_query.pop();
+        self.refilter(selected);
+    }
+
+    /// Stops typing but keeps the list filtered by what was typed.
+    pub fn finish_search(&mut self) {
+        self.searching = false;
+        self.status_message = if self.search_query.is_empty() {
+            "Showing all devices".to_string()
+        } else {
+            format!("Showing devices matching \"{}\"", self.search_query)
+        };
+    }
+
+    pub fn clear_search(&mut self) {
+        let selected = self.selected_key();
+        self.searching = false;
+        self.search_query.clear();
+        self.refilter(selected);
+        self.status_message = "Search cleared".to_string();
+    }
+
+    pub fn toggle_hide_unnamed(&mut self) {
+        let selected = self.selected_key();
+        self.hide_unnamed = !self.hide_unnamed;
+        self.refilter(selected);
+        self.status_message = if self.hide_unnamed {
+            "Hiding unnamed devices".to_string()
+        } else {
+            "Showing unnamed devices".to_string()
+        };
+    }
+
+    pub fn toggle_only_known(&mut self) {
+        let selected = self.selected_key();
+        self.only_known = !self.only_known;
+        self.refilter(selected);
+        self.status_message = if self.only_known {
+            "Showing only known devices".to_string()
+        } else {
+            "Showing known and new devices".to_string()
+        };
+    }
+
+    pub fn toggle_only_connected(&mut self) {
+        let selected = self.selected_key();
+        self.only_connected = !self.only_connected;
+        self.refilter(selected);
+        self.status_message = if self.only_connected {
+            "Showing only connected devices".to_string()
+        } else {
+            "Showing connected and disconnected devices".to_string()
+        };
+    }
+
+    pub fn toggle_only_blocked(&mut self) {
+        let selected = self.selected_key();
+        self.only_blocked = !self.only_blocked;
+        self.refilter(selected);
+        self.status_message = if self.only_blocked {
+            "Showing only blocked devices".to_string()
+        } else {
+            "Showing blocked and unblocked devices".to_string()
+        };
+    }
+
+    /// Keeps the cursor on the same device after a filter changed, or in
+    /// range when that device is now hidden.
+    fn refilter(&mut self, selected: Option<(String, String)>) {
+        let visible = self.visible_devices().len();
+        self.selected_index = self.selected_index.min(visible.saturating_sub(1));
+        self.reselect(selected);
+    }
+
+    pub fn cycle_sort_mode(&mut self) {
+        let selected = self.selected_key();
+        self.sort_mode = self.sort_mode.next();
+        self.reselect(selected);
+        self.status_message = match self.keymap.list.key(Action::CycleSortMode) {
+            Some(key) => format!("Sorted by {} ('{}' to change)", self.sort_mode.label(), key),
+            None => format!("Sorted by {}", self.sort_mode.label()),
+        };
+    }
+
+    /// Cycles the type filter through the types currently in the list, then
+    /// back to showing every device.
+    pub fn cycle_type_filter(&mut self) {
+        let mut types: Vec<DeviceType> = self.devices.iter().map(|d| d.device_type).collect();
+        types.sort();
+        types.dedup();
+
+        self.type_filter = match self.type_filter {
+            None => types.first().copied(),
+            Some(current) => types.into_iter().find(|kind| *kind > current),
+        };
+        self.selected_index = 0;
+        let next_key = self.keymap.list.key(Action::CycleTypeFilter);
+        self.status_message = match (self.type_filter, next_key) {
+            (Some(kind), Some(key)) => {
+                format!("Showing {} devices ('{}' for next type)", kind.label(), key)
             }
+            (Some(kind), None) => format!("Showing {} devices", kind.label()),
+            (None, _) => "Showing all devices".to_string(),
+        };
+    }
+
+    /// Selects a device, dropping the type filter if it would hide it.
+    pub fn select_device(&mut self, adapter: &str, address: &str) {
+        if !self.visible_devices().iter().any(|d| d.is(adapter, address)) {
+            self.type_filter = None;
+        }
+        if let Some(index) = self
+            .visible_devices()
+            .iter()
+            .position(|d| d.is(adapter, address))
+        {
+            self.selected_index = index;
         }
     }
 
-    pub fn pair_device(&mut self) {
-        if let Some(device) = self.devices.get_mut(self.selected_index) {
-            if !device.paired {
-                // TODO: Implement real Bluetooth pairing
-                device.paired = true;
-                device.trusted = true;
-                device.known = true;
-                self.status_message = format!("Paired with {}", device.display_name());
-            }
-        }
-    }
-
-    pub fn remove_device(&mut self) {
-        if !self.devices.is_empty() {
-            let device_name = self.devices[self.selected_index].display_name();
-            let was_known = self.devices[self.selected_index].known;
-            
-            // TODO: Implement real device removal from BlueZ
-            self.devices.remove(self.selected_index);
-            if self.selected_index >= self.devices.len() && !self.devices.is_empty() {
-                self.selected_index = self.devices.len() - 1;
-            }
-            
-            if was_known {
-                self.status_message = format!("Removed {} from known devices", device_name);
+    /// Selects the `index`th row of the table, e.g. on a click.
+    pub fn select_index(&mut self, index: usize) {
+        if index < self.visible_devices().len() {
+            self.selected_index = index;
+        }
+    }
+
+    /// Moves the selection a screenful down, stopping at the last device.
+    pub fn select_page_down(&mut self) {
+        let count = self.visible_devices().len();
+        self.selected_index = (self.selected_index + self.table_rows.max(1)).min(count.saturating_sub(1));
+    }
+
+    /// Moves the selection a screenful up, stopping at the first device.
+    pub fn select_page_up(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(self.table_rows.max(1));
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected_index = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected_index = self.visible_devices().len().saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.visible_devices().len();
+        if count > 0 {
+            self.selected_index = (self.selected_index + 1) % count;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let count = self.visible_devices().len();
+        if count > 0 {
+            self.selected_index = if self.selected_index == 0 {
+                count - 1
             } else {
-                self.status_message = format!("Removed {}", device_name);
+                self.selected_index - 1
+            };
+        }
+    }
+
+    /// The device the details view is open on, even when the filters now
+    /// hide it; otherwise the one under the cursor in the list.
+    pub fn get_selected_device(&self) -> Option<&BluetoothDevice> {
+        match &self.details_device {
+            Some((adapter, address)) => self.devices.iter().find(|d| d.is(adapter, address)),
+            None => self.visible_devices().get(self.selected_index).copied(),
+        }
+    }
+
+    pub async fn toggle_bluetooth(&mut self) {
+        self.set_bluetooth(!self.bluetooth_enabled).await;
+    }
+
+    /// Powers the adapter on or off. A soft rfkill block is not lifted
+    /// without asking: `unblock_prompt` is set for the UI to confirm first.
+    pub async fn set_bluetooth(&mut self, powered: bool) {
+        if powered {
+            self.refresh_rfkill();
+            match self.adapter_block_state() {
+                BlockState::HardBlocked => {
+                    self.report_error(
+                        "Bluetooth is hard-blocked by rfkill (hardware switch or firmware)".to_string(),
+                    );
+                    return;
+                }
+                BlockState::SoftBlocked => {
+                    self.unblock_prompt = true;
+                    self.status_message = "Bluetooth is soft-blocked by rfkill".to_string();
+                    return;
+                }
+                BlockState::Unblocked => {}
             }
         }
+        let Some((client, adapter)) = self.adapter_target() else { return };
+
+        let result = client.set_powered(&adapter, powered).await;
+        let done = if powered { "Bluetooth enabled" } else { "Bluetooth disabled" };
+        self.finish_adapter_change(result, done, "Failed to change adapter power").await;
     }
 
-    pub fn change_view(&mut self, view: View) {
-        self.current_view = view;
+    pub fn block_state(&self) -> BlockState {
+        BlockState::of(&self.rfkill_switches)
     }
 
-    pub fn get_device_count(&self) -> usize {
-        self.devices.len()
+    /// The switches of the selected adapter, so a blocked second adapter
+    /// does not stop this one.
+    pub fn adapter_switches(&self) -> Vec<RfkillSwitch> {
+        let name = self.selected_adapter.as_deref().unwrap_or_default();
+        rfkill::adapter_switches(&self.rfkill_switches, name)
     }
 
-    pub fn get_connected_count(&self) -> usize {
-        self.devices.iter().filter(|d| d.connected).count()
+    pub fn adapter_block_state(&self) -> BlockState {
+        BlockState::of(&self.adapter_switches())
     }
 
-    pub fn get_known_devices_count(&self) -> usize {
-        self.devices.iter().filter(|d| d.known).count()
+    /// Re-reads the rfkill switches and logs when the block changes.
+    pub fn refresh_rfkill(&mut self) {
+        let switches = match self.rfkill.switches() {
+            Ok(switches) => switches,
+            // Keep the last known state; the next poll tries again
+            Err(_) => return,
+        };
+        let previous = self.block_state();
+        self.rfkill_switches = switches;
+        let state = self.block_state();
+        if state != previous {
+            let level = if state == BlockState::Unblocked { LogLevel::Info } else { LogLevel::Warning };
+            self.log(level, format!("Bluetooth rfkill: {}", state.label().to_lowercase()));
+        }
     }
 
-    pub fn get_available_devices_count(&self) -> usize {
-        self.devices.iter().filter(|d| !d.known).count()
+    /// Lifts the soft block the user agreed to, then powers the adapter on.
+    pub async fn confirm_unblock(&mut self) {
+        self.unblock_prompt = false;
+        let name = self.selected_adapter.clone().unwrap_or_default();
+        if let Err(err) = self.rfkill.unblock(&name) {
+            self.report_error(format!("Failed to unblock Bluetooth: {}", err));
+            return;
+        }
+        self.refresh_rfkill();
+        let state = self.adapter_block_state();
+        if state != BlockState::Unblocked {
+            self.report_error(format!("Bluetooth is still {}", state.label().to_lowercase()));
+            return;
+        }
+        self.set_bluetooth(true).await;
+    }
+
+    pub fn cancel_unblock(&mut self) {
+        self.unblock_prompt = false;
+        self.status_message = "Bluetooth left blocked".to_string();
     }
-} 
\ No newline at end of file
+
+    pub async fn toggle_discoverable(&mut self) {
+        let Some((client, name)) = self.adapter_target() else { return };
+        let Some(adapter) = self.current_adapter() else { return };
+
+        let discoverable = !adapter.discoverable;
+        let result = client.set_discoverable(&name, discoverable).await;
+        let done = if discoverable { "Adapter is discoverable" } else { "Adapter is hidden" };
+        self.finish_adapter_change(result, done, "Failed to change discoverable mode").await;
+    }
+
+    pub async fn toggle_pairable(&mut self) {
+        let Some((client, name)) = self.adapter_target() else { return };
+        let Some(adapter) = self.current_adapter() else { return };
+
+        let pairable = !adapter.pairable;
+        let result = client.set_pairable(&name, pairable).await;
+        let done = if pairable { "Adapter is pairable" } else { "Adapter is not pairable" };
+        self.finish_adapter_change(result, done, "Failed to change pairable mode").await;
+    }
+
+    /// Moves the discoverable timeout up or down by one step (0 means never).
+    pub async fn adjust_discoverable_timeout(&mut self, increase: bool) {
+        let Some((client, name)) = self.adapter_target() else { return };
+        let Some(adapter) = self.current_adapter() else { return };
+
+        let timeout = if increase {
+            adapter.discoverable_timeout.saturating_add(DISCOVERABLE_TIMEOUT_STEP)
+        } else {
+            adapter.discoverable_timeout.saturating_sub(DISCOVERABLE_TIMEOUT_STEP)
+        };
+        let result = client.set_discoverable_timeout(&name, timeout).await;
+        let done = format!("Discoverable timeout set to {}", format_timeout(timeout));
+        self.finish_adapter_change(result, &done, "Failed to change discoverable timeout")
+            .await;
+    }
+
+    /// Reports an Adapter1 property write and re-reads the adapter, so the
+    /// settings show what BlueZ accepted rather than what was requested.
+    async fn finish_adapter_change(&mut self, result: BackendResult<()>, done: &str, failed: &str) {
+        match result {
+            Ok(()) => {
+                self.error_message = None;
+                self.status_message = done.to_string();
+            }
+            Err(err) => {
+                self.report_error(format!("{}: {}", failed, err));
+            }
+        }
+        self.refresh_adapters().await;
+    }
+
+    pub fn select_previous_filter_field(&mut self) {
+        self.filter_field = self.filter_field.previous();
+    }
+
+    pub fn select_next_filter_field(&mut self) {
+        self.filter_field = self.filter_field.next();
+    }
+
+    /// Steps the field under the cursor: cycles the transport, moves the
+    /// RSSI or pathloss limit, or flips duplicate data.
+    pub async fn change_filter_value(&mut self, up: bool) {
+        let mut filter = self.discovery_filter.clone();
+        match self.filter_field {
+            FilterField::Transport => {
+                filter.transport = if up { filter.transport.next() } else { filter.transport.previous() };
+            }
+            FilterField::Rssi => filter.step_rssi(up),
+            FilterField::Pathloss => filter.step_pathloss(up),
+            FilterField::DuplicateData => filter.duplicate_data = !filter.duplicate_data,
+            FilterField::Uuids => {
+                self.open_filter_editor(FilterField::Uuids);
+                return;
+            }
+        }
+        self.set_discovery_filter(filter).await;
+    }
+
+    /// Opens the editor for a typed field; other fields are stepped.
+    pub async fn edit_filter_field(&mut self) {
+        let field = self.filter_field;
+        if field.is_typed() {
+            self.open_filter_editor(field);
+        } else {
+            self.change_filter_value(true).await;
+        }
+    }
+
+    fn open_filter_editor(&mut self, field: FilterField) {
+        self.filter_input = self.discovery_filter.field_input(field);
+        self.filter_edit = Some(field);
+    }
+
+    pub fn cancel_filter_edit(&mut self) {
+        self.filter_edit = None;
+        self.filter_input.clear();
+    }
+
+    /// Sets the edited field from the typed text. Invalid text keeps the
+    /// editor open so it can be fixed.
+    pub async fn commit_filter_edit(&mut self) {
+        let Some(field) = self.filter_edit else { return };
+        let mut filter = self.discovery_filter.clone();
+        if let Err(err) = filter.set_field(field, &self.filter_input) {
+            self.error_message = Some(err);
+            return;
+        }
+        self.cancel_filter_edit();
+        self.set_discovery_filter(filter).await;
+    }
+
+    pub async fn reset_discovery_filter(&mut self) {
+        self.set_discovery_filter(DiscoveryFilter::default()).await;
+    }
+
+    /// Remembers `filter` for the next runs and, while scanning, hands it
+    /// to BlueZ, which applies it to the running discovery.
+    async fn set_discovery_filter(&mut self, filter: DiscoveryFilter) {
+        if filter == self.discovery_filter {
+            return;
+        }
+        self.discovery_filter = filter;
+        let summary = self.discovery_filter.summary();
+        self.log(LogLevel::Info, format!("Discovery filter: {}", summary));
+        self.error_message = None;
+        self.status_message = format!("Discovery filter: {}", summary);
+        if self.scanning {
+            if let Some((client, adapter)) = self.adapter_target() {
+                if let Err(err) = client.set_discovery_filter(&adapter, &self.discovery_filter).await {
+                    self.report_error(format!("Failed to set discovery filter: {}", err));
+                }
+            }
+        }
+        self.save_discovery_filter();
+    }
+
+    fn save_discovery_filter(&mut self) {
+        let Some(path) = &self.discovery_filter_path else { return };
+        if let Err(err) = self.discovery_filter.save(path) {
+            self.report_error(format!("Failed to save discovery filter: {}", err));
+        }
+    }
+
+    /// Trusts or untrusts the selected device (`Device1.Trusted`).
+    pub async fn toggle_trusted(&mut self) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+        let Some(client) = self.backend.clone() else {
+            self.report_error("BlueZ is not available".to_string());
+            return;
+        };
+
+        let trusted = !device.trusted;
+        let result = client
+            .set_device_trusted(&device.adapter, &device.address, trusted)
+            .await;
+        let (done, failed) = if trusted {
+            ("Trusted", "Failed to trust")
+        } else {
+            ("Untrusted", "Failed to untrust")
+        };
+        self.finish_device_change(&device, result, done, failed).await;
+    }
+
+    /// Blocks or unblocks the selected device (`Device1.Blocked`). BlueZ
+    /// disconnects a device when it is blocked.
+    pub async fn toggle_blocked(&mut self) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+        let Some(client) = self.backend.clone() else {
+            self.report_error("BlueZ is not available".to_string());
+            return;
+        };
+
+        let blocked = !device.blocked;
+        let result = client
+            .set_device_blocked(&device.adapter, &device.address, blocked)
+            .await;
+        let (done, failed) = if blocked {
+            ("Blocked", "Failed to block")
+        } else {
+            ("Unblocked", "Failed to unblock")
+        };
+        self.finish_device_change(&device, result, done, failed).await;
+    }
+
+    /// Reports a device property change and re-reads the device.
+    async fn finish_device_change(
+        &mut self,
+        device: &BluetoothDevice,
+        result: BackendResult<()>,
+        done: &str,
+        failed: &str,
+    ) {
+        let name = device.display_name();
+        match result {
+            Ok(()) => {
+                self.error_message = None;
+                self.status_message = format!("{} {}", done, name);
+                self.log(LogLevel::Info, self.status_message.clone());
+            }
+            Err(err) => {
+                self.report_error(format!("{} {}: {}", failed, name, err));
+                return;
+            }
+        }
+
+        let Some(client) = &self.backend else { return };
+        match client.device(&device.adapter, &device.address).await {
+            Ok(updated) => self.upsert_device(updated),
+            Err(err) => self.report_error(format!("Failed to refresh {}: {}", name, err)),
+        }
+    }
+
+    pub fn connect_device(&mut self) {
+        let Some(device) = self.get_selected_device() else { return };
+        let operation = if device.connected {
+            DeviceOperation::Disconnect
+        } else {
+            DeviceOperation::Connect
+        };
+        self.start_device_operation(operation);
+    }
+
+    pub fn select_next_profile(&mut self) {
+        let count = self.get_selected_device().map_or(0, |d| d.uuids.len());
+        if count > 0 {
+            self.profile_index = (self.profile_index + 1) % count;
+        }
+    }
+
+    pub fn select_previous_profile(&mut self) {
+        let count = self.get_selected_device().map_or(0, |d| d.uuids.len());
+        if count > 0 {
+            self.profile_index = (self.profile_index + count - 1) % count;
+        }
+    }
+
+    /// UUID under the cursor in the profile list of the details view.
+    pub fn selected_profile(&self) -> Option<&String> {
+        self.get_selected_device()?.uuids.get(self.profile_index)
+    }
+
+    pub fn connect_profile(&mut self) {
+        if let Some(uuid) = self.selected_profile().cloned() {
+            self.start_device_operation(DeviceOperation::ConnectProfile(uuid));
+        }
+    }
+
+    pub fn disconnect_profile(&mut self) {
+        if let Some(uuid) = self.selected_profile().cloned() {
+            self.start_device_operation(DeviceOperation::DisconnectProfile(uuid));
+        }
+    }
+
+    pub fn pair_device(&mut self) {
+        if self.get_selected_device().is_some_and(|d| !d.paired) {
+            self.start_device_operation(DeviceOperation::Pair);
+        }
+    }
+
+    pub fn start_device_operation(&mut self, operation: DeviceOperation) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+        let (Some(backend), Some(events)) = (&self.backend, &self.backend_events) else {
+            self.report_error("BlueZ is not available".to_string());
+            return;
+        };
+
+        self.error_message = None;
+        self.status_message = format!("{} {}...", operation.in_progress(), device.display_name());
+        spawn_device_operation(
+            Arc::clone(backend),
+            device.adapter.clone(),
+            device.address.clone(),
+            operation,
+            events.clone(),
+        );
+    }
+
+    pub async fn remove_device(&mut self) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+        let Some(client) = self.backend.clone() else {
+            self.report_error("BlueZ is not available".to_string());
+            return;
+        };
+
+        if let Err(err) = client.remove_device(&device.adapter, &device.address).await {
+            self.report_error(format!("Failed to remove {}: {}", device.display_name(), err));
+            return;
+        }
+
+        self.error_message = None;
+        self.forget_device(&device.adapter, &device.address);
+        if self.known_devices.forget(&device.adapter, &device.address) {
+            self.save_known_devices();
+        }
+        if device.known {
+            self.status_message = format!("Removed {} from known devices", device.display_name());
+        } else {
+            self.status_message = format!("Removed {}", device.display_name());
+        }
+        self.log(LogLevel::Info, self.status_message.clone());
+    }
+
+    /// Reports the outcome of a Device1 call and re-reads the device so the
+    /// list shows what BlueZ actually did.
+    async fn finish_device_operation(
+        &mut self,
+        adapter: &str,
+        address: &str,
+        operation: DeviceOperation,
+        result: BackendResult<()>,
+    ) {
+        let name = self.device_name(adapter, address);
+        let automatic = self
+            .auto_connecting
+            .remove(&(adapter.to_string(), address.to_string()));
+        match result {
+            Ok(()) => {
+                self.error_message = None;
+                self.status_message = if automatic {
+                    format!("Auto-connected {}", name)
+                } else {
+                    format!("{} {}", operation.done(), name)
+                };
+                match &operation {
+                    DeviceOperation::ConnectProfile(uuid) => {
+                        self.remember_preferred_profile(adapter, address, uuid);
+                    }
+                    DeviceOperation::Pair => self.trust_paired_device(adapter, address, &name).await,
+                    _ => {}
+                }
+            }
+            // Background retries only report in the status bar
+            Err(err) if automatic => {
+                self.status_message = format!("Auto-connect to {} failed: {}", name, err);
+                self.log(LogLevel::Warning, self.status_message.clone());
+            }
+            Err(err) => {
+                self.report_error(format!("{} {}: {}", operation.failed(), name, err));
+            }
+        }
+
+        let Some(client) = &self.backend else { return };
+        match client.device(adapter, address).await {
+            Ok(updated) => self.upsert_device(updated),
+            Err(err) => {
+                if self.error_message.is_none() {
+                    self.report_error(format!("Failed to refresh {}: {}", name, err));
+                }
+            }
+        }
+    }
+
+    /// Trusts a device once it is paired, so it can reconnect on its own
+    /// later. If that fails the device stays paired and the failure is
+    /// reported on its own.
+    async fn trust_paired_device(&mut self, adapter: &str, address: &str, name: &str) {
+        let Some(client) = self.backend.clone() else { return };
+        if let Err(err) = client.set_device_trusted(adapter, address, true).await {
+            self.report_error(format!("Paired with {}, but could not mark it trusted: {}", name, err));
+        }
+    }
+
+    /// Makes the profile the one auto-reconnect asks for on a known device.
+    fn remember_preferred_profile(&mut self, adapter: &str, address: &str, uuid: &str) {
+        let Some(device) = self.devices.iter().find(|d| d.is(adapter, address)).cloned() else {
+            return;
+        };
+        if device.known && self.known_devices.set_preferred_profile(&device, uuid) {
+            self.save_known_devices();
+        }
+    }
+
+    pub fn device_name(&self, adapter: &str, address: &str) -> String {
+        self.devices
+            .iter()
+            .find(|d| d.is(adapter, address))
+            .map(|d| d.display_name())
+            .unwrap_or_else(|| address.to_string())
+    }
+
+    /// Starts editing the selected device's alias, prefilled with the name shown.
+    pub fn edit_device_alias(&mut self) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+        self.alias_input = device.display_name();
+        self.alias_edit = Some(AliasTarget::Device {
+            adapter: device.adapter,
+            address: device.address,
+        });
+    }
+
+    /// Starts editing the alias of the adapter selected in Settings.
+    pub fn edit_adapter_alias(&mut self) {
+        let Some(adapter) = self.current_adapter().cloned() else { return };
+        self.alias_input = adapter.alias;
+        self.alias_edit = Some(AliasTarget::Adapter(adapter.name));
+    }
+
+    pub fn cancel_alias_edit(&mut self) {
+        self.alias_edit = None;
+        self.alias_input.clear();
+    }
+
+    /// Writes the typed alias to BlueZ. An empty alias resets the name.
+    pub async fn commit_alias_edit(&mut self) {
+        let Some(target) = self.alias_edit.take() else { return };
+        let alias = self.alias_input.trim().to_string();
+        self.alias_input.clear();
+        let Some(client) = self.backend.clone() else {
+            self.report_error("BlueZ is not available".to_string());
+            return;
+        };
+
+        match target {
+            AliasTarget::Adapter(name) => {
+                let result = client.set_adapter_alias(&name, &alias).await;
+                let done = if alias.is_empty() {
+                    format!("Reset the alias of {}", name)
+                } else {
+                    format!("Renamed {} to {}", name, alias)
+                };
+                self.finish_adapter_change(result, &done, "Failed to rename adapter").await;
+            }
+            AliasTarget::Device { adapter, address } => {
+                let name = self.device_name(&adapter, &address);
+                if let Err(err) = client.set_device_alias(&adapter, &address, &alias).await {
+                    self.report_error(format!("Failed to rename {}: {}", name, err));
+                    return;
+                }
+
+                self.error_message = None;
+                self.status_message = if alias.is_empty() {
+                    format!("Reset the alias of {}", name)
+                } else {
+                    format!("Renamed {} to {}", name, alias)
+                };
+                if let Some(device) = self.devices.iter().find(|d| d.is(&adapter, &address)).cloned() {
+                    if device.known {
+                        let alias = (!alias.is_empty()).then_some(alias);
+                        self.known_devices.set_alias(&device, alias);
+                        self.save_known_devices();
+                    }
+                }
+                match client.device(&adapter, &address).await {
+                    Ok(updated) => self.upsert_device(updated),
+                    Err(err) => {
+                        self.report_error(format!("Failed to refresh {}: {}", name, err));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Opens the GATT view for the selected device, which has to be
+    /// connected for BlueZ to have resolved its services.
+    pub async fn open_gatt_browser(&mut self) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+        let Some(client) = self.backend.clone() else {
+            self.report_error("BlueZ is not available".to_string());
+            return;
+        };
+        if !device.connected {
+            self.report_error(format!(
+                "Connect {} to browse its GATT services",
+                device.display_name()
+            ));
+            return;
+        }
+
+        match client.gatt_attributes(&device.adapter, &device.address).await {
+            Ok(attributes) => {
+                self.error_message = None;
+                self.status_message = gatt_summary(&attributes, &device.display_name(), &self.keymap);
+                self.gatt = Some(GattBrowser::new(device.adapter, device.address, attributes));
+                self.current_view = View::Gatt;
+            }
+            Err(err) => {
+                self.report_error(format!(
+                    "Failed to list GATT services of {}: {}",
+                    device.display_name(),
+                    err
+                ));
+            }
+        }
+    }
+
+    /// Leaves the GATT view, turning off the notifications it turned on.
+    pub async fn close_gatt_browser(&mut self) {
+        let Some(browser) = self.gatt.take() else { return };
+        if let Some(client) = self.backend.clone() {
+            for attribute in browser.attributes.iter().filter(|a| a.notifying) {
+                let _ = client.set_gatt_notify(&attribute.path, false).await;
+            }
+        }
+        self.current_view = View::DeviceDetails;
+    }
+
+    /// Lists the attributes again, e.g. once `ServicesResolved` came in.
+    pub async fn reload_gatt(&mut self) {
+        let Some(browser) = &self.gatt else { return };
+        let Some(client) = self.backend.clone() else { return };
+        let (adapter, address) = (browser.adapter.clone(), browser.address.clone());
+        let name = self.device_name(&adapter, &address);
+
+        match client.gatt_attributes(&adapter, &address).await {
+            Ok(attributes) => {
+                self.error_message = None;
+                self.status_message = gatt_summary(&attributes, &name, &self.keymap);
+                if let Some(browser) = &mut self.gatt {
+                    browser.replace_attributes(attributes);
+                }
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to list GATT services of {}: {}", name, err));
+            }
+        }
+    }
+
+    /// The backend and the attribute under the cursor of the GATT view.
+    fn gatt_target(&mut self) -> Option<(Arc<dyn BluetoothBackend>, GattAttribute)> {
+        let attribute = self.gatt.as_ref()?.selected_attribute()?.clone();
+        let Some(client) = self.backend.clone() else {
+            self.report_error("BlueZ is not available".to_string());
+            return None;
+        };
+        Some((client, attribute))
+    }
+
+    pub async fn read_gatt_value(&mut self) {
+        let Some((client, attribute)) = self.gatt_target() else { return };
+        let label = gatt_label(&attribute.uuid);
+        if !attribute.can_read() {
+            self.error_message = Some(format!("{} is not readable", label));
+            return;
+        }
+
+        match client.read_gatt_value(&attribute.path).await {
+            Ok(value) => {
+                self.error_message = None;
+                self.status_message = format!("Read {} ({} bytes)", label, value.len());
+                if let Some(attribute) = self.gatt.as_mut().and_then(|b| b.attribute_mut(&attribute.path)) {
+                    attribute.value = Some(value);
+                }
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to read {}: {}", label, err));
+            }
+        }
+    }
+
+    /// Starts typing a value for the selected attribute, as hex by default.
+    pub fn start_gatt_write(&mut self) {
+        let Some(browser) = &mut self.gatt else { return };
+        let Some(attribute) = browser.selected_attribute() else { return };
+        if !attribute.can_write() {
+            self.error_message = Some(format!("{} is not writable", gatt_label(&attribute.uuid)));
+            return;
+        }
+        browser.write_format = Some(Default::default());
+        browser.write_input.clear();
+    }
+
+    pub fn toggle_gatt_write_format(&mut self) {
+        if let Some(format) = self.gatt.as_mut().and_then(|b| b.write_format.as_mut()) {
+            *format = format.toggle();
+        }
+    }
+
+    pub fn cancel_gatt_write(&mut self) {
+        if let Some(browser) = &mut self.gatt {
+            browser.write_format = None;
+            browser.write_input.clear();
+        }
+    }
+
+    /// Writes the typed value; a value that does not parse keeps the editor open.
+    pub async fn commit_gatt_write(&mut self) {
+        let Some(browser) = &self.gatt else { return };
+        let Some(format) = browser.write_format else { return };
+        let value = match format.parse(&browser.write_input) {
+            Ok(value) => value,
+            Err(err) => {
+                self.error_message = Some(format!("Invalid {} value: {}", format.label(), err));
+                return;
+            }
+        };
+        self.cancel_gatt_write();
+        let Some((client, attribute)) = self.gatt_target() else { return };
+        let label = gatt_label(&attribute.uuid);
+
+        match client.write_gatt_value(&attribute.path, &value).await {
+            Ok(()) => {
+                self.error_message = None;
+                self.status_message = format!("Wrote {} bytes to {}", value.len(), label);
+                if let Some(attribute) = self.gatt.as_mut().and_then(|b| b.attribute_mut(&attribute.path)) {
+                    attribute.value = Some(value);
+                }
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to write {}: {}", label, err));
+            }
+        }
+    }
+
+    pub async fn toggle_gatt_notify(&mut self) {
+        let Some((client, attribute)) = self.gatt_target() else { return };
+        let label = gatt_label(&attribute.uuid);
+        if !attribute.can_notify() {
+            self.error_message = Some(format!("{} does not support notifications", label));
+            return;
+        }
+
+        let enabled = !attribute.notifying;
+        match client.set_gatt_notify(&attribute.path, enabled).await {
+            Ok(()) => {
+                self.error_message = None;
+                self.status_message = if enabled {
+                    format!("Notifications on for {}", label)
+                } else {
+                    format!("Notifications off for {}", label)
+                };
+                if let Some(attribute) = self.gatt.as_mut().and_then(|b| b.attribute_mut(&attribute.path)) {
+                    attribute.notifying = enabled;
+                }
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to change notifications of {}: {}", label, err));
+            }
+        }
+    }
+
+    pub fn clear_gatt_log(&mut self) {
+        if let Some(browser) = &mut self.gatt {
+            browser.notifications.clear();
+            self.status_message = "Notification log cleared".to_string();
+        }
+    }
+
+    /// Lets obexd offer incoming files. obexd is optional, so without it
+    /// only file transfers are unavailable.
+    pub async fn register_obex_agent(&mut self) {
+        if let (Some(backend), Some(events)) = (&self.backend, &self.backend_events) {
+            let _ = backend.register_obex_agent(events.clone()).await;
+        }
+    }
+
+    /// Opens the file picker to send a file to the selected device,
+    /// starting in the current directory.
+    pub fn open_file_picker(&mut self) {
+        if self.get_selected_device().is_none() {
+            return;
+        }
+        let start = std::env::current_dir()
+            .ok()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("/"));
+        match FilePicker::open(&start) {
+            Ok(picker) => self.file_picker = Some(picker),
+            Err(err) => {
+                self.report_error(format!("Failed to open {}: {}", start.display(), err));
+            }
+        }
+    }
+
+    pub fn close_file_picker(&mut self) {
+        self.file_picker = None;
+    }
+
+    /// Opens the directory under the cursor, or sends the file under it.
+    pub async fn file_picker_enter(&mut self) {
+        let Some(picker) = &mut self.file_picker else { return };
+        match picker.enter() {
+            Ok(Some(file)) => {
+                self.file_picker = None;
+                self.send_file(&file).await;
+            }
+            Ok(None) => self.error_message = None,
+            Err(err) => self.report_error(format!("Failed to open directory: {}", err)),
+        }
+    }
+
+    pub fn file_picker_parent(&mut self) {
+        let Some(picker) = &mut self.file_picker else { return };
+        if let Err(err) = picker.parent() {
+            self.report_error(format!("Failed to open directory: {}", err));
+        }
+    }
+
+    /// Pushes `file` to the selected device over OBEX.
+    pub async fn send_file(&mut self, file: &Path) {
+        let Some(device) = self.get_selected_device().cloned() else { return };
+        let (Some(backend), Some(events)) = (self.backend.clone(), self.backend_events.clone()) else {
+            self.report_error("BlueZ is not available".to_string());
+            return;
+        };
+
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match backend
+            .send_file(&device.adapter, &device.address, file, events)
+            .await
+        {
+            Ok(transfer) => {
+                self.error_message = None;
+                self.status_message = format!("Sending {} to {}...", name, device.display_name());
+                self.update_transfer(transfer);
+            }
+            Err(err) => {
+                self.report_error(format!(
+                    "Failed to send {} to {}: {}",
+                    name,
+                    device.display_name(),
+                    err
+                ));
+            }
+        }
+    }
+
+    /// Keeps the latest transfer of each device and reports when one ends.
+    fn update_transfer(&mut self, transfer: FileTransfer) {
+        let previous = self
+            .transfers
+            .iter()
+            .find(|t| t.id == transfer.id)
+            .map(|t| t.status);
+        if previous.is_some_and(|status| status.is_finished()) {
+            return;
+        }
+
+        let name = self.device_name(&transfer.adapter, &transfer.address);
+        match (transfer.status, transfer.direction) {
+            (TransferStatus::Complete, TransferDirection::Outgoing) => {
+                self.status_message = format!("Sent {} to {}", transfer.name, name);
+                self.log(LogLevel::Info, self.status_message.clone());
+            }
+            (TransferStatus::Complete, TransferDirection::Incoming) => {
+                self.status_message = match &transfer.filename {
+                    Some(filename) => format!("Received {} from {}", filename.display(), name),
+                    None => format!("Received {} from {}", transfer.name, name),
+                };
+                self.log(LogLevel::Info, self.status_message.clone());
+            }
+            (TransferStatus::Error, TransferDirection::Outgoing) => {
+                self.report_error(format!("Failed to send {} to {}", transfer.name, name));
+            }
+            (TransferStatus::Error, TransferDirection::Incoming) => {
+                self.report_error(format!("Failed to receive {} from {}", transfer.name, name));
+            }
+            (_, TransferDirection::Incoming) if previous.is_none() => {
+                self.status_message = format!("Receiving {} from {}...", transfer.name, name);
+            }
+            _ => {}
+        }
+
+        self.transfers
+            .retain(|t| t.id != transfer.id && !(t.adapter == transfer.adapter && t.address == transfer.address));
+        self.transfers.push(transfer);
+    }
+
+    /// The latest file transfer with `device`, if any.
+    pub fn transfer_for(&self, device: &BluetoothDevice) -> Option<&FileTransfer> {
+        self.transfers
+            .iter()
+            .find(|t| device.is(&t.adapter, &t.address))
+    }
+
+    /// Shows the request as a dialog on top of whatever view is open, or
+    /// queues it while another one is shown; the device is looked up so the
+    /// dialog can name it.
+    async fn show_agent_request(&mut self, request: AgentRequest) {
+        if !self.devices.iter().any(|d| d.is(&request.adapter, &request.address)) {
+            if let Some(client) = &self.backend {
+                if let Ok(device) = client.device(&request.adapter, &request.address).await {
+                    self.upsert_device(device);
+                }
+            }
+        }
+
+        // DisplayPasskey comes again each time a digit is typed on the remote
+        // keyboard; it updates its dialog instead of queueing another one.
+        let pending = self
+            .agent_request
+            .iter_mut()
+            .chain(self.agent_queue.iter_mut())
+            .find(|pending| pending.is_updated_by(&request));
+        if let Some(pending) = pending {
+            *pending = request;
+        } else if self.agent_request.is_some() {
+            self.agent_queue.push_back(request);
+        } else {
+            self.agent_input.clear();
+            self.agent_request = Some(request);
+        }
+    }
+
+    /// Moves on to the oldest queued request once the dialog is closed.
+    fn show_next_agent_request(&mut self) {
+        self.agent_input.clear();
+        self.agent_request = self.agent_queue.pop_front();
+    }
+
+    /// Drops the request BlueZ or obexd gave up on, shown or queued.
+    fn cancel_agent_request(&mut self, id: u64) {
+        self.agent_queue.retain(|request| request.id != id);
+        let Some(request) = self.agent_request.take_if(|request| request.id == id) else {
+            return;
+        };
+        self.show_next_agent_request();
+        self.status_message = if matches!(request.prompt, AgentPrompt::AuthorizePush { .. }) {
+            "Incoming file canceled".to_string()
+        } else {
+            "Pairing request canceled".to_string()
+        };
+        self.log(LogLevel::Info, self.status_message.clone());
+    }
+
+    pub fn accept_agent_request(&mut self) {
+        let Some(request) = self.agent_request.take() else { return };
+
+        let reply = match request.prompt {
+            AgentPrompt::RequestPinCode => {
+                let pin = self.agent_input.trim().to_string();
+                if pin.is_empty() || pin.len() > 16 {
+                    self.error_message = Some("PIN code must be 1-16 characters".to_string());
+                    self.agent_request = Some(request);
+                    return;
+                }
+                AgentReply::PinCode(pin)
+            }
+            AgentPrompt::RequestPasskey => match self.agent_input.trim().parse::<u32>() {
+                Ok(passkey) if passkey <= 999_999 => AgentReply::Passkey(passkey),
+                _ => {
+                    self.error_message = Some("Passkey must be a number from 0 to 999999".to_string());
+                    self.agent_request = Some(request);
+                    return;
+                }
+            },
+            _ => AgentReply::Accept,
+        };
+
+        self.error_message = None;
+        request.respond(reply);
+        self.show_next_agent_request();
+    }
+
+    pub fn reject_agent_request(&mut self) {
+        if let Some(request) = self.agent_request.take() {
+            let display_only = request.prompt.is_display_only();
+            let push = matches!(request.prompt, AgentPrompt::AuthorizePush { .. });
+            request.respond(AgentReply::Reject);
+            self.show_next_agent_request();
+            if push {
+                self.status_message = "Incoming file rejected".to_string();
+            } else if !display_only {
+                self.status_message = "Pairing request rejected".to_string();
+            } else {
+                return;
+            }
+            self.log(LogLevel::Info, self.status_message.clone());
+        }
+    }
+
+    /// Moves the log cursor by `delta` entries. Reaching the newest entry
+    /// follows new events again.
+    pub fn scroll_log(&mut self, delta: isize) {
+        let Some(last) = self.event_log.len().checked_sub(1) else { return };
+        let index = self
+            .log_index
+            .unwrap_or(last)
+            .saturating_add_signed(delta)
+            .min(last);
+        self.log_index = (index < last).then_some(index);
+    }
+
+    pub fn scroll_log_page(&mut self, down: bool) {
+        let page = self.log_rows.max(1) as isize;
+        self.scroll_log(if down { page } else { -page });
+    }
+
+    pub fn scroll_log_to_oldest(&mut self) {
+        self.scroll_log(-(self.event_log.len() as isize));
+    }
+
+    pub fn follow_log(&mut self) {
+        self.log_index = None;
+    }
+
+    pub fn clear_event_log(&mut self) {
+        self.event_log.clear();
+        self.log_index = None;
+        self.status_message = "Event log cleared".to_string();
+    }
+
+    /// Writes the log to a timestamped file in the current directory.
+    pub fn export_event_log(&mut self) {
+        let file = format!("bluetooth-manager-{}.log", UtcTime::at(unix_now()).compact());
+        let path = std::env::current_dir()
+            .map(|dir| dir.join(&file))
+            .unwrap_or_else(|_| PathBuf::from(&file));
+        match self.event_log.export(&path) {
+            Ok(()) => {
+                self.error_message = None;
+                self.status_message = format!(
+                    "Exported {} events to {}",
+                    self.event_log.len(),
+                    path.display()
+                );
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to export the log to {}: {}", path.display(), err));
+            }
+        }
+    }
+
+    /// Connects to the desktop notification server. Without one the log
+    /// still works; notifications are turned off.
+    pub async fn connect_notifier(&mut self) {
+        if self.notifier.is_some() {
+            return;
+        }
+        match DesktopNotifier::connect().await {
+            Ok(notifier) => self.notifier = Some(notifier),
+            Err(err) => {
+                self.desktop_notifications = false;
+                self.report_error(format!("Desktop notifications unavailable: {}", err));
+            }
+        }
+    }
+
+    pub async fn toggle_desktop_notifications(&mut self) {
+        self.desktop_notifications = !self.desktop_notifications;
+        if self.desktop_notifications {
+            self.connect_notifier().await;
+        }
+        if self.desktop_notifications {
+            self.error_message = None;
+            self.status_message = format!(
+                "Desktop notifications on for {} events and above",
+                self.notify_level.label()
+            );
+        } else if self.notifier.is_some() {
+            self.status_message = "Desktop notifications off".to_string();
+        }
+    }
+
+    /// Called by the event loop at a fixed rate to drive animations.
+    pub fn on_tick(&mut self) {
+        self.tick_count = self.tick_count.wrapping_add(1);
+        if self.tick_count.is_multiple_of(RFKILL_POLL_TICKS) {
+            self.refresh_rfkill();
+        }
+    }
+
+    pub fn spinner(&self) -> char {
+        SPINNER_FRAMES[(self.tick_count % SPINNER_FRAMES.len() as u64) as usize]
+    }
+
+    /// Uses the key bindings, colours and log settings of `config`.
+    pub fn apply_config(&mut self, config: Config) {
+        self.status_message = ready_message(&config.keymap);
+        self.keymap = config.keymap;
+        self.theme = config.theme;
+        self.event_log.set_capacity(config.log.size);
+        self.desktop_notifications = config.log.notifications;
+        self.notify_level = config.log.notify_level;
+    }
+
+    /// Opening the details pins the device under the cursor until the
+    /// details are left for another view.
+    pub fn change_view(&mut self, view: View) {
+        match view {
+            View::DeviceDetails => {
+                self.profile_index = 0;
+                self.details_device = self.selected_key();
+            }
+            View::Gatt => {}
+            _ => {
+                let pinned = self.details_device.take();
+                self.reselect(pinned);
+            }
+        }
+        self.current_view = view;
+    }
+
+    // Counts cover the devices the active filters leave visible.
+
+    pub fn get_device_count(&self) -> usize {
+        self.visible_devices().len()
+    }
+
+    pub fn get_connected_count(&self) -> usize {
+        self.visible_devices()
+            .iter()
+            .filter(|d| d.connected)
+            .count()
+    }
+
+    pub fn get_known_devices_count(&self) -> usize {
+        self.visible_devices().iter().filter(|d| d.known).count()
+    }
+
+    pub fn get_available_devices_count(&self) -> usize {
+        self.visible_devices().iter().filter(|d| !d.known).count()
+    }
+}
+
+/// "5 GATT services on X", or a hint when none are resolved yet.
+fn gatt_summary(attributes: &[GattAttribute], device_name: &str, keymap: &Keymap) -> String {
+    let services = attributes
+        .iter()
+        .filter(|a| a.kind == GattKind::Service)
+        .count();
+    if services > 0 {
+        return format!("{} GATT services on {}", services, device_name);
+    }
+    let hint = format!("No GATT services resolved on {} yet", device_name);
+    match keymap.gatt.key(Action::Reload) {
+        Some(key) => format!("{} ('{}' to reload)", hint, key),
+        None => hint,
+    }
+}
+
+/// Initial status line, pointing at the key that starts a scan.
+fn ready_message(keymap: &Keymap) -> String {
+    match keymap.list.key(Action::ToggleScan) {
+        Some(key) => format!("Ready - Press '{}' to start scanning", key),
+        None => "Ready".to_string(),
+    }
+}
+
+pub fn format_timeout(seconds: u32) -> String {
+    if seconds == 0 {
+        "never".to_string()
+    } else if seconds.is_multiple_of(60) {
+        format!("{} min", seconds / 60)
+    } else {
+        format!("{} s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulated::SimulatedBackend;
+
+    const HEADPHONES: &str = "00:1B:66:A1:B2:C3";
+    const SPEAKER: &str = "11:22:33:44:55:66";
+    const KEYBOARD: &str = "AA:BB:CC:DD:EE:FF";
+    const PHONE: &str = "7C:96:D2:10:4E:8A";
+    const MOUSE: &str = "C8:3A:1F:77:02:B5";
+    const UNNAMED: &str = "E4:5F:01:3C:9D:20";
+
+    async fn demo_app() -> (App, UnboundedReceiver<BluezEvent>) {
+        let mut app = App::new();
+        let events = app.attach_backend(Arc::new(SimulatedBackend::demo())).await;
+        (app, events)
+    }
+
+    /// Feeds the backend's events to the app, as the main loop does, until
+    /// `done` holds. Time is paused, so the script plays back instantly.
+    async fn run_until(
+        app: &mut App,
+        events: &mut UnboundedReceiver<BluezEvent>,
+        done: impl Fn(&App) -> bool,
+    ) {
+        let waiting = async {
+            while !done(app) {
+                let event = events.recv().await.expect("backend stopped sending events");
+                app.handle_bluez_event(event).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(60), waiting)
+            .await
+            .expect("timed out waiting for the simulated backend");
+    }
+
+    fn has_device(app: &App, address: &str) -> bool {
+        app.devices.iter().any(|d| d.is("hci0", address))
+    }
+
+    fn device<'a>(app: &'a App, address: &str) -> &'a BluetoothDevice {
+        app.devices
+            .iter()
+            .find(|d| d.is("hci0", address))
+            .expect("device not in the list")
+    }
+
+    fn visible_addresses(app: &App) -> Vec<&str> {
+        app.visible_devices()
+            .iter()
+            .map(|d| d.address.as_str())
+            .collect()
+    }
+
+    /// Scans until the demo script has found the phone, the keyboard's
+    /// signal got stronger and the mouse appeared.
+    async fn scanned_demo_app() -> (App, UnboundedReceiver<BluezEvent>) {
+        let (mut app, mut events) = demo_app().await;
+        app.toggle_scanning().await;
+        run_until(&mut app, &mut events, |app| has_device(app, MOUSE)).await;
+        (app, events)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn attaching_loads_adapters_and_devices() {
+        let (app, _events) = demo_app().await;
+
+        let adapters: Vec<&str> = app.adapters.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(adapters, ["hci0", "hci1"]);
+        assert_eq!(app.selected_adapter.as_deref(), Some("hci0"));
+        assert!(app.bluetooth_enabled);
+        assert!(!app.scanning);
+        assert_eq!(visible_addresses(&app), [HEADPHONES]);
+        assert!(device(&app, HEADPHONES).connected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scanning_adds_devices_in_discovery_order() {
+        let (app, _events) = scanned_demo_app().await;
+
+        assert!(app.scanning);
+        assert_eq!(
+            visible_addresses(&app),
+            [HEADPHONES, SPEAKER, KEYBOARD, PHONE, MOUSE]
+        );
+        assert_eq!(app.status_message, "Found MX Master 3");
+        assert_eq!(device(&app, KEYBOARD).rssi, Some(-41));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sort_modes_reorder_the_list_and_keep_the_selection() {
+        let (mut app, _events) = scanned_demo_app().await;
+        app.select_device("hci0", PHONE);
+
+        app.cycle_sort_mode();
+        assert_eq!(app.sort_mode, SortMode::Rssi);
+        assert_eq!(
+            visible_addresses(&app),
+            [KEYBOARD, SPEAKER, MOUSE, PHONE, HEADPHONES]
+        );
+        assert_eq!(app.get_selected_device().unwrap().address, PHONE);
+
+        app.cycle_sort_mode();
+        assert_eq!(app.sort_mode, SortMode::Name);
+        assert_eq!(
+            visible_addresses(&app),
+            [SPEAKER, KEYBOARD, MOUSE, PHONE, HEADPHONES]
+        );
+        assert_eq!(app.get_selected_device().unwrap().address, PHONE);
+
+        app.cycle_sort_mode();
+        app.cycle_sort_mode();
+        assert_eq!(app.sort_mode, SortMode::ConnectedFirst);
+        assert_eq!(visible_addresses(&app)[0], HEADPHONES);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn status_hints_name_the_configured_keys() {
+        let (mut app, _events) = scanned_demo_app().await;
+        app.cycle_sort_mode();
+        assert_eq!(app.status_message, "Sorted by signal strength ('o' to change)");
+
+        let overrides = HashMap::from([(
+            "list".to_string(),
+            HashMap::from([
+                ("sort".to_string(), vec!["F6".to_string()]),
+                ("type-filter".to_string(), vec![]),
+            ]),
+        )]);
+        app.keymap = Keymap::with_overrides(&overrides).unwrap();
+        app.cycle_sort_mode();
+        assert_eq!(app.status_message, "Sorted by name ('F6' to change)");
+        app.cycle_type_filter();
+        assert_eq!(app.status_message, "Showing Phone devices");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn search_and_toggles_filter_the_list() {
+        let (mut app, _events) = scanned_demo_app().await;
+
+        app.start_search();
+        for c in "mx".chars() {
+            app.push_search_char(c);
+        }
+        app.finish_search();
+        assert_eq!(visible_addresses(&app), [KEYBOARD, MOUSE]);
+        assert!(app.is_filtered());
+
+        app.clear_search();
+        app.toggle_only_connected();
+        assert_eq!(visible_addresses(&app), [HEADPHONES]);
+
+        app.toggle_only_connected();
+        app.toggle_only_known();
+        assert_eq!(visible_addresses(&app), [HEADPHONES, SPEAKER]);
+
+        app.toggle_only_known();
+        app.cycle_type_filter();
+        assert_eq!(app.type_filter, Some(DeviceType::Phone));
+        assert_eq!(visible_addresses(&app), [PHONE]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn selection_moves_within_the_visible_devices() {
+        let (mut app, _events) = scanned_demo_app().await;
+
+        app.select_last();
+        assert_eq!(app.get_selected_device().unwrap().address, MOUSE);
+        app.select_previous();
+        assert_eq!(app.get_selected_device().unwrap().address, PHONE);
+        app.select_first();
+        assert_eq!(app.get_selected_device().unwrap().address, HEADPHONES);
+        // Both ends wrap around.
+        app.select_previous();
+        assert_eq!(app.get_selected_device().unwrap().address, MOUSE);
+        app.select_next();
+        assert_eq!(app.get_selected_device().unwrap().address, HEADPHONES);
+
+        // Selecting a device hidden by the type filter drops the filter.
+        app.cycle_type_filter();
+        app.select_device("hci0", KEYBOARD);
+        assert_eq!(app.type_filter, None);
+        assert_eq!(app.get_selected_device().unwrap().address, KEYBOARD);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pairing_asks_the_agent_and_marks_the_device_paired() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        app.register_agent().await;
+        app.select_device("hci0", MOUSE);
+
+        app.pair_device();
+        assert_eq!(app.status_message, "Pairing with MX Master 3...");
+        run_until(&mut app, &mut events, |app| app.agent_request.is_some()).await;
+        let request = app.agent_request.as_ref().unwrap();
+        assert_eq!(request.address, MOUSE);
+        assert!(matches!(request.prompt, AgentPrompt::RequestConfirmation(123_456)));
+        // The dialog opens over the list instead of switching views.
+        assert_eq!(app.current_view, View::DeviceList);
+
+        app.accept_agent_request();
+        run_until(&mut app, &mut events, |app| device(app, MOUSE).paired).await;
+        assert!(device(&app, MOUSE).known);
+        run_until(&mut app, &mut events, |app| {
+            app.status_message == "Paired with MX Master 3"
+        })
+        .await;
+        assert_eq!(app.error_message, None);
+        assert!(device(&app, MOUSE).trusted);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_trust_after_pairing_is_reported_apart() {
+        let mut app = App::new();
+        let backend = SimulatedBackend::demo()
+            .with_trust_failure(MOUSE, "org.bluez.Error.Failed: Operation failed");
+        let mut events = app.attach_backend(Arc::new(backend)).await;
+        app.register_agent().await;
+        app.toggle_scanning().await;
+        run_until(&mut app, &mut events, |app| has_device(app, MOUSE)).await;
+        app.select_device("hci0", MOUSE);
+
+        app.pair_device();
+        run_until(&mut app, &mut events, |app| app.agent_request.is_some()).await;
+        app.accept_agent_request();
+        run_until(&mut app, &mut events, |app| app.error_message.is_some()).await;
+
+        assert_eq!(
+            app.error_message.as_deref(),
+            Some(
+                "Paired with MX Master 3, but could not mark it trusted: \
+                 org.bluez.Error.Failed: Operation failed"
+            )
+        );
+        assert!(device(&app, MOUSE).paired);
+        assert!(!device(&app, MOUSE).trusted);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rejecting_the_pairing_request_reports_the_error() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        app.register_agent().await;
+        app.select_device("hci0", MOUSE);
+
+        app.pair_device();
+        run_until(&mut app, &mut events, |app| app.agent_request.is_some()).await;
+        app.reject_agent_request();
+        run_until(&mut app, &mut events, |app| app.error_message.is_some()).await;
+
+        let error = app.error_message.as_deref().unwrap();
+        assert!(error.starts_with("Failed to pair with MX Master 3"), "{}", error);
+        assert!(!device(&app, MOUSE).paired);
+    }
+
+    fn agent_request(address: &str, prompt: AgentPrompt) -> AgentRequest {
+        AgentRequest::notice("hci0".to_string(), address.to_string(), prompt)
+    }
+
+    #[tokio::test]
+    async fn agent_requests_wait_their_turn() {
+        let mut app = App::new();
+        let (first, first_reply) = AgentRequest::new(
+            "hci0".to_string(),
+            MOUSE.to_string(),
+            AgentPrompt::RequestPasskey,
+        );
+        let (push, push_reply) = AgentRequest::new(
+            "hci0".to_string(),
+            PHONE.to_string(),
+            AgentPrompt::AuthorizePush { name: "photo.jpg".to_string(), size: Some(1024) },
+        );
+        app.handle_bluez_event(BluezEvent::AgentRequest(first)).await;
+        app.handle_bluez_event(BluezEvent::AgentRequest(push)).await;
+        assert_eq!(app.agent_request.as_ref().unwrap().address, MOUSE);
+        assert_eq!(app.agent_queue.len(), 1);
+
+        app.agent_input = "123456".to_string();
+        app.accept_agent_request();
+        assert!(matches!(first_reply.await, Ok(AgentReply::Passkey(123_456))));
+        // The next one opens with an empty input.
+        assert_eq!(app.agent_request.as_ref().unwrap().address, PHONE);
+        assert_eq!(app.agent_input, "");
+        assert!(app.agent_queue.is_empty());
+
+        app.reject_agent_request();
+        assert!(matches!(push_reply.await, Ok(AgentReply::Reject)));
+        assert!(app.agent_request.is_none());
+    }
+
+    #[tokio::test]
+    async fn display_passkey_updates_its_own_dialog() {
+        let mut app = App::new();
+        let passkey = |entered| AgentPrompt::DisplayPasskey { passkey: 123_456, entered };
+        app.handle_bluez_event(BluezEvent::AgentRequest(agent_request(KEYBOARD, passkey(0))))
+            .await;
+        app.handle_bluez_event(BluezEvent::AgentRequest(agent_request(
+            MOUSE,
+            AgentPrompt::RequestAuthorization,
+        )))
+        .await;
+        app.handle_bluez_event(BluezEvent::AgentRequest(agent_request(KEYBOARD, passkey(3))))
+            .await;
+
+        assert_eq!(app.agent_request.as_ref().unwrap().prompt, passkey(3));
+        assert_eq!(app.agent_queue.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_cancel_closes_only_its_own_request() {
+        let mut app = App::new();
+        let pairing = agent_request(MOUSE, AgentPrompt::RequestAuthorization);
+        let push = agent_request(
+            PHONE,
+            AgentPrompt::AuthorizePush { name: "photo.jpg".to_string(), size: None },
+        );
+        let queued = agent_request(KEYBOARD, AgentPrompt::RequestAuthorization);
+        let (pairing_id, push_id, queued_id) = (pairing.id, push.id, queued.id);
+        for request in [pairing, push, queued] {
+            app.handle_bluez_event(BluezEvent::AgentRequest(request)).await;
+        }
+
+        // A queued request goes quietly; the dialog stays.
+        app.handle_bluez_event(BluezEvent::AgentCanceled(queued_id)).await;
+        assert_eq!(app.agent_request.as_ref().unwrap().id, pairing_id);
+        assert_eq!(app.agent_queue.len(), 1);
+
+        app.handle_bluez_event(BluezEvent::AgentCanceled(pairing_id)).await;
+        assert_eq!(app.status_message, "Pairing request canceled");
+        assert_eq!(app.agent_request.as_ref().unwrap().id, push_id);
+
+        // Canceling something already answered changes nothing.
+        app.handle_bluez_event(BluezEvent::AgentCanceled(pairing_id)).await;
+        assert_eq!(app.agent_request.as_ref().unwrap().id, push_id);
+
+        app.handle_bluez_event(BluezEvent::AgentCanceled(push_id)).await;
+        assert_eq!(app.status_message, "Incoming file canceled");
+        assert!(app.agent_request.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connecting_and_disconnecting_update_the_device() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        app.select_device("hci0", SPEAKER);
+
+        app.connect_device();
+        assert_eq!(app.status_message, "Connecting to JBL Flip 5...");
+        run_until(&mut app, &mut events, |app| device(app, SPEAKER).connected).await;
+        run_until(&mut app, &mut events, |app| {
+            app.status_message == "Connected to JBL Flip 5"
+        })
+        .await;
+        assert_eq!(device(&app, SPEAKER).active_profiles.len(), 1);
+
+        app.connect_device();
+        run_until(&mut app, &mut events, |app| !device(app, SPEAKER).connected).await;
+        assert!(device(&app, SPEAKER).active_profiles.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_connection_is_reported() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        app.select_device("hci0", PHONE);
+
+        app.connect_device();
+        run_until(&mut app, &mut events, |app| app.error_message.is_some()).await;
+
+        assert_eq!(
+            app.error_message.as_deref(),
+            Some(
+                "Failed to connect to Pixel 7: \
+                 org.bluez.Error.Failed: br-connection-profile-unavailable"
+            )
+        );
+        assert!(!device(&app, PHONE).connected);
+        assert_eq!(app.event_log.entries().back().unwrap().level, LogLevel::Error);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn removing_a_device_drops_it_from_the_list() {
+        let (mut app, _events) = scanned_demo_app().await;
+        app.select_device("hci0", PHONE);
+
+        app.remove_device().await;
+        assert_eq!(app.status_message, "Removed Pixel 7");
+        assert!(!has_device(&app, PHONE));
+        assert_eq!(app.get_selected_device().unwrap().address, MOUSE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn details_return_to_the_list_when_their_device_goes_away() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        run_until(&mut app, &mut events, |app| has_device(app, UNNAMED)).await;
+        app.select_device("hci0", UNNAMED);
+        app.change_view(View::DeviceDetails);
+        app.open_file_picker();
+        app.edit_device_alias();
+        assert!(app.file_picker.is_some());
+
+        run_until(&mut app, &mut events, |app| !has_device(app, UNNAMED)).await;
+        assert_eq!(app.current_view, View::DeviceList);
+        assert_eq!(app.details_device, None);
+        assert!(app.file_picker.is_none());
+        assert_eq!(app.alias_edit, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn details_stay_on_a_device_the_filters_now_hide() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        app.toggle_only_connected();
+        app.change_view(View::DeviceDetails);
+        assert_eq!(app.get_selected_device().unwrap().address, HEADPHONES);
+
+        app.connect_device();
+        run_until(&mut app, &mut events, |app| !device(app, HEADPHONES).connected).await;
+        assert!(app.visible_devices().is_empty());
+        assert_eq!(app.current_view, View::DeviceDetails);
+        assert_eq!(app.get_selected_device().unwrap().address, HEADPHONES);
+
+        // Actions still go to the headphones, not to whatever row is at the cursor.
+        app.toggle_only_connected();
+        app.select_device("hci0", PHONE);
+        app.connect_device();
+        assert_eq!(app.status_message, "Connecting to WH-1000XM4...");
+
+        app.change_view(View::DeviceList);
+        assert_eq!(app.get_selected_device().unwrap().address, HEADPHONES);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn auto_connect_devices_reconnect_when_found() {
+        let mut app = App::new();
+        app.known_devices = SimulatedBackend::demo_known_devices();
+        let mut events = app.attach_backend(Arc::new(SimulatedBackend::demo())).await;
+
+        app.toggle_scanning().await;
+        run_until(&mut app, &mut events, |app| {
+            has_device(app, SPEAKER) && device(app, SPEAKER).connected
+        })
+        .await;
+        run_until(&mut app, &mut events, |app| {
+            app.status_message == "Auto-connected JBL Flip 5"
+        })
+        .await;
+
+        // With the preferred profile, A2DP, rather than a plain Connect.
+        assert_eq!(
+            device(&app, SPEAKER).active_profiles,
+            ["0000110b-0000-1000-8000-00805f9b34fb"]
+        );
+        assert!(app.auto_connecting.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn auto_connect_waits_for_the_adapter_and_the_setting() {
+        let mut app = App::new();
+        app.known_devices = SimulatedBackend::demo_known_devices();
+        app.auto_reconnect = false;
+        let mut events = app.attach_backend(Arc::new(SimulatedBackend::demo())).await;
+
+        app.toggle_scanning().await;
+        run_until(&mut app, &mut events, |app| has_device(app, MOUSE)).await;
+        assert!(!device(&app, SPEAKER).connected);
+        assert!(app.auto_connecting.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn signal_history_grows_with_each_new_reading() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        run_until(&mut app, &mut events, |app| {
+            device(app, SPEAKER).rssi == Some(-44)
+        })
+        .await;
+
+        let history = app.rssi_history(device(&app, SPEAKER)).unwrap();
+        assert_eq!(history.iter().copied().collect::<Vec<_>>(), [-62, -55, -49, -44]);
+        // The headphones are connected but never reported a signal.
+        assert!(app.rssi_history(device(&app, HEADPHONES)).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn low_battery_is_warned_about_when_crossing_the_threshold() {
+        let (mut app, mut events) = scanned_demo_app().await;
+        run_until(&mut app, &mut events, |app| {
+            device(app, HEADPHONES).battery == Some(18)
+        })
+        .await;
+
+        let warnings: Vec<&str> = app
+            .event_log
+            .entries()
+            .iter()
+            .filter(|entry| entry.level == LogLevel::Warning)
+            .map(|entry| entry.message.as_str())
+            .collect();
+        assert_eq!(warnings, ["Low battery: WH-1000XM4 is at 18%"]);
+    }
+
+    #[test]
+    fn log_scrolling_stays_within_the_entries() {
+        let mut app = App::new();
+        app.scroll_log(-1);
+        assert_eq!(app.log_index, None);
+
+        for n in 0..5 {
+            app.log(LogLevel::Info, format!("event {}", n));
+        }
+        app.log_rows = 2;
+        app.scroll_log(-1);
+        assert_eq!(app.log_index, Some(3));
+        app.scroll_log_page(false);
+        assert_eq!(app.log_index, Some(1));
+        app.scroll_log(-10);
+        assert_eq!(app.log_index, Some(0));
+        app.scroll_log_page(true);
+        assert_eq!(app.log_index, Some(2));
+        // Reaching the newest entry, or going past it, follows again.
+        app.scroll_log(10);
+        assert_eq!(app.log_index, None);
+
+        app.scroll_log_to_oldest();
+        assert_eq!(app.log_index, Some(0));
+        app.scroll_log(1);
+        assert_eq!(app.log_index, Some(1));
+        // The cursor stays on its entry when a new one drops the oldest.
+        app.event_log.set_capacity(5);
+        app.log(LogLevel::Info, "event 5");
+        assert_eq!(app.log_index, Some(0));
+        assert_eq!(app.event_log.entries()[0].message, "event 1");
+
+        app.event_log.set_capacity(1);
+        app.scroll_log(1);
+        assert_eq!(app.log_index, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn switching_adapter_stops_the_scan() {
+        let (mut app, mut events) = scanned_demo_app().await;
+
+        app.select_next_adapter().await;
+        assert_eq!(app.selected_adapter.as_deref(), Some("hci1"));
+        assert!(!app.scanning);
+        assert!(!app.bluetooth_enabled);
+        assert!(app.status_message.starts_with("Scan stopped. Using adapter hci1"));
+        run_until(&mut app, &mut events, |app| {
+            !app.adapters.iter().any(|a| a.discovering)
+        })
+        .await;
+    }
+
+    #[test]
+    fn timeouts_are_shown_in_minutes_when_whole() {
+        assert_eq!(format_timeout(0), "never");
+        assert_eq!(format_timeout(180), "3 min");
+        assert_eq!(format_timeout(90), "90 s");
+    }
+}
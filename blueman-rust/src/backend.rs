@@ -0,0 +1,137 @@
+use crate::app::{BluetoothAdapter, BluetoothDevice};
+use crate::bluez::{describe_error, BluezEvent, DeviceOperation};
+use crate::discovery_filter::DiscoveryFilter;
+use crate::gatt::GattAttribute;
+use crate::obex::FileTransfer;
+use async_trait::async_trait;
+use std::{fmt, path::Path, sync::Arc};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Error returned by a backend, already formatted for the status bar
+/// (e.g. `org.bluez.Error.AuthenticationFailed: Authentication Failed`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackendError(pub String);
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<zbus::Error> for BackendError {
+    fn from(err: zbus::Error) -> BackendError {
+        BackendError(describe_error(&err))
+    }
+}
+
+impl From<zbus::fdo::Error> for BackendError {
+    fn from(err: zbus::fdo::Error) -> BackendError {
+        BackendError::from(zbus::Error::from(err))
+    }
+}
+
+pub type BackendResult<T> = Result<T, BackendError>;
+
+/// Everything `App` needs from the Bluetooth stack. Adapters are addressed
+/// by their `hciN` name and devices by adapter name plus address.
+#[async_trait]
+pub trait BluetoothBackend: Send + Sync {
+    /// Lists every adapter, sorted by name.
+    async fn adapters(&self) -> BackendResult<Vec<BluetoothAdapter>>;
+
+    /// Returns every known device on all adapters.
+    async fn devices(&self) -> BackendResult<Vec<BluetoothDevice>>;
+
+    /// Reads the current state of a single device.
+    async fn device(&self, adapter: &str, address: &str) -> BackendResult<BluetoothDevice>;
+
+    /// Sets what the next or running discovery of this client reports.
+    async fn set_discovery_filter(&self, adapter: &str, filter: &DiscoveryFilter) -> BackendResult<()>;
+
+    async fn start_discovery(&self, adapter: &str) -> BackendResult<()>;
+    async fn stop_discovery(&self, adapter: &str) -> BackendResult<()>;
+    async fn set_powered(&self, adapter: &str, powered: bool) -> BackendResult<()>;
+    async fn set_discoverable(&self, adapter: &str, discoverable: bool) -> BackendResult<()>;
+    async fn set_pairable(&self, adapter: &str, pairable: bool) -> BackendResult<()>;
+    async fn set_discoverable_timeout(&self, adapter: &str, seconds: u32) -> BackendResult<()>;
+
+    /// Renames the adapter; an empty alias restores the system name.
+    async fn set_adapter_alias(&self, adapter: &str, alias: &str) -> BackendResult<()>;
+
+    /// Renames a device; an empty alias restores the name it advertises.
+    async fn set_device_alias(&self, adapter: &str, address: &str, alias: &str) -> BackendResult<()>;
+
+    /// Sets `Device1.Trusted`: a trusted device may connect without asking the agent.
+    async fn set_device_trusted(&self, adapter: &str, address: &str, trusted: bool) -> BackendResult<()>;
+
+    /// Sets `Device1.Blocked`: BlueZ disconnects a blocked device and
+    /// refuses its connections.
+    async fn set_device_blocked(&self, adapter: &str, address: &str, blocked: bool) -> BackendResult<()>;
+
+    /// Runs a Device1 call to completion; may wait on the pairing agent.
+    async fn device_operation(
+        &self,
+        adapter: &str,
+        address: &str,
+        operation: &DeviceOperation,
+    ) -> BackendResult<()>;
+
+    async fn remove_device(&self, adapter: &str, address: &str) -> BackendResult<()>;
+
+    /// Lists the GATT services, characteristics and descriptors BlueZ has
+    /// resolved for a connected LE device, sorted by path.
+    async fn gatt_attributes(&self, adapter: &str, address: &str) -> BackendResult<Vec<GattAttribute>>;
+
+    // GATT attributes are addressed by their object path from `gatt_attributes`.
+
+    async fn read_gatt_value(&self, path: &str) -> BackendResult<Vec<u8>>;
+    async fn write_gatt_value(&self, path: &str, value: &[u8]) -> BackendResult<()>;
+
+    /// Turns notifications of a characteristic on or off; values then arrive
+    /// on the watch channel as `GattValueChanged`.
+    async fn set_gatt_notify(&self, path: &str, enabled: bool) -> BackendResult<()>;
+
+    /// Pushes `file` to a device with OBEX Object Push; progress arrives on
+    /// `events` as `TransferChanged` until it is complete or failed.
+    async fn send_file(
+        &self,
+        adapter: &str,
+        address: &str,
+        file: &Path,
+        events: UnboundedSender<BluezEvent>,
+    ) -> BackendResult<FileTransfer>;
+
+    /// Registers the OBEX agent; incoming files are offered on `events` as
+    /// `AuthorizePush` agent requests.
+    async fn register_obex_agent(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()>;
+
+    /// Starts forwarding adapter and device changes to `events`.
+    async fn watch(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()>;
+
+    /// Registers the pairing agent; its requests arrive on `events`.
+    async fn register_agent(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()>;
+}
+
+/// Runs `operation` in the background and sends `OperationFinished` when done,
+/// so a pairing agent prompt can be answered while `Pair` is still pending.
+pub fn spawn_device_operation(
+    backend: Arc<dyn BluetoothBackend>,
+    adapter: String,
+    address: String,
+    operation: DeviceOperation,
+    events: UnboundedSender<BluezEvent>,
+) {
+    tokio::spawn(async move {
+        let result = backend
+            .device_operation(&adapter, &address, &operation)
+            .await;
+        let _ = events.send(BluezEvent::OperationFinished {
+            adapter,
+            address,
+            operation,
+            result,
+        });
+    });
+}
@@ -0,0 +1,1237 @@
+use crate::agent::{AgentRequest, PairingAgent, AGENT_CAPABILITY, AGENT_PATH};
+use crate::app::{BluetoothAdapter, BluetoothDevice};
+use crate::backend::{BackendResult, BluetoothBackend};
+use crate::device_type::DeviceType;
+use crate::discovery_filter::DiscoveryFilter;
+use crate::gatt::{GattAttribute, GattKind};
+use crate::obex::{self, FileTransfer};
+use crate::profiles::profile_label;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use std::{collections::HashMap, path::Path, sync::Arc};
+use tokio::sync::{mpsc::UnboundedSender, OnceCell};
+use zbus::{
+    fdo::{ObjectManagerProxy, PropertiesProxy},
+    message::Type as MessageType,
+    names::{InterfaceName, OwnedInterfaceName},
+    proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+    Connection, MatchRule, MessageStream,
+};
+
+const BLUEZ_SERVICE: &str = "org.bluez";
+const BLUEZ_ROOT: &str = "/org/bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+const MEDIA_TRANSPORT_INTERFACE: &str = "org.bluez.MediaTransport1";
+const GATT_SERVICE_INTERFACE: &str = "org.bluez.GattService1";
+const GATT_CHARACTERISTIC_INTERFACE: &str = "org.bluez.GattCharacteristic1";
+const GATT_DESCRIPTOR_INTERFACE: &str = "org.bluez.GattDescriptor1";
+
+/// Set to `session` to talk to a BlueZ service on the session bus (e.g. a mock).
+const BUS_ENV_VAR: &str = "BLUETOOTH_MANAGER_BUS";
+
+pub type Properties = HashMap<String, OwnedValue>;
+
+#[proxy(interface = "org.bluez.Adapter1", default_service = "org.bluez")]
+trait Adapter1 {
+    fn set_discovery_filter(&self, filter: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+    fn start_discovery(&self) -> zbus::Result<()>;
+    fn stop_discovery(&self) -> zbus::Result<()>;
+    fn remove_device(&self, device: &ObjectPath<'_>) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_powered(&self, value: bool) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_discoverable(&self, value: bool) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_pairable(&self, value: bool) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_discoverable_timeout(&self, value: u32) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_alias(&self, value: &str) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+trait Device1 {
+    fn connect(&self) -> zbus::Result<()>;
+    fn disconnect(&self) -> zbus::Result<()>;
+    fn pair(&self) -> zbus::Result<()>;
+    fn connect_profile(&self, uuid: &str) -> zbus::Result<()>;
+    fn disconnect_profile(&self, uuid: &str) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_trusted(&self, value: bool) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_blocked(&self, value: bool) -> zbus::Result<()>;
+    #[zbus(property)]
+    fn set_alias(&self, value: &str) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.bluez.GattCharacteristic1", default_service = "org.bluez")]
+trait GattCharacteristic1 {
+    fn read_value(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<Vec<u8>>;
+    fn write_value(&self, value: &[u8], options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+    fn start_notify(&self) -> zbus::Result<()>;
+    fn stop_notify(&self) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.bluez.GattDescriptor1", default_service = "org.bluez")]
+trait GattDescriptor1 {
+    fn read_value(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<Vec<u8>>;
+    fn write_value(&self, value: &[u8], options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.bluez.AgentManager1",
+    default_service = "org.bluez",
+    default_path = "/org/bluez"
+)]
+trait AgentManager1 {
+    fn register_agent(&self, agent: &ObjectPath<'_>, capability: &str) -> zbus::Result<()>;
+    fn request_default_agent(&self, agent: &ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+/// Device1 calls that may take a while (and may involve the pairing agent),
+/// so they run in the background and report back through `BluezEvent`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceOperation {
+    Connect,
+    Disconnect,
+    Pair,
+    ConnectProfile(String),    // profile UUID
+    DisconnectProfile(String), // profile UUID
+}
+
+impl DeviceOperation {
+    pub fn in_progress(&self) -> String {
+        match self {
+            DeviceOperation::Connect => "Connecting to".to_string(),
+            DeviceOperation::Disconnect => "Disconnecting from".to_string(),
+            DeviceOperation::Pair => "Pairing with".to_string(),
+            DeviceOperation::ConnectProfile(uuid) => format!("Connecting {} on", profile_label(uuid)),
+            DeviceOperation::DisconnectProfile(uuid) => {
+                format!("Disconnecting {} on", profile_label(uuid))
+            }
+        }
+    }
+
+    pub fn done(&self) -> String {
+        match self {
+            DeviceOperation::Connect => "Connected to".to_string(),
+            DeviceOperation::Disconnect => "Disconnected from".to_string(),
+            DeviceOperation::Pair => "Paired with".to_string(),
+            DeviceOperation::ConnectProfile(uuid) => format!("Connected {} on", profile_label(uuid)),
+            DeviceOperation::DisconnectProfile(uuid) => {
+                format!("Disconnected {} on", profile_label(uuid))
+            }
+        }
+    }
+
+    pub fn failed(&self) -> String {
+        match self {
+            DeviceOperation::Connect => "Failed to connect to".to_string(),
+            DeviceOperation::Disconnect => "Failed to disconnect from".to_string(),
+            DeviceOperation::Pair => "Failed to pair with".to_string(),
+            DeviceOperation::ConnectProfile(uuid) => {
+                format!("Failed to connect {} on", profile_label(uuid))
+            }
+            DeviceOperation::DisconnectProfile(uuid) => {
+                format!("Failed to disconnect {} on", profile_label(uuid))
+            }
+        }
+    }
+}
+
+/// Everything BlueZ reports back to the app.
+#[derive(Debug)]
+pub enum BluezEvent {
+    AdapterChanged(BluetoothAdapter),
+    AdapterRemoved(String),
+    DeviceAdded(BluetoothDevice),
+    DeviceChanged(BluetoothDevice),
+    /// Only the properties one `PropertiesChanged` signal carried.
+    DevicePropertiesChanged {
+        adapter: String,
+        address: String,
+        changes: DeviceChanges,
+    },
+    DeviceRemoved {
+        adapter: String,
+        address: String,
+    },
+    OperationFinished {
+        adapter: String,
+        address: String,
+        operation: DeviceOperation,
+        result: BackendResult<()>,
+    },
+    AgentRequest(AgentRequest),
+    /// BlueZ or obexd gave up on the agent request with this id.
+    AgentCanceled(u64),
+    /// A characteristic's value changed: a notification, or the result of a read.
+    GattValueChanged {
+        path: String,
+        value: Vec<u8>,
+    },
+    /// Progress of a file sent or received over OBEX.
+    TransferChanged(FileTransfer),
+}
+
+/// Device1 and Battery1 properties from one `PropertiesChanged` signal.
+/// `None` leaves the field as it was.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceChanges {
+    pub name: Option<String>,
+    pub alias: Option<String>, // BlueZ's alias, which falls back to the name
+    pub connected: Option<bool>,
+    pub paired: Option<bool>,
+    pub trusted: Option<bool>,
+    pub blocked: Option<bool>,
+    pub rssi: Option<Option<i16>>, // Some(None) once BlueZ invalidates it
+    pub battery: Option<Option<u8>>,
+    pub uuids: Option<Vec<String>>,
+}
+
+impl DeviceChanges {
+    pub fn new(changed: &Properties, invalidated: &[String]) -> DeviceChanges {
+        let invalid = |key: &str| invalidated.iter().any(|name| name == key);
+        DeviceChanges {
+            name: prop_string(changed, "Name"),
+            alias: prop_string(changed, "Alias"),
+            connected: changed.get("Connected").map(|_| prop_bool(changed, "Connected")),
+            paired: changed.get("Paired").map(|_| prop_bool(changed, "Paired")),
+            trusted: changed.get("Trusted").map(|_| prop_bool(changed, "Trusted")),
+            blocked: changed.get("Blocked").map(|_| prop_bool(changed, "Blocked")),
+            rssi: match changed.get("RSSI") {
+                Some(value) => Some(value.downcast_ref::<i16>().ok()),
+                None => invalid("RSSI").then_some(None),
+            },
+            battery: match battery_percentage(changed) {
+                Some(percentage) => Some(Some(percentage)),
+                None => invalid("Percentage").then_some(None),
+            },
+            uuids: changed
+                .get("UUIDs")
+                .and_then(|v| v.try_clone().ok())
+                .and_then(|v| Vec::<String>::try_from(v).ok()),
+        }
+    }
+
+    pub fn apply_to(&self, device: &mut BluetoothDevice) {
+        if let Some(name) = &self.name {
+            device.name = name.clone();
+        }
+        if let Some(alias) = &self.alias {
+            device.alias = custom_alias(alias, device);
+        }
+        if let Some(connected) = self.connected {
+            device.connected = connected;
+            if !connected {
+                // Its media transports go away with the connection.
+                device.active_profiles.clear();
+            }
+        }
+        if let Some(paired) = self.paired {
+            device.paired = paired;
+            device.known |= paired;
+        }
+        if let Some(trusted) = self.trusted {
+            device.trusted = trusted;
+        }
+        if let Some(blocked) = self.blocked {
+            device.blocked = blocked;
+        }
+        if let Some(rssi) = self.rssi {
+            device.rssi = rssi;
+        }
+        if let Some(battery) = self.battery {
+            device.battery = battery;
+        }
+        if let Some(uuids) = &self.uuids {
+            device.uuids = uuids.clone();
+        }
+    }
+}
+
+/// The device type is decoded from these together, so a change to any of
+/// them re-reads the whole device instead of patching it.
+const DEVICE_TYPE_PROPERTIES: [&str; 3] = ["Class", "Appearance", "Icon"];
+
+/// Handle on the BlueZ service. Adapters are addressed by their `hciN` name
+/// and devices by adapter name plus address.
+#[derive(Clone)]
+pub struct BluezClient {
+    connection: Connection,
+    obex: Arc<OnceCell<Connection>>, // obexd lives on the session bus
+}
+
+impl BluezClient {
+    /// Connects to BlueZ on the system bus, or on the session bus when
+    /// `BLUETOOTH_MANAGER_BUS=session` is set.
+    pub async fn connect() -> zbus::Result<BluezClient> {
+        let connection = match std::env::var(BUS_ENV_VAR).as_deref() {
+            Ok("session") => Connection::session().await?,
+            _ => Connection::system().await?,
+        };
+        Ok(BluezClient::with_connection(connection))
+    }
+
+    pub fn with_connection(connection: Connection) -> BluezClient {
+        BluezClient {
+            connection,
+            obex: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Connection to obexd, opened on first use.
+    async fn obex_connection(&self) -> zbus::Result<Connection> {
+        self.obex
+            .get_or_try_init(Connection::session)
+            .await
+            .cloned()
+    }
+
+    /// Reads the current Adapter1 properties.
+    async fn adapter_info(&self, adapter: &str) -> zbus::Result<BluetoothAdapter> {
+        let path = adapter_path(adapter)?;
+        let props = self.properties(path.clone(), ADAPTER_INTERFACE).await?;
+        Ok(adapter_from_properties(&path, &props))
+    }
+
+    /// Reads the current Device1 properties of a single device, plus its
+    /// Battery1 level when it reports one.
+    async fn device_info(&self, adapter: &str, address: &str) -> zbus::Result<BluetoothDevice> {
+        let path = device_path(adapter, address)?;
+        let props = self.properties(path.clone(), DEVICE_INTERFACE).await?;
+
+        let mut device = device_from_properties(&path, &props)
+            .ok_or_else(|| zbus::Error::Failure(format!("{} has no address", address)))?;
+        // Battery1 only exists while a device with a battery service is connected.
+        device.battery = self
+            .properties(path.clone(), BATTERY_INTERFACE)
+            .await
+            .ok()
+            .and_then(|props| battery_percentage(&props));
+        if device.connected {
+            let objects = object_manager(&self.connection)
+                .await?
+                .get_managed_objects()
+                .await?;
+            device.active_profiles = transport_profiles(&objects)
+                .into_iter()
+                .filter(|(owner, _)| *owner == (adapter.to_string(), address.to_string()))
+                .map(|(_, uuid)| uuid)
+                .collect();
+        }
+        Ok(device)
+    }
+
+    /// Sends `AdapterChanged` whenever Adapter1 properties change on any
+    /// adapter, including changes made by other tools.
+    async fn watch_adapters(&self, sender: UnboundedSender<BluezEvent>) -> zbus::Result<()> {
+        let mut changes = self.properties_changed_stream(ADAPTER_INTERFACE).await?;
+
+        let client = self.clone();
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = changes.next().await {
+                let header = message.header();
+                let Some(name) = header.path().and_then(adapter_name) else { continue };
+
+                let Ok(adapter) = client.adapter_info(&name).await else { continue };
+                if sender.send(BluezEvent::AdapterChanged(adapter)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Sends `DevicePropertiesChanged` whenever properties of a per-device
+    /// interface (Device1 RSSI, Connected, Paired, ... or Battery1 Percentage)
+    /// change, straight from the signal so an RSSI update costs no round trip.
+    async fn watch_device_properties(
+        &self,
+        interface: &'static str,
+        sender: UnboundedSender<BluezEvent>,
+    ) -> zbus::Result<()> {
+        let mut changes = self.properties_changed_stream(interface).await?;
+
+        let client = self.clone();
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = changes.next().await {
+                let header = message.header();
+                let Some((adapter, address)) = header.path().and_then(device_location) else {
+                    continue;
+                };
+
+                let Ok((_, changed, invalidated)) = message
+                    .body()
+                    .deserialize::<(String, Properties, Vec<String>)>()
+                else {
+                    continue;
+                };
+
+                let event = if DEVICE_TYPE_PROPERTIES.iter().any(|key| changed.contains_key(*key)) {
+                    // The device may already be gone again; that arrives as DeviceRemoved.
+                    let Ok(device) = client.device_info(&adapter, &address).await else { continue };
+                    BluezEvent::DeviceChanged(device)
+                } else {
+                    BluezEvent::DevicePropertiesChanged {
+                        adapter,
+                        address,
+                        changes: DeviceChanges::new(&changed, &invalidated),
+                    }
+                };
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Sends `GattValueChanged` with the new value whenever a characteristic's
+    /// `Value` changes, which is how BlueZ delivers notifications.
+    async fn watch_gatt_values(&self, sender: UnboundedSender<BluezEvent>) -> zbus::Result<()> {
+        let mut changes = self
+            .properties_changed_stream(GATT_CHARACTERISTIC_INTERFACE)
+            .await?;
+
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = changes.next().await {
+                let Some(path) = message.header().path().map(|p| p.to_string()) else { continue };
+                let Ok((_, changed, _)) = message
+                    .body()
+                    .deserialize::<(String, Properties, Vec<String>)>()
+                else {
+                    continue;
+                };
+                let Some(value) = prop_bytes(&changed, "Value") else { continue };
+
+                if sender.send(BluezEvent::GattValueChanged { path, value }).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Forwards `InterfacesAdded`/`InterfacesRemoved` for adapters and devices to `sender`.
+    async fn watch_objects(&self, sender: UnboundedSender<BluezEvent>) -> zbus::Result<()> {
+        let manager = object_manager(&self.connection).await?;
+        let mut added = manager.receive_interfaces_added().await?;
+        let mut removed = manager.receive_interfaces_removed().await?;
+
+        let added_sender = sender.clone();
+        let client = self.clone();
+        tokio::spawn(async move {
+            while let Some(signal) = added.next().await {
+                let Ok(args) = signal.args() else { continue };
+                let path = args.object_path();
+                let interfaces = args.interfaces_and_properties();
+
+                let event = if let Some(props) = interfaces.get(DEVICE_INTERFACE) {
+                    device_from_properties(path, &owned_properties(props)).map(|mut device| {
+                        device.battery = interfaces
+                            .get(BATTERY_INTERFACE)
+                            .and_then(|props| battery_percentage(&owned_properties(props)));
+                        BluezEvent::DeviceAdded(device)
+                    })
+                } else if interfaces.contains_key(BATTERY_INTERFACE)
+                    || interfaces.contains_key(MEDIA_TRANSPORT_INTERFACE)
+                {
+                    client.changed_device(path).await
+                } else {
+                    interfaces.get(ADAPTER_INTERFACE).map(|props| {
+                        BluezEvent::AdapterChanged(adapter_from_properties(
+                            path,
+                            &owned_properties(props),
+                        ))
+                    })
+                };
+
+                if let Some(event) = event {
+                    if added_sender.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let client = self.clone();
+        tokio::spawn(async move {
+            while let Some(signal) = removed.next().await {
+                let Ok(args) = signal.args() else { continue };
+                let path = args.object_path();
+
+                let event = if args.interfaces().contains(&DEVICE_INTERFACE) {
+                    device_location(path)
+                        .map(|(adapter, address)| BluezEvent::DeviceRemoved { adapter, address })
+                } else if args.interfaces().contains(&BATTERY_INTERFACE)
+                    || args.interfaces().contains(&MEDIA_TRANSPORT_INTERFACE)
+                {
+                    client.changed_device(path).await
+                } else if args.interfaces().contains(&ADAPTER_INTERFACE) {
+                    adapter_name(path).map(BluezEvent::AdapterRemoved)
+                } else {
+                    None
+                };
+
+                if let Some(event) = event {
+                    if sender.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Re-reads a device after one of its secondary interfaces (or one of
+    /// its media transports) came or went.
+    async fn changed_device(&self, path: &ObjectPath<'_>) -> Option<BluezEvent> {
+        let (adapter, address) = device_owner(path)?;
+        let device = self.device_info(&adapter, &address).await.ok()?;
+        Some(BluezEvent::DeviceChanged(device))
+    }
+
+    /// Subscribes to `PropertiesChanged` for one BlueZ interface on every object.
+    async fn properties_changed_stream(
+        &self,
+        interface: &'static str,
+    ) -> zbus::Result<MessageStream> {
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace(BLUEZ_ROOT)?
+            .arg(0, interface)?
+            .build();
+        MessageStream::for_match_rule(rule, &self.connection, None).await
+    }
+
+    async fn adapter(&self, adapter: &str) -> zbus::Result<Adapter1Proxy<'static>> {
+        Adapter1Proxy::builder(&self.connection)
+            .path(adapter_path(adapter)?)?
+            .build()
+            .await
+    }
+
+    async fn properties(
+        &self,
+        path: OwnedObjectPath,
+        interface: &'static str,
+    ) -> zbus::Result<Properties> {
+        PropertiesProxy::builder(&self.connection)
+            .destination(BLUEZ_SERVICE)?
+            .path(path)?
+            .build()
+            .await?
+            .get_all(Some(InterfaceName::from_static_str_unchecked(interface)).into())
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn characteristic(&self, path: &str) -> zbus::Result<GattCharacteristic1Proxy<'static>> {
+        GattCharacteristic1Proxy::builder(&self.connection)
+            .path(OwnedObjectPath::try_from(path.to_string())?)?
+            .build()
+            .await
+    }
+
+    async fn descriptor(&self, path: &str) -> zbus::Result<GattDescriptor1Proxy<'static>> {
+        GattDescriptor1Proxy::builder(&self.connection)
+            .path(OwnedObjectPath::try_from(path.to_string())?)?
+            .build()
+            .await
+    }
+
+    async fn device_proxy(
+        &self,
+        adapter: &str,
+        address: &str,
+    ) -> zbus::Result<Device1Proxy<'static>> {
+        Device1Proxy::builder(&self.connection)
+            .path(device_path(adapter, address)?)?
+            .build()
+            .await
+    }
+}
+
+#[async_trait]
+impl BluetoothBackend for BluezClient {
+    async fn adapters(&self) -> BackendResult<Vec<BluetoothAdapter>> {
+        let objects = object_manager(&self.connection)
+            .await?
+            .get_managed_objects()
+            .await?;
+
+        let mut adapters: Vec<BluetoothAdapter> = objects
+            .into_iter()
+            .filter_map(|(path, interfaces)| {
+                interfaces
+                    .into_iter()
+                    .find(|(name, _)| name.as_str() == ADAPTER_INTERFACE)
+                    .map(|(_, props)| adapter_from_properties(&path, &props))
+            })
+            .collect();
+        adapters.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(adapters)
+    }
+
+    async fn devices(&self) -> BackendResult<Vec<BluetoothDevice>> {
+        let objects = object_manager(&self.connection)
+            .await?
+            .get_managed_objects()
+            .await?;
+
+        let transports = transport_profiles(&objects);
+        let mut devices: Vec<BluetoothDevice> = objects
+            .into_iter()
+            .filter_map(|(path, interfaces)| {
+                let interface = |wanted: &str| {
+                    interfaces
+                        .iter()
+                        .find(|(name, _)| name.as_str() == wanted)
+                        .map(|(_, props)| props)
+                };
+                let mut device = device_from_properties(&path, interface(DEVICE_INTERFACE)?)?;
+                device.battery = interface(BATTERY_INTERFACE).and_then(battery_percentage);
+                device.active_profiles = transports
+                    .iter()
+                    .filter(|((adapter, address), _)| device.is(adapter, address))
+                    .map(|(_, uuid)| uuid.clone())
+                    .collect();
+                Some(device)
+            })
+            .collect();
+        devices.sort_by(|a, b| (&a.adapter, &a.address).cmp(&(&b.adapter, &b.address)));
+
+        Ok(devices)
+    }
+
+    async fn device(&self, adapter: &str, address: &str) -> BackendResult<BluetoothDevice> {
+        Ok(self.device_info(adapter, address).await?)
+    }
+
+    async fn set_discovery_filter(&self, adapter: &str, filter: &DiscoveryFilter) -> BackendResult<()> {
+        let mut arguments = HashMap::from([
+            ("Transport", Value::from(filter.transport.name())),
+            ("DuplicateData", Value::from(filter.duplicate_data)),
+        ]);
+        if let Some(rssi) = filter.rssi {
+            arguments.insert("RSSI", Value::from(rssi));
+        }
+        if let Some(pathloss) = filter.pathloss {
+            arguments.insert("Pathloss", Value::from(pathloss));
+        }
+        if !filter.uuids.is_empty() {
+            arguments.insert("UUIDs", Value::from(filter.uuids.clone()));
+        }
+        Ok(self.adapter(adapter).await?.set_discovery_filter(arguments).await?)
+    }
+
+    async fn start_discovery(&self, adapter: &str) -> BackendResult<()> {
+        Ok(self.adapter(adapter).await?.start_discovery().await?)
+    }
+
+    async fn stop_discovery(&self, adapter: &str) -> BackendResult<()> {
+        Ok(self.adapter(adapter).await?.stop_discovery().await?)
+    }
+
+    async fn set_powered(&self, adapter: &str, powered: bool) -> BackendResult<()> {
+        Ok(self.adapter(adapter).await?.set_powered(powered).await?)
+    }
+
+    async fn set_discoverable(&self, adapter: &str, discoverable: bool) -> BackendResult<()> {
+        Ok(self.adapter(adapter).await?.set_discoverable(discoverable).await?)
+    }
+
+    async fn set_pairable(&self, adapter: &str, pairable: bool) -> BackendResult<()> {
+        Ok(self.adapter(adapter).await?.set_pairable(pairable).await?)
+    }
+
+    async fn set_discoverable_timeout(&self, adapter: &str, seconds: u32) -> BackendResult<()> {
+        Ok(self
+            .adapter(adapter)
+            .await?
+            .set_discoverable_timeout(seconds)
+            .await?)
+    }
+
+    async fn set_adapter_alias(&self, adapter: &str, alias: &str) -> BackendResult<()> {
+        Ok(self.adapter(adapter).await?.set_alias(alias).await?)
+    }
+
+    async fn set_device_alias(&self, adapter: &str, address: &str, alias: &str) -> BackendResult<()> {
+        Ok(self
+            .device_proxy(adapter, address)
+            .await?
+            .set_alias(alias)
+            .await?)
+    }
+
+    async fn set_device_trusted(&self, adapter: &str, address: &str, trusted: bool) -> BackendResult<()> {
+        Ok(self
+            .device_proxy(adapter, address)
+            .await?
+            .set_trusted(trusted)
+            .await?)
+    }
+
+    async fn set_device_blocked(&self, adapter: &str, address: &str, blocked: bool) -> BackendResult<()> {
+        Ok(self
+            .device_proxy(adapter, address)
+            .await?
+            .set_blocked(blocked)
+            .await?)
+    }
+
+    async fn device_operation(
+        &self,
+        adapter: &str,
+        address: &str,
+        operation: &DeviceOperation,
+    ) -> BackendResult<()> {
+        let device = self.device_proxy(adapter, address).await?;
+        match operation {
+            DeviceOperation::Connect => device.connect().await?,
+            DeviceOperation::Disconnect => device.disconnect().await?,
+            DeviceOperation::ConnectProfile(uuid) => device.connect_profile(uuid).await?,
+            DeviceOperation::DisconnectProfile(uuid) => device.disconnect_profile(uuid).await?,
+            // Trusting the new pairing is up to the caller, so that a failure
+            // there is not reported as a failed pairing.
+            DeviceOperation::Pair => device.pair().await?,
+        }
+        Ok(())
+    }
+
+    async fn remove_device(&self, adapter: &str, address: &str) -> BackendResult<()> {
+        let path = device_path(adapter, address)?;
+        Ok(self.adapter(adapter).await?.remove_device(&path).await?)
+    }
+
+    async fn gatt_attributes(&self, adapter: &str, address: &str) -> BackendResult<Vec<GattAttribute>> {
+        let device = device_path(adapter, address)?;
+        let objects = object_manager(&self.connection)
+            .await?
+            .get_managed_objects()
+            .await?;
+
+        let mut attributes: Vec<GattAttribute> = objects
+            .iter()
+            .filter(|(path, _)| {
+                path.as_str()
+                    .strip_prefix(device.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .filter_map(|(path, interfaces)| {
+                interfaces
+                    .iter()
+                    .find_map(|(name, props)| {
+                        let kind = match name.as_str() {
+                            GATT_SERVICE_INTERFACE => GattKind::Service,
+                            GATT_CHARACTERISTIC_INTERFACE => GattKind::Characteristic,
+                            GATT_DESCRIPTOR_INTERFACE => GattKind::Descriptor,
+                            _ => return None,
+                        };
+                        Some((kind, props))
+                    })
+                    .map(|(kind, props)| gatt_attribute_from_properties(path, kind, props))
+            })
+            .collect();
+        attributes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(attributes)
+    }
+
+    async fn read_gatt_value(&self, path: &str) -> BackendResult<Vec<u8>> {
+        if is_descriptor_path(path) {
+            Ok(self.descriptor(path).await?.read_value(HashMap::new()).await?)
+        } else {
+            Ok(self.characteristic(path).await?.read_value(HashMap::new()).await?)
+        }
+    }
+
+    async fn write_gatt_value(&self, path: &str, value: &[u8]) -> BackendResult<()> {
+        if is_descriptor_path(path) {
+            Ok(self.descriptor(path).await?.write_value(value, HashMap::new()).await?)
+        } else {
+            Ok(self
+                .characteristic(path)
+                .await?
+                .write_value(value, HashMap::new())
+                .await?)
+        }
+    }
+
+    async fn set_gatt_notify(&self, path: &str, enabled: bool) -> BackendResult<()> {
+        let characteristic = self.characteristic(path).await?;
+        if enabled {
+            Ok(characteristic.start_notify().await?)
+        } else {
+            Ok(characteristic.stop_notify().await?)
+        }
+    }
+
+    async fn send_file(
+        &self,
+        adapter: &str,
+        address: &str,
+        file: &Path,
+        events: UnboundedSender<BluezEvent>,
+    ) -> BackendResult<FileTransfer> {
+        let source = self.adapter_info(adapter).await?.address;
+        let connection = self.obex_connection().await?;
+        let (mut transfer, session) = obex::send_file(&connection, &source, address, file).await?;
+        transfer.adapter = adapter.to_string();
+        obex::follow_transfer(connection, transfer.clone(), Some(session), events);
+        Ok(transfer)
+    }
+
+    async fn register_obex_agent(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()> {
+        let connection = self.obex_connection().await?;
+        Ok(obex::register_agent(&connection, self.clone(), events).await?)
+    }
+
+    async fn watch(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()> {
+        self.watch_objects(events.clone()).await?;
+        self.watch_device_properties(DEVICE_INTERFACE, events.clone())
+            .await?;
+        self.watch_device_properties(BATTERY_INTERFACE, events.clone())
+            .await?;
+        self.watch_gatt_values(events.clone()).await?;
+        self.watch_adapters(events).await?;
+        Ok(())
+    }
+
+    /// Exports our `org.bluez.Agent1` object and makes it the default agent.
+    async fn register_agent(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()> {
+        self.connection
+            .object_server()
+            .at(AGENT_PATH, PairingAgent::new(events))
+            .await?;
+
+        let path = ObjectPath::from_static_str_unchecked(AGENT_PATH);
+        let manager = AgentManager1Proxy::new(&self.connection).await?;
+        manager.register_agent(&path, AGENT_CAPABILITY).await?;
+        Ok(manager.request_default_agent(&path).await?)
+    }
+}
+
+async fn object_manager(connection: &Connection) -> zbus::Result<ObjectManagerProxy<'static>> {
+    ObjectManagerProxy::builder(connection)
+        .destination(BLUEZ_SERVICE)?
+        .path("/")?
+        .build()
+        .await
+}
+
+/// Formats a D-Bus error the way BlueZ reports it, e.g.
+/// `org.bluez.Error.AuthenticationFailed: Authentication Failed`.
+pub fn describe_error(err: &zbus::Error) -> String {
+    match err {
+        zbus::Error::MethodError(name, Some(message), _) => format!("{}: {}", name, message),
+        zbus::Error::MethodError(name, None, _) => name.to_string(),
+        zbus::Error::FDO(fdo) => fdo.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn adapter_path(adapter: &str) -> zbus::Result<OwnedObjectPath> {
+    Ok(OwnedObjectPath::try_from(format!("{}/{}", BLUEZ_ROOT, adapter))?)
+}
+
+fn device_path(adapter: &str, address: &str) -> zbus::Result<OwnedObjectPath> {
+    let path = format!("{}/{}/dev_{}", BLUEZ_ROOT, adapter, address.replace(':', "_"));
+    Ok(OwnedObjectPath::try_from(path)?)
+}
+
+/// `/org/bluez/hci0` -> `hci0`.
+fn adapter_name(path: &ObjectPath<'_>) -> Option<String> {
+    path.as_str()
+        .strip_prefix(BLUEZ_ROOT)?
+        .strip_prefix('/')
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+        .map(str::to_string)
+}
+
+/// BlueZ names device objects `/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF`;
+/// returns the adapter name and the device address.
+pub fn device_location(path: &ObjectPath<'_>) -> Option<(String, String)> {
+    let rest = path.as_str().strip_prefix(BLUEZ_ROOT)?.strip_prefix('/')?;
+    let (adapter, device) = rest.split_once('/')?;
+    let address = device.strip_prefix("dev_")?;
+    if address.contains('/') {
+        return None;
+    }
+
+    Some((adapter.to_string(), address.replace('_', ":")))
+}
+
+/// Like `device_location`, but also accepts objects below a device, such as
+/// `/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF/sep1/fd0`.
+fn device_owner(path: &ObjectPath<'_>) -> Option<(String, String)> {
+    let device_part = path.as_str().splitn(6, '/').take(5).collect::<Vec<_>>().join("/");
+    device_location(&ObjectPath::try_from(device_part).ok()?)
+}
+
+fn owned_properties(props: &HashMap<&str, Value<'_>>) -> Properties {
+    props
+        .iter()
+        .filter_map(|(k, v)| Some((k.to_string(), v.try_to_owned().ok()?)))
+        .collect()
+}
+
+fn adapter_from_properties(path: &ObjectPath<'_>, props: &Properties) -> BluetoothAdapter {
+    BluetoothAdapter {
+        name: adapter_name(path).unwrap_or_default(),
+        address: prop_string(props, "Address").unwrap_or_default(),
+        alias: prop_string(props, "Alias").unwrap_or_default(),
+        powered: prop_bool(props, "Powered"),
+        discoverable: prop_bool(props, "Discoverable"),
+        pairable: prop_bool(props, "Pairable"),
+        discoverable_timeout: props
+            .get("DiscoverableTimeout")
+            .and_then(|v| v.downcast_ref::<u32>().ok())
+            .unwrap_or(0),
+        discovering: prop_bool(props, "Discovering"),
+    }
+}
+
+fn device_from_properties(path: &ObjectPath<'_>, props: &Properties) -> Option<BluetoothDevice> {
+    let (adapter, _) = device_location(path)?;
+    let address = prop_string(props, "Address")?;
+    let name = prop_string(props, "Name").unwrap_or_else(|| address.clone());
+    let paired = prop_bool(props, "Paired");
+
+    let device_type = DeviceType::decode(
+        props.get("Class").and_then(|v| v.downcast_ref::<u32>().ok()),
+        props.get("Appearance").and_then(|v| v.downcast_ref::<u16>().ok()),
+        prop_string(props, "Icon").as_deref(),
+    );
+
+    let mut device = if paired {
+        BluetoothDevice::new_known(address, name, device_type)
+    } else {
+        let mut device = BluetoothDevice::new(address, name);
+        device.device_type = device_type;
+        device
+    };
+
+    device.adapter = adapter;
+    if let Some(alias) = prop_string(props, "Alias") {
+        device.alias = custom_alias(&alias, &device);
+    }
+    device.uuids = props
+        .get("UUIDs")
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| Vec::<String>::try_from(v).ok())
+        .unwrap_or_default();
+    device.connected = prop_bool(props, "Connected");
+    device.trusted = prop_bool(props, "Trusted");
+    device.blocked = prop_bool(props, "Blocked");
+    device.rssi = props.get("RSSI").and_then(|v| v.downcast_ref::<i16>().ok());
+
+    Some(device)
+}
+
+/// BlueZ's alias when the user set one; otherwise it repeats the name, or
+/// the address with dashes for devices without a name.
+fn custom_alias(alias: &str, device: &BluetoothDevice) -> Option<String> {
+    (alias != device.name && alias != device.address.replace(':', "-")).then(|| alias.to_string())
+}
+
+/// Profile UUID of every media transport, keyed by the device it belongs to.
+/// A transport exists while audio for that profile is set up.
+fn transport_profiles(
+    objects: &HashMap<OwnedObjectPath, HashMap<OwnedInterfaceName, Properties>>,
+) -> Vec<((String, String), String)> {
+    objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let props = interfaces
+                .iter()
+                .find(|(name, _)| name.as_str() == MEDIA_TRANSPORT_INTERFACE)?
+                .1;
+            Some((device_owner(path)?, prop_string(props, "UUID")?.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+fn gatt_attribute_from_properties(
+    path: &ObjectPath<'_>,
+    kind: GattKind,
+    props: &Properties,
+) -> GattAttribute {
+    GattAttribute {
+        path: path.to_string(),
+        kind,
+        uuid: prop_string(props, "UUID")
+            .unwrap_or_default()
+            .to_ascii_lowercase(),
+        primary: prop_bool(props, "Primary"),
+        flags: props
+            .get("Flags")
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| Vec::<String>::try_from(v).ok())
+            .unwrap_or_default(),
+        value: prop_bytes(props, "Value"),
+        notifying: prop_bool(props, "Notifying"),
+    }
+}
+
+/// BlueZ names descriptor objects `descXXXX`, below their characteristic.
+fn is_descriptor_path(path: &str) -> bool {
+    path.rsplit('/').next().is_some_and(|name| name.starts_with("desc"))
+}
+
+/// `Battery1.Percentage`, 0-100.
+fn battery_percentage(props: &Properties) -> Option<u8> {
+    props
+        .get("Percentage")
+        .and_then(|v| v.downcast_ref::<u8>().ok())
+}
+
+pub fn prop_string(props: &Properties, key: &str) -> Option<String> {
+    props
+        .get(key)
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+        .map(str::to_string)
+}
+
+/// A byte array property such as `GattCharacteristic1.Value`.
+fn prop_bytes(props: &Properties, key: &str) -> Option<Vec<u8>> {
+    props
+        .get(key)
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| Vec::<u8>::try_from(v).ok())
+}
+
+fn prop_bool(props: &Properties, key: &str) -> bool {
+    props
+        .get(key)
+        .and_then(|v| v.downcast_ref::<bool>().ok())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    fn properties(values: Vec<(&str, Value<'_>)>) -> Properties {
+        values
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.try_to_owned().unwrap()))
+            .collect()
+    }
+
+    fn headphones() -> BluetoothDevice {
+        let mut device =
+            BluetoothDevice::new("00:1B:66:A1:B2:C3".to_string(), "WH-1000".to_string());
+        device.adapter = "hci0".to_string();
+        device.connected = true;
+        device.rssi = Some(-50);
+        device.battery = Some(80);
+        device.active_profiles = vec!["0000110b-0000-1000-8000-00805f9b34fb".to_string()];
+        device
+    }
+
+    #[test]
+    fn changes_carry_only_the_signalled_properties() {
+        let changed = properties(vec![("RSSI", Value::from(-70i16))]);
+        let changes = DeviceChanges::new(&changed, &[]);
+        assert_eq!(
+            changes,
+            DeviceChanges {
+                rssi: Some(Some(-70)),
+                ..DeviceChanges::default()
+            }
+        );
+
+        let mut device = headphones();
+        changes.apply_to(&mut device);
+        assert_eq!(device.rssi, Some(-70));
+        assert!(device.connected);
+        assert_eq!(device.battery, Some(80));
+        assert_eq!(device.name, "WH-1000");
+    }
+
+    #[test]
+    fn invalidated_rssi_and_battery_are_cleared() {
+        let invalidated = vec!["RSSI".to_string(), "Percentage".to_string()];
+        let mut device = headphones();
+        DeviceChanges::new(&Properties::new(), &invalidated).apply_to(&mut device);
+        assert_eq!(device.rssi, None);
+        assert_eq!(device.battery, None);
+        assert!(device.connected);
+    }
+
+    #[test]
+    fn disconnecting_drops_the_active_profiles() {
+        let changed = properties(vec![("Connected", Value::from(false))]);
+        let mut device = headphones();
+        DeviceChanges::new(&changed, &[]).apply_to(&mut device);
+        assert!(!device.connected);
+        assert!(device.active_profiles.is_empty());
+    }
+
+    #[test]
+    fn pairing_makes_the_device_known() {
+        let changed = properties(vec![
+            ("Paired", Value::from(true)),
+            ("Trusted", Value::from(true)),
+            ("Percentage", Value::from(42u8)),
+        ]);
+        let mut device = headphones();
+        DeviceChanges::new(&changed, &[]).apply_to(&mut device);
+        assert!(device.paired && device.known && device.trusted);
+        assert_eq!(device.battery, Some(42));
+    }
+
+    #[test]
+    fn alias_repeating_the_name_or_address_is_not_custom() {
+        let mut device = headphones();
+        let rename = |alias: &str, device: &mut BluetoothDevice| {
+            let changed = properties(vec![("Alias", Value::from(alias))]);
+            DeviceChanges::new(&changed, &[]).apply_to(device);
+        };
+
+        rename("Kitchen", &mut device);
+        assert_eq!(device.alias.as_deref(), Some("Kitchen"));
+        rename("WH-1000", &mut device);
+        assert_eq!(device.alias, None);
+        rename("00-1B-66-A1-B2-C3", &mut device);
+        assert_eq!(device.alias, None);
+
+        let changed = properties(vec![
+            ("Name", Value::from("WH-1000XM5")),
+            ("Alias", Value::from("WH-1000XM5")),
+        ]);
+        DeviceChanges::new(&changed, &[]).apply_to(&mut device);
+        assert_eq!(device.name, "WH-1000XM5");
+        assert_eq!(device.alias, None);
+    }
+
+    const MOCK_TEST: &str = "bluez::tests::client_follows_the_mock_bluez";
+    const SPEAKER: &str = "11:22:33:44:55:66";
+    const KEYBOARD: &str = "AA:BB:CC:DD:EE:FF";
+
+    /// The first event `pick` accepts; the others are skipped.
+    async fn wait_for<T>(
+        events: &mut UnboundedReceiver<BluezEvent>,
+        mut pick: impl FnMut(BluezEvent) -> Option<T>,
+    ) -> T {
+        let wait = async {
+            loop {
+                let event = events.recv().await.expect("client is watching");
+                if let Some(found) = pick(event) {
+                    return found;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), wait)
+            .await
+            .expect("event from mock_bluez")
+    }
+
+    /// Runs the client against `examples/mock_bluez` on a private session
+    /// bus: the test starts itself again under `dbus-run-session` and the
+    /// mock, which sets `BLUETOOTH_MANAGER_BUS=session`. Skipped where
+    /// `dbus-run-session` is not installed.
+    #[tokio::test]
+    async fn client_follows_the_mock_bluez() {
+        if std::env::var(BUS_ENV_VAR).as_deref() != Ok("session") {
+            let test = std::env::current_exe().unwrap();
+            // target/debug/deps/<test> next to target/debug/examples/mock_bluez
+            let mock = test.parent().and_then(Path::parent).unwrap().join("examples/mock_bluez");
+            if !mock.exists() {
+                eprintln!("skipped: {} is not built", mock.display());
+                return;
+            }
+            let output = std::process::Command::new("dbus-run-session")
+                .arg("--")
+                .arg(&mock)
+                .arg(&test)
+                .args([MOCK_TEST, "--exact"])
+                .output();
+            match output {
+                Ok(output) => assert!(
+                    output.status.success(),
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+                Err(err) => eprintln!("skipped: cannot run dbus-run-session: {}", err),
+            }
+            return;
+        }
+
+        let client = BluezClient::connect().await.unwrap();
+        let adapters = client.adapters().await.unwrap();
+        let powered: Vec<(&str, bool)> =
+            adapters.iter().map(|a| (a.name.as_str(), a.powered)).collect();
+        assert_eq!(powered, [("hci0", true), ("hci1", false)]);
+
+        // GetManagedObjects: only the paired headphones, out of range.
+        let devices = client.devices().await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].address, "00:1B:66:A1:B2:C3");
+        assert_eq!(devices[0].battery, Some(64));
+        assert_eq!(devices[0].rssi, None);
+
+        let (sender, mut events) = mpsc::unbounded_channel();
+        client.watch(sender).await.unwrap();
+        client.start_discovery("hci0").await.unwrap();
+        wait_for(&mut events, |event| match event {
+            BluezEvent::AdapterChanged(a) if a.name == "hci0" && a.discovering => Some(()),
+            _ => None,
+        })
+        .await;
+
+        // InterfacesAdded, with the RSSI of a device in range.
+        let mut found = vec![];
+        while found.len() < 2 {
+            let device = wait_for(&mut events, |event| match event {
+                BluezEvent::DeviceAdded(device) => Some(device),
+                _ => None,
+            })
+            .await;
+            assert_eq!(device.adapter, "hci0");
+            found.push((device.address, device.rssi));
+        }
+        assert_eq!(
+            found,
+            [(SPEAKER.to_string(), Some(-60)), (KEYBOARD.to_string(), Some(-45))]
+        );
+
+        client.stop_discovery("hci0").await.unwrap();
+        wait_for(&mut events, |event| match event {
+            BluezEvent::AdapterChanged(a) if a.name == "hci0" && !a.discovering => Some(()),
+            _ => None,
+        })
+        .await;
+        assert!(client.stop_discovery("hci0").await.is_err());
+        assert!(client.start_discovery("hci1").await.is_err());
+
+        // InterfacesRemoved
+        client.remove_device("hci0", SPEAKER).await.unwrap();
+        let removed = wait_for(&mut events, |event| match event {
+            BluezEvent::DeviceRemoved { adapter, address } => Some((adapter, address)),
+            _ => None,
+        })
+        .await;
+        assert_eq!(removed, ("hci0".to_string(), SPEAKER.to_string()));
+        let addresses: Vec<String> =
+            client.devices().await.unwrap().into_iter().map(|d| d.address).collect();
+        assert_eq!(addresses.len(), 2);
+        assert!(!addresses.contains(&SPEAKER.to_string()));
+    }
+}
@@ -0,0 +1,825 @@
+use crate::agent::AgentPrompt;
+use crate::app::{App, BluetoothDevice};
+use crate::backend::{BackendResult, BluetoothBackend};
+use crate::bluez::{BluezEvent, DeviceOperation};
+use crate::profiles::profile_label;
+use crate::rfkill::{self, BlockState};
+use serde_json::{json, Value};
+use std::{
+    io::Write,
+    process::ExitCode,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader, Stdin},
+    sync::mpsc::UnboundedReceiver,
+    time::MissedTickBehavior,
+};
+
+/// Runs against the scripted in-memory backend instead of BlueZ.
+const SIMULATE_FLAG: &str = "--simulate";
+const JSON_FLAG: &str = "--json";
+const ADAPTER_FLAG: &str = "--adapter";
+const TIMEOUT_FLAG: &str = "--timeout";
+const STATUS_FLAG: &str = "--status";
+const FOLLOW_FLAG: &str = "--follow";
+
+/// How long `scan` discovers without `--timeout`.
+const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tick of `--status --follow`; the same as the TUI's, so rfkill changes
+/// show up as quickly.
+const STATUS_TICK: Duration = Duration::from_millis(250);
+
+/// Answers typed on the terminal. One reader serves the whole command, so
+/// lines piped in ahead of the questions are not lost between them.
+type Input = BufReader<Stdin>;
+
+pub const EXIT_FAILED: u8 = 1; // BlueZ refused or the operation failed
+pub const EXIT_USAGE: u8 = 2;
+pub const EXIT_UNAVAILABLE: u8 = 3; // no BlueZ or no adapter
+pub const EXIT_NOT_FOUND: u8 = 4; // unknown device or adapter
+
+pub const USAGE: &str = "\
+Usage: bluetooth-manager [--simulate]            start the interactive UI
+       bluetooth-manager [OPTIONS] COMMAND
+       bluetooth-manager [OPTIONS] --status [--follow]
+
+Commands:
+  list                       list adapters and the devices BlueZ knows
+  scan [--timeout SECONDS]   discover nearby devices (default 10 s)
+  connect ADDRESS            connect a device
+  disconnect ADDRESS         disconnect a device
+  pair ADDRESS               pair a device, asking for codes on the terminal
+  remove ADDRESS             remove a device
+  power on|off               power the adapter on or off
+
+Status:
+  --status         print adapter power, connected devices and battery
+                   levels as one JSON object
+  --follow         with --status, keep running and print a new object
+                   on every change, one per line
+
+Options:
+  --adapter hciN   adapter to use (default: the first one)
+  --json           print the result as JSON on stdout
+  --simulate       use the simulated backend instead of BlueZ
+  -h, --help       show this help
+
+Exit codes:
+  0 success, 1 operation failed, 2 usage error,
+  3 BlueZ or adapter unavailable, 4 device or adapter not found";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    List,
+    Scan { timeout: Duration },
+    Connect(String),
+    Disconnect(String),
+    Pair(String),
+    Remove(String),
+    Power(bool),
+    Status { follow: bool }, // --status, not a word of its own
+}
+
+/// Command line arguments. Without a command the TUI is started.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub command: Option<Command>,
+    pub help: bool,
+    pub simulate: bool,
+    pub json: bool,
+    pub adapter: Option<String>,
+}
+
+impl Options {
+    /// Parses the arguments after the program name; options may appear
+    /// before or after the command.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut words = vec![];
+        let mut timeout = None;
+        let (mut status, mut follow) = (false, false);
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                SIMULATE_FLAG => options.simulate = true,
+                JSON_FLAG => options.json = true,
+                STATUS_FLAG => status = true,
+                FOLLOW_FLAG => follow = true,
+                "-h" | "--help" => options.help = true,
+                ADAPTER_FLAG => {
+                    let name = args.next().ok_or("--adapter needs an adapter name")?;
+                    options.adapter = Some(name);
+                }
+                TIMEOUT_FLAG => {
+                    let value = args.next().ok_or("--timeout needs a number of seconds")?;
+                    let seconds = value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid timeout: {}", value))?;
+                    timeout = Some(Duration::from_secs(seconds));
+                }
+                flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+                _ => words.push(arg),
+            }
+        }
+
+        if options.help {
+            return Ok(options);
+        }
+
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let address = |address: &str| address.to_ascii_uppercase();
+        options.command = match words.as_slice() {
+            [] => None,
+            ["list"] => Some(Command::List),
+            ["scan"] => Some(Command::Scan {
+                timeout: timeout.unwrap_or(DEFAULT_SCAN_TIMEOUT),
+            }),
+            ["connect", device] => Some(Command::Connect(address(device))),
+            ["disconnect", device] => Some(Command::Disconnect(address(device))),
+            ["pair", device] => Some(Command::Pair(address(device))),
+            ["remove", device] => Some(Command::Remove(address(device))),
+            ["power", "on"] => Some(Command::Power(true)),
+            ["power", "off"] => Some(Command::Power(false)),
+            [command @ ("list" | "scan" | "connect" | "disconnect" | "pair" | "remove" | "power"), ..] => {
+                return Err(format!("Wrong arguments for {}", command))
+            }
+            [command, ..] => return Err(format!("Unknown command: {}", command)),
+        };
+
+        if timeout.is_some() && !matches!(options.command, Some(Command::Scan { .. })) {
+            return Err("--timeout only applies to scan".to_string());
+        }
+        if status {
+            if let Some(command) = words.first() {
+                return Err(format!("--status cannot be combined with {}", command));
+            }
+            options.command = Some(Command::Status { follow });
+        } else if follow {
+            return Err("--follow only applies to --status".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Prints results as text or JSON and turns them into exit codes.
+struct Output {
+    json: bool,
+}
+
+impl Output {
+    /// `text` goes to stdout as is; in JSON mode `data` is printed with `"ok": true`.
+    fn success(&self, text: &str, mut data: Value) -> ExitCode {
+        if self.json {
+            data["ok"] = Value::Bool(true);
+            println!("{}", data);
+        } else if !text.is_empty() {
+            println!("{}", text);
+        }
+        ExitCode::SUCCESS
+    }
+
+    fn failure(&self, code: u8, message: &str) -> ExitCode {
+        if self.json {
+            println!("{}", json!({ "ok": false, "error": message }));
+        } else {
+            eprintln!("bluetooth-manager: {}", message);
+        }
+        ExitCode::from(code)
+    }
+}
+
+/// Runs one command against `backend` through the same `App` logic the
+/// TUI uses, and returns the process exit code.
+pub async fn run(
+    command: Command,
+    options: &Options,
+    mut app: App,
+    backend: BackendResult<Arc<dyn BluetoothBackend>>,
+) -> ExitCode {
+    let output = Output { json: options.json };
+    let mut input = BufReader::new(tokio::io::stdin());
+    let backend = match backend {
+        Ok(backend) => backend,
+        Err(err) => return output.failure(EXIT_UNAVAILABLE, &format!("BlueZ unavailable: {}", err)),
+    };
+    // A one-shot command must not start connections it will not wait for.
+    app.auto_reconnect = false;
+    if matches!(command, Command::Status { .. }) {
+        // A status bar runs next to the TUI for hours; saving its stale copy
+        // of the store would undo the TUI's edits.
+        app.known_devices.keep_in_memory();
+    }
+    let mut events = app.attach_backend(backend).await;
+
+    if let Some(name) = &options.adapter {
+        if !app.select_adapter(name) {
+            return output.failure(EXIT_NOT_FOUND, &format!("No adapter named {}", name));
+        }
+    }
+    // A status bar wants "powered": false rather than an error while the
+    // adapter is unplugged.
+    let needs_adapter = !matches!(command, Command::Status { .. });
+    if needs_adapter && app.current_adapter().is_none() {
+        return output.failure(EXIT_UNAVAILABLE, "No Bluetooth adapter found");
+    }
+    if let Some(warning) = app.error_message.take() {
+        eprintln!("bluetooth-manager: warning: {}", warning);
+    }
+
+    match command {
+        Command::List => {
+            let devices = adapter_devices(&app, options, |_| true);
+            let text = format!("{}\n\n{}", adapters_text(&app), devices_text(&devices));
+            output.success(
+                &text,
+                json!({ "adapters": app.adapters, "devices": devices, "rfkill": app.rfkill_switches }),
+            )
+        }
+        Command::Scan { timeout } => scan(&mut app, &mut events, options, &output, timeout).await,
+        Command::Connect(address) => {
+            let operation = DeviceOperation::Connect;
+            device_command(&mut app, &mut events, &mut input, options, &output, &address, operation)
+                .await
+        }
+        Command::Disconnect(address) => {
+            let operation = DeviceOperation::Disconnect;
+            device_command(&mut app, &mut events, &mut input, options, &output, &address, operation)
+                .await
+        }
+        Command::Pair(address) => {
+            app.register_agent().await;
+            if let Some(warning) = app.error_message.take() {
+                eprintln!("bluetooth-manager: warning: {}", warning);
+            }
+            let operation = DeviceOperation::Pair;
+            device_command(&mut app, &mut events, &mut input, options, &output, &address, operation)
+                .await
+        }
+        Command::Remove(address) => {
+            let Some(device) = find_device(&app, options, &address) else {
+                return output.failure(EXIT_NOT_FOUND, &not_found(&address));
+            };
+            app.select_device(&device.adapter, &device.address);
+            app.remove_device().await;
+            match app.error_message.take() {
+                Some(err) => output.failure(EXIT_FAILED, &err),
+                None => output.success(
+                    &app.status_message,
+                    json!({ "message": app.status_message, "device": device }),
+                ),
+            }
+        }
+        Command::Status { follow } => status(&mut app, &mut events, options, &output, follow).await,
+        Command::Power(powered) => {
+            app.set_bluetooth(powered).await;
+            if app.unblock_prompt {
+                let question = "Bluetooth is soft-blocked by rfkill. Unblock it? [y/N] ";
+                match read_answer(&mut input, question).await {
+                    Some(answer) if matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes") => {
+                        app.confirm_unblock().await;
+                    }
+                    _ => {
+                        app.cancel_unblock();
+                        return output.failure(EXIT_FAILED, "Bluetooth is soft-blocked by rfkill");
+                    }
+                }
+            }
+            match app.error_message.take() {
+                Some(err) => output.failure(EXIT_FAILED, &err),
+                None => output.success(
+                    &app.status_message,
+                    json!({ "message": app.status_message, "adapter": app.current_adapter() }),
+                ),
+            }
+        }
+    }
+}
+
+/// Prints the status once, or with `follow` again after every event that
+/// changes it, until BlueZ goes away or stdout is closed.
+async fn status(
+    app: &mut App,
+    events: &mut UnboundedReceiver<BluezEvent>,
+    options: &Options,
+    output: &Output,
+    follow: bool,
+) -> ExitCode {
+    let mut printed = status_json(app, options);
+    if !print_line(&printed) || !follow {
+        return ExitCode::SUCCESS;
+    }
+
+    let mut ticker = tokio::time::interval(STATUS_TICK);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => app.handle_bluez_event(event).await,
+                None => return output.failure(EXIT_FAILED, "Lost the connection to BlueZ"),
+            },
+            _ = ticker.tick() => app.on_tick(),
+        }
+        if let Some(err) = app.error_message.take() {
+            eprintln!("bluetooth-manager: {}", err);
+        }
+
+        let current = status_json(app, options);
+        if current != printed {
+            if !print_line(&current) {
+                return ExitCode::SUCCESS;
+            }
+            printed = current;
+        }
+    }
+}
+
+/// What a status bar shows: the power and rfkill state of the selected
+/// adapter and of each adapter, and the connected devices with their
+/// battery levels.
+fn status_json(app: &App, options: &Options) -> Value {
+    let adapter = app.current_adapter();
+    let adapters: Vec<Value> = app
+        .adapters
+        .iter()
+        .filter(|a| options.adapter.as_ref().is_none_or(|name| &a.name == name))
+        .map(|a| {
+            let switches = rfkill::adapter_switches(&app.rfkill_switches, &a.name);
+            json!({
+                "name": a.name,
+                "powered": a.powered,
+                "discovering": a.discovering,
+                "rfkill": BlockState::of(&switches).label().to_lowercase(),
+            })
+        })
+        .collect();
+    let connected: Vec<Value> = adapter_devices(app, options, |d| d.connected)
+        .into_iter()
+        .map(|d| {
+            json!({
+                "adapter": d.adapter,
+                "address": d.address,
+                "name": d.display_name(),
+                "type": d.device_type,
+                "battery": d.battery,
+            })
+        })
+        .collect();
+    json!({
+        "adapter": adapter.map(|a| &a.name),
+        "powered": adapter.is_some_and(|a| a.powered),
+        "discovering": adapter.is_some_and(|a| a.discovering),
+        "rfkill": app.adapter_block_state().label().to_lowercase(),
+        "adapters": adapters,
+        "connected": connected,
+    })
+}
+
+/// Writes one line to stdout. Returns false once the reader is gone, e.g.
+/// when the status bar restarts, instead of panicking like `println!`.
+fn print_line(value: &Value) -> bool {
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", value).and_then(|()| stdout.flush()).is_ok()
+}
+
+/// Discovers for `timeout` and prints the devices that were in range.
+async fn scan(
+    app: &mut App,
+    events: &mut UnboundedReceiver<BluezEvent>,
+    options: &Options,
+    output: &Output,
+    timeout: Duration,
+) -> ExitCode {
+    let already_scanning = app.scanning;
+    if !already_scanning {
+        app.toggle_scanning().await;
+        if let Some(err) = app.error_message.take() {
+            return output.failure(EXIT_FAILED, &err);
+        }
+    }
+
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            event = events.recv() => match event {
+                Some(event) => app.handle_bluez_event(event).await,
+                None => break,
+            },
+        }
+    }
+
+    // Leave discovery running if someone else started it.
+    if !already_scanning && app.scanning {
+        app.toggle_scanning().await;
+    }
+    let devices = adapter_devices(app, options, |d| d.connected || d.rssi.is_some());
+    output.success(&devices_text(&devices), json!({ "devices": devices }))
+}
+
+/// Connects, disconnects or pairs a device and waits for the outcome.
+async fn device_command(
+    app: &mut App,
+    events: &mut UnboundedReceiver<BluezEvent>,
+    input: &mut Input,
+    options: &Options,
+    output: &Output,
+    address: &str,
+    operation: DeviceOperation,
+) -> ExitCode {
+    let Some(device) = find_device(app, options, address) else {
+        return output.failure(EXIT_NOT_FOUND, &not_found(address));
+    };
+
+    let already = match operation {
+        DeviceOperation::Connect if device.connected => Some("connected"),
+        DeviceOperation::Disconnect if !device.connected => Some("disconnected"),
+        DeviceOperation::Pair if device.paired => Some("paired"),
+        _ => None,
+    };
+    if let Some(state) = already {
+        let message = format!("{} is already {}", device.display_name(), state);
+        return output.success(&message, json!({ "message": message, "device": device }));
+    }
+
+    app.select_device(&device.adapter, &device.address);
+    app.start_device_operation(operation);
+    loop {
+        let Some(event) = events.recv().await else {
+            return output.failure(EXIT_FAILED, "Lost the connection to BlueZ");
+        };
+        let finished = matches!(
+            &event,
+            BluezEvent::OperationFinished { adapter, address, .. }
+                if device.is(adapter, address)
+        );
+        app.handle_bluez_event(event).await;
+        answer_agent_request(app, input).await;
+        if finished {
+            break;
+        }
+    }
+
+    if let Some(err) = app.error_message.take() {
+        return output.failure(EXIT_FAILED, &err);
+    }
+    let updated = app
+        .devices
+        .iter()
+        .find(|d| d.is(&device.adapter, &device.address))
+        .unwrap_or(&device);
+    output.success(
+        &app.status_message,
+        json!({ "message": app.status_message, "device": updated }),
+    )
+}
+
+/// Answers pairing prompts on the terminal, the way the TUI dialog does.
+/// Yes/no questions default to no, so unattended pairing never accepts
+/// a passkey by itself.
+async fn answer_agent_request(app: &mut App, input: &mut Input) {
+    while let Some(request) = &app.agent_request {
+        let device = app.device_name(&request.adapter, &request.address);
+        let needs_input = request.prompt.needs_input();
+        let question = match &request.prompt {
+            AgentPrompt::DisplayPinCode(pin) => {
+                eprintln!("Type the PIN code {} on {}", pin, device);
+                None
+            }
+            AgentPrompt::DisplayPasskey { passkey, .. } => {
+                eprintln!("Type the passkey {:06} on {}", passkey, device);
+                None
+            }
+            AgentPrompt::RequestPinCode => Some(format!("PIN code for {}: ", device)),
+            AgentPrompt::RequestPasskey => Some(format!("Passkey shown on {}: ", device)),
+            AgentPrompt::RequestConfirmation(passkey) => Some(format!(
+                "Does {} show the passkey {:06}? [y/N] ",
+                device, passkey
+            )),
+            AgentPrompt::RequestAuthorization => Some(format!("Allow {} to pair? [y/N] ", device)),
+            AgentPrompt::AuthorizeService(uuid) => Some(format!(
+                "Allow {} to use {}? [y/N] ",
+                device,
+                profile_label(uuid)
+            )),
+            AgentPrompt::AuthorizePush { name, .. } => {
+                Some(format!("Accept {} from {}? [y/N] ", name, device))
+            }
+        };
+
+        let Some(question) = question else {
+            app.accept_agent_request();
+            continue;
+        };
+        match read_answer(input, &question).await {
+            Some(answer) if needs_input => {
+                app.agent_input = answer;
+                app.accept_agent_request();
+                // Invalid input keeps the request pending; ask again.
+                if let Some(err) = app.error_message.take() {
+                    eprintln!("{}", err);
+                }
+            }
+            Some(answer) if matches!(answer.to_ascii_lowercase().as_str(), "y" | "yes") => {
+                app.accept_agent_request();
+            }
+            _ => app.reject_agent_request(),
+        }
+    }
+}
+
+/// Asks on stderr and reads one line from stdin; None at end of input.
+async fn read_answer(input: &mut Input, question: &str) -> Option<String> {
+    eprint!("{}", question);
+    let _ = std::io::stderr().flush();
+
+    let mut line = String::new();
+    match input.read_line(&mut line).await {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+/// The device with `address`, on `--adapter` if given, else preferably on
+/// the selected adapter.
+fn find_device(app: &App, options: &Options, address: &str) -> Option<BluetoothDevice> {
+    let mut matches = app.devices.iter().filter(|d| {
+        d.address == address && options.adapter.as_ref().is_none_or(|name| &d.adapter == name)
+    });
+    let selected = app.selected_adapter.as_deref().unwrap_or_default();
+    let first = matches.next()?;
+    let device = if first.adapter == selected {
+        first
+    } else {
+        matches.find(|d| d.adapter == selected).unwrap_or(first)
+    };
+    Some(device.clone())
+}
+
+fn not_found(address: &str) -> String {
+    format!("Device {} not found (run 'scan' first)", address)
+}
+
+/// Devices on `--adapter` (or every adapter) that match `filter`.
+fn adapter_devices<'a>(
+    app: &'a App,
+    options: &Options,
+    filter: impl Fn(&BluetoothDevice) -> bool,
+) -> Vec<&'a BluetoothDevice> {
+    app.devices
+        .iter()
+        .filter(|d| options.adapter.as_ref().is_none_or(|name| &d.adapter == name))
+        .filter(|d| filter(d))
+        .collect()
+}
+
+fn adapters_text(app: &App) -> String {
+    app.adapters
+        .iter()
+        .map(|a| {
+            let block = match app.rfkill_switches.iter().find(|s| s.name == a.name) {
+                Some(switch) if switch.hard => " (hard-blocked)",
+                Some(switch) if switch.soft => " (soft-blocked)",
+                _ => "",
+            };
+            format!(
+                "{:<6} {} {:<20} {}{}",
+                a.name,
+                a.address,
+                a.alias,
+                if a.powered { "on" } else { "off" },
+                block
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn devices_text(devices: &[&BluetoothDevice]) -> String {
+    if devices.is_empty() {
+        return "No devices found".to_string();
+    }
+
+    devices
+        .iter()
+        .map(|d| {
+            let status = if d.connected {
+                "connected"
+            } else if d.paired {
+                "paired"
+            } else {
+                "available"
+            };
+            let rssi = d.rssi.map(|r| format!("{} dBm", r)).unwrap_or_default();
+            let battery = d.battery.map(|b| format!("{}%", b)).unwrap_or_default();
+            format!(
+                "{} {:<6} {:<24} {:<12} {:<10} {:>8} {:>4}",
+                d.address,
+                d.adapter,
+                d.display_name(),
+                d.device_type.label(),
+                status,
+                rssi,
+                battery
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendError;
+    use crate::rfkill::RfkillSwitch;
+    use crate::simulated::SimulatedBackend;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    async fn run_with(args: &[&str], backend: SimulatedBackend) -> ExitCode {
+        let options = parse(args).unwrap();
+        let command = options.command.clone().unwrap();
+        run(command, &options, App::new(), Ok(Arc::new(backend))).await
+    }
+
+    #[test]
+    fn no_command_starts_the_tui() {
+        let options = parse(&["--simulate"]).unwrap();
+        assert_eq!(options.command, None);
+        assert!(options.simulate);
+    }
+
+    #[test]
+    fn options_may_come_after_the_command() {
+        let args = ["connect", "aa:bb:cc:dd:ee:ff", "--json", "--adapter", "hci1"];
+        let options = parse(&args).unwrap();
+        let address = "AA:BB:CC:DD:EE:FF".to_string();
+        assert_eq!(options.command, Some(Command::Connect(address)));
+        assert!(options.json);
+        assert_eq!(options.adapter.as_deref(), Some("hci1"));
+    }
+
+    #[test]
+    fn scan_timeout() {
+        let default = parse(&["scan"]).unwrap();
+        assert_eq!(default.command, Some(Command::Scan { timeout: DEFAULT_SCAN_TIMEOUT }));
+        let given = parse(&["--timeout", "5", "scan"]).unwrap();
+        assert_eq!(given.command, Some(Command::Scan { timeout: Duration::from_secs(5) }));
+        assert!(parse(&["list", "--timeout", "5"]).is_err());
+        assert!(parse(&["scan", "--timeout", "soon"]).is_err());
+    }
+
+    #[test]
+    fn power_takes_on_or_off() {
+        assert_eq!(parse(&["power", "on"]).unwrap().command, Some(Command::Power(true)));
+        assert_eq!(parse(&["power", "off"]).unwrap().command, Some(Command::Power(false)));
+        assert!(parse(&["power", "maybe"]).is_err());
+    }
+
+    #[test]
+    fn wrong_arguments_are_usage_errors() {
+        let errors = [
+            parse(&["frobnicate"]),
+            parse(&["connect"]),
+            parse(&["list", "extra"]),
+            parse(&["--adapter"]),
+            parse(&["--verbose", "list"]),
+        ];
+        for error in errors {
+            assert!(error.is_err());
+        }
+    }
+
+    #[test]
+    fn help_wins_over_a_bad_command() {
+        let options = parse(&["frobnicate", "--help"]).unwrap();
+        assert!(options.help);
+        assert_eq!(options.command, None);
+    }
+
+    #[test]
+    fn status_and_follow() {
+        let status = parse(&["--status"]).unwrap();
+        assert_eq!(status.command, Some(Command::Status { follow: false }));
+        let follow = parse(&["--follow", "--status", "--adapter", "hci0"]).unwrap();
+        assert_eq!(follow.command, Some(Command::Status { follow: true }));
+        assert_eq!(
+            parse(&["--status", "list"]).unwrap_err(),
+            "--status cannot be combined with list"
+        );
+        assert_eq!(parse(&["--follow"]).unwrap_err(), "--follow only applies to --status");
+    }
+
+    #[tokio::test]
+    async fn unavailable_bluez_exits_3() {
+        let options = parse(&["list"]).unwrap();
+        let backend = Err(BackendError("org.freedesktop.DBus.Error.ServiceUnknown".to_string()));
+        let code = run(Command::List, &options, App::new(), backend).await;
+        assert_eq!(code, ExitCode::from(EXIT_UNAVAILABLE));
+    }
+
+    #[tokio::test]
+    async fn missing_adapter_exits_3() {
+        let code = run_with(&["list"], SimulatedBackend::new()).await;
+        assert_eq!(code, ExitCode::from(EXIT_UNAVAILABLE));
+    }
+
+    #[tokio::test]
+    async fn unknown_adapter_or_device_exits_4() {
+        let code = run_with(&["list", "--adapter", "hci9"], SimulatedBackend::demo()).await;
+        assert_eq!(code, ExitCode::from(EXIT_NOT_FOUND));
+        let code = run_with(&["connect", "01:02:03:04:05:06"], SimulatedBackend::demo()).await;
+        assert_eq!(code, ExitCode::from(EXIT_NOT_FOUND));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn device_commands_succeed() {
+        let commands: [&[&str]; 4] = [
+            &["list"],
+            // Already connected is not an error.
+            &["connect", "00:1B:66:A1:B2:C3"],
+            &["disconnect", "00:1b:66:a1:b2:c3"],
+            &["power", "on", "--adapter", "hci1"],
+        ];
+        for args in commands {
+            assert_eq!(run_with(args, SimulatedBackend::demo()).await, ExitCode::SUCCESS);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refused_operation_exits_1() {
+        let address = "12:34:56:78:9A:BC".to_string();
+        let mut device = BluetoothDevice::new(address, "Sensor".to_string());
+        device.adapter = "hci1".to_string();
+        let backend = SimulatedBackend::demo().with_device(device);
+        // hci1 is powered off.
+        let code = run_with(&["connect", "12:34:56:78:9A:BC"], backend).await;
+        assert_eq!(code, ExitCode::from(EXIT_FAILED));
+    }
+
+    #[tokio::test]
+    async fn status_json_shape() {
+        let mut app = App::new();
+        app.attach_backend(Arc::new(SimulatedBackend::demo())).await;
+        assert_eq!(
+            status_json(&app, &Options::default()),
+            json!({
+                "adapter": "hci0",
+                "powered": true,
+                "discovering": false,
+                "rfkill": "unblocked",
+                "adapters": [
+                    { "name": "hci0", "powered": true, "discovering": false, "rfkill": "unblocked" },
+                    { "name": "hci1", "powered": false, "discovering": false, "rfkill": "unblocked" },
+                ],
+                "connected": [{
+                    "adapter": "hci0",
+                    "address": "00:1B:66:A1:B2:C3",
+                    "name": "WH-1000XM4",
+                    "type": "headphones",
+                    "battery": 24,
+                }],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn status_json_without_adapter() {
+        let mut app = App::new();
+        app.attach_backend(Arc::new(SimulatedBackend::new())).await;
+        assert_eq!(
+            status_json(&app, &Options::default()),
+            json!({
+                "adapter": null,
+                "powered": false,
+                "discovering": false,
+                "rfkill": "unblocked",
+                "adapters": [],
+                "connected": [],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn status_json_reports_rfkill_per_adapter() {
+        let mut app = App::new();
+        app.attach_backend(Arc::new(SimulatedBackend::demo())).await;
+        app.rfkill_switches = vec![
+            RfkillSwitch { index: 0, name: "hci0".to_string(), soft: false, hard: false },
+            RfkillSwitch { index: 1, name: "hci1".to_string(), soft: true, hard: false },
+        ];
+        let status = status_json(&app, &Options::default());
+        assert_eq!(status["rfkill"], "unblocked");
+        assert_eq!(status["adapters"][0]["rfkill"], "unblocked");
+        assert_eq!(status["adapters"][1]["rfkill"], "soft-blocked");
+
+        assert!(app.select_adapter("hci1"));
+        assert_eq!(status_json(&app, &Options::default())["rfkill"], "soft-blocked");
+    }
+}
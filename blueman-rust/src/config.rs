@@ -0,0 +1,215 @@
+use crate::event_log::{LogLevel, DEFAULT_LOG_SIZE};
+use crate::keymap::Keymap;
+use crate::known_devices::CONFIG_DIR;
+use crate::theme::Theme;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// A key, or a list of keys, bound to an action.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            KeyList::One(key) => vec![key],
+            KeyList::Many(keys) => keys,
+        }
+    }
+}
+
+/// On-disk layout. Every table is optional:
+///
+/// ```toml
+/// [keys.list]
+/// scan = ["s", "F5"]
+/// up = ["Up", "k"]
+///
+/// [theme]
+/// accent = "light-blue"
+/// selection-bg = "#44475a"
+///
+/// [log]
+/// size = 1000
+/// notifications = true
+/// notify-level = "info"
+/// ```
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    keys: HashMap<String, HashMap<String, KeyList>>,
+    #[serde(default)]
+    theme: HashMap<String, String>,
+    #[serde(default)]
+    log: LogFile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct LogFile {
+    size: Option<usize>,
+    notifications: Option<bool>,
+    notify_level: Option<String>,
+}
+
+/// The `[log]` table: how many events the log keeps and which of them are
+/// also sent as desktop notifications.
+#[derive(Debug, Clone, Copy)]
+pub struct LogConfig {
+    pub size: usize,
+    pub notifications: bool,
+    pub notify_level: LogLevel,
+}
+
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig {
+            size: DEFAULT_LOG_SIZE,
+            notifications: false,
+            notify_level: LogLevel::Warning,
+        }
+    }
+}
+
+/// User preferences read from `config.toml`: key bindings, colours and
+/// the event log.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub log: LogConfig,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/bluetooth-manager/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// Reads the config at `path`; a missing file means the defaults.
+    pub fn load(path: &Path) -> io::Result<Config> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(err),
+        };
+        let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidData, err);
+
+        let file: ConfigFile = toml::from_str(&contents).map_err(|err| invalid(err.to_string()))?;
+        let keys = file
+            .keys
+            .into_iter()
+            .map(|(section, actions)| {
+                let actions = actions
+                    .into_iter()
+                    .map(|(action, keys)| (action, keys.into_vec()))
+                    .collect();
+                (section, actions)
+            })
+            .collect();
+
+        let defaults = LogConfig::default();
+        let log = LogConfig {
+            size: match file.log.size {
+                Some(0) => return Err(invalid("[log] size: must be at least 1".to_string())),
+                Some(size) => size,
+                None => defaults.size,
+            },
+            notifications: file.log.notifications.unwrap_or(defaults.notifications),
+            notify_level: match file.log.notify_level {
+                Some(level) => level
+                    .parse()
+                    .map_err(|err| invalid(format!("[log] notify-level: {}", err)))?,
+                None => defaults.notify_level,
+            },
+        };
+
+        Ok(Config {
+            keymap: Keymap::with_overrides(&keys).map_err(invalid)?,
+            theme: Theme::with_overrides(&file.theme).map_err(invalid)?,
+            log,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{Action, Key};
+    use crossterm::event::KeyCode;
+    use ratatui::style::Color;
+
+    fn load(name: &str, contents: &str) -> io::Result<Config> {
+        let dir = std::env::temp_dir().join(format!(
+            "bluetooth-manager-config-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE);
+        fs::write(&path, contents).unwrap();
+        let config = Config::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        config
+    }
+
+    #[test]
+    fn a_missing_file_means_the_defaults() {
+        let path = std::env::temp_dir().join("bluetooth-manager-config-missing/config.toml");
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.theme, Theme::default());
+        assert_eq!(config.log.size, DEFAULT_LOG_SIZE);
+        assert_eq!(
+            config.keymap.list.key(Action::ToggleScan),
+            Some(Key(KeyCode::Char('s')))
+        );
+    }
+
+    #[test]
+    fn tables_override_the_defaults() {
+        let config = load(
+            "tables",
+            "[keys.list]\nscan = \"F5\"\nup = [\"Up\", \"w\"]\n\n\
+             [theme]\naccent = \"magenta\"\n\n\
+             [log]\nsize = 10\nnotifications = true\nnotify-level = \"info\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.keymap.list.key(Action::ToggleScan),
+            Some(Key(KeyCode::F(5)))
+        );
+        assert_eq!(
+            config.keymap.list.action(KeyCode::Char('w')),
+            Some(Action::SelectPrevious)
+        );
+        assert_eq!(config.theme.accent, Color::Magenta);
+        assert_eq!(config.log.size, 10);
+        assert!(config.log.notifications);
+        assert_eq!(config.log.notify_level, LogLevel::Info);
+    }
+
+    #[test]
+    fn invalid_files_are_errors() {
+        for (name, contents) in [
+            ("syntax", "[keys.list\n"),
+            ("table", "[colours]\naccent = \"red\"\n"),
+            ("size", "[log]\nsize = 0\n"),
+            ("level", "[log]\nnotify-level = \"loud\"\n"),
+            ("keys", "[keys.list]\nscan = \"s\"\nsearch = \"s\"\n"),
+        ] {
+            let err = load(name, contents).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", name);
+        }
+    }
+}
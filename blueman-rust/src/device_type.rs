@@ -0,0 +1,322 @@
+use serde::Serialize;
+
+/// What kind of device a remote is, decoded from the BlueZ `Class`,
+/// `Appearance` and `Icon` properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceType {
+    Computer,
+    Phone,
+    Tablet,
+    Headset,
+    Headphones,
+    Speaker,
+    Microphone,
+    Keyboard,
+    Mouse,
+    Gamepad,
+    Watch,
+    Display,
+    Camera,
+    Printer,
+    Network,
+    Sensor,
+    Tag,
+    #[default]
+    Unknown,
+}
+
+impl DeviceType {
+    /// Decodes the most specific type available. The class of device is the
+    /// most detailed for classic devices, the appearance for LE ones, and the
+    /// icon is BlueZ's own coarse guess from either.
+    pub fn decode(class: Option<u32>, appearance: Option<u16>, icon: Option<&str>) -> DeviceType {
+        [
+            class.map(DeviceType::from_class),
+            appearance.map(DeviceType::from_appearance),
+            icon.map(DeviceType::from_icon),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|kind| *kind != DeviceType::Unknown)
+        .unwrap_or_default()
+    }
+
+    /// Major/minor device class from the Bluetooth Assigned Numbers.
+    pub fn from_class(class: u32) -> DeviceType {
+        let major = (class >> 8) & 0x1f;
+        let minor = (class >> 2) & 0x3f;
+
+        match major {
+            0x01 => match minor {
+                0x06 => DeviceType::Watch,
+                0x07 => DeviceType::Tablet,
+                _ => DeviceType::Computer,
+            },
+            0x02 => DeviceType::Phone,
+            0x03 => DeviceType::Network,
+            0x04 => match minor {
+                0x01 | 0x02 => DeviceType::Headset,
+                0x04 => DeviceType::Microphone,
+                0x06 => DeviceType::Headphones,
+                0x05 | 0x07 | 0x08 | 0x0a => DeviceType::Speaker,
+                0x0c | 0x0d => DeviceType::Camera,
+                0x0e | 0x0f => DeviceType::Display,
+                0x12 => DeviceType::Gamepad,
+                _ => DeviceType::Unknown,
+            },
+            0x05 => match (minor >> 4, minor & 0x0f) {
+                (0x01 | 0x03, _) => DeviceType::Keyboard,
+                (0x02, _) => DeviceType::Mouse,
+                (_, 0x01 | 0x02) => DeviceType::Gamepad,
+                (_, 0x04) => DeviceType::Sensor,
+                (_, 0x05) => DeviceType::Tablet,
+                _ => DeviceType::Unknown,
+            },
+            0x06 => {
+                if class & 0xc0 != 0 {
+                    DeviceType::Printer
+                } else if class & 0x20 != 0 {
+                    DeviceType::Camera
+                } else if class & 0x10 != 0 {
+                    DeviceType::Display
+                } else {
+                    DeviceType::Unknown
+                }
+            }
+            0x07 => match minor {
+                0x01 => DeviceType::Watch,
+                _ => DeviceType::Unknown,
+            },
+            0x08 => match minor {
+                0x04 | 0x05 => DeviceType::Gamepad,
+                _ => DeviceType::Unknown,
+            },
+            0x09 => DeviceType::Sensor,
+            _ => DeviceType::Unknown,
+        }
+    }
+
+    /// GAP appearance: a 10-bit category followed by a 6-bit subcategory.
+    pub fn from_appearance(appearance: u16) -> DeviceType {
+        let category = appearance >> 6;
+        let subcategory = appearance & 0x3f;
+
+        match category {
+            0x001 => DeviceType::Phone,
+            0x002 => match subcategory {
+                0x06 => DeviceType::Watch,
+                0x07 => DeviceType::Tablet,
+                _ => DeviceType::Computer,
+            },
+            0x003 => DeviceType::Watch,
+            0x005 => DeviceType::Display,
+            0x008 | 0x009 => DeviceType::Tag,
+            0x00a => DeviceType::Speaker,
+            0x00c..=0x00e | 0x010..=0x012 | 0x015 | 0x031 | 0x032 => DeviceType::Sensor,
+            0x014 => DeviceType::Network,
+            0x00f => match subcategory {
+                0x01 => DeviceType::Keyboard,
+                0x02 => DeviceType::Mouse,
+                0x03 | 0x04 => DeviceType::Gamepad,
+                0x05 => DeviceType::Tablet,
+                _ => DeviceType::Unknown,
+            },
+            0x021 => DeviceType::Speaker,
+            0x022 => DeviceType::Microphone,
+            0x025 => match subcategory {
+                0x03 => DeviceType::Headphones,
+                _ => DeviceType::Headset,
+            },
+            _ => DeviceType::Unknown,
+        }
+    }
+
+    /// The freedesktop icon name BlueZ exposes as `Icon`.
+    pub fn from_icon(icon: &str) -> DeviceType {
+        match icon {
+            "computer" => DeviceType::Computer,
+            "phone" | "modem" => DeviceType::Phone,
+            "input-tablet" => DeviceType::Tablet,
+            "audio-headset" => DeviceType::Headset,
+            "audio-headphones" => DeviceType::Headphones,
+            "audio-card" | "multimedia-player" => DeviceType::Speaker,
+            "audio-input-microphone" => DeviceType::Microphone,
+            "input-keyboard" => DeviceType::Keyboard,
+            "input-mouse" => DeviceType::Mouse,
+            "input-gaming" => DeviceType::Gamepad,
+            "video-display" => DeviceType::Display,
+            "camera-photo" | "camera-video" => DeviceType::Camera,
+            "printer" | "scanner" => DeviceType::Printer,
+            "network-wireless" => DeviceType::Network,
+            _ => DeviceType::Unknown,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeviceType::Computer => "Computer",
+            DeviceType::Phone => "Phone",
+            DeviceType::Tablet => "Tablet",
+            DeviceType::Headset => "Headset",
+            DeviceType::Headphones => "Headphones",
+            DeviceType::Speaker => "Speaker",
+            DeviceType::Microphone => "Microphone",
+            DeviceType::Keyboard => "Keyboard",
+            DeviceType::Mouse => "Mouse",
+            DeviceType::Gamepad => "Gamepad",
+            DeviceType::Watch => "Watch",
+            DeviceType::Display => "Display",
+            DeviceType::Camera => "Camera",
+            DeviceType::Printer => "Printer",
+            DeviceType::Network => "Network",
+            DeviceType::Sensor => "Sensor",
+            DeviceType::Tag => "Tag",
+            DeviceType::Unknown => "Unknown",
+        }
+    }
+
+    /// Single-width symbol shown in front of the label in the device table.
+    pub fn glyph(&self) -> char {
+        match self {
+            DeviceType::Computer => '▣',
+            DeviceType::Phone => '✆',
+            DeviceType::Tablet => '▯',
+            DeviceType::Headset | DeviceType::Headphones => '♫',
+            DeviceType::Speaker => '♪',
+            DeviceType::Microphone => '◖',
+            DeviceType::Keyboard => '⌨',
+            DeviceType::Mouse => '⌖',
+            DeviceType::Gamepad => '✚',
+            DeviceType::Watch => '◷',
+            DeviceType::Display => '▭',
+            DeviceType::Camera => '◉',
+            DeviceType::Printer => '⎙',
+            DeviceType::Network => '⇄',
+            DeviceType::Sensor => '∿',
+            DeviceType::Tag => '◈',
+            DeviceType::Unknown => '·',
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_of_device_major_and_minor() {
+        let cases = [
+            (0x00010c, DeviceType::Computer),   // laptop
+            (0x000118, DeviceType::Watch),      // wearable computer
+            (0x00011c, DeviceType::Tablet),     // palm-size computer
+            (0x5a020c, DeviceType::Phone),      // smartphone
+            (0x000300, DeviceType::Network),    // LAN access point
+            (0x240404, DeviceType::Headset),    // wearable headset
+            (0x200408, DeviceType::Headset),    // hands-free
+            (0x200410, DeviceType::Microphone), // microphone
+            (0x200418, DeviceType::Headphones), // headphones
+            (0x240414, DeviceType::Speaker),    // loudspeaker
+            (0x200420, DeviceType::Speaker),    // car audio
+            (0x200430, DeviceType::Camera),     // video camera
+            (0x20043c, DeviceType::Display),    // video display and loudspeaker
+            (0x200448, DeviceType::Gamepad),    // gaming toy
+            (0x002540, DeviceType::Keyboard),   // keyboard
+            (0x002580, DeviceType::Mouse),      // pointing device
+            (0x0025c0, DeviceType::Keyboard),   // combo keyboard and mouse
+            (0x002508, DeviceType::Gamepad),    // gamepad
+            (0x002510, DeviceType::Sensor),     // sensing device
+            (0x002514, DeviceType::Tablet),     // digitizer tablet
+            (0x000680, DeviceType::Printer),    // printer
+            (0x000620, DeviceType::Camera),     // camera
+            (0x000610, DeviceType::Display),    // display
+            (0x000704, DeviceType::Watch),      // wristwatch
+            (0x000814, DeviceType::Gamepad),    // game controller
+            (0x000900, DeviceType::Sensor),     // health
+        ];
+        for (class, expected) in cases {
+            assert_eq!(DeviceType::from_class(class), expected, "{:#08x}", class);
+        }
+    }
+
+    #[test]
+    fn unknown_and_reserved_classes() {
+        let cases = [
+            0x000000, // miscellaneous
+            0x001f00, // uncategorized
+            0x000400, // audio/video, uncategorized
+            0x000444, // audio/video, reserved minor class
+            0x002500, // peripheral, uncategorized
+            0x000600, // imaging without a minor bit
+            0x000708, // wearable pager
+            0x000a00, // reserved major class
+        ];
+        for class in cases {
+            assert_eq!(
+                DeviceType::from_class(class),
+                DeviceType::Unknown,
+                "{:#08x}",
+                class
+            );
+        }
+    }
+
+    #[test]
+    fn appearance_categories() {
+        let cases = [
+            (0x0040, DeviceType::Phone),
+            (0x0080, DeviceType::Computer),
+            (0x0086, DeviceType::Watch),
+            (0x00c1, DeviceType::Watch),
+            (0x0140, DeviceType::Display),
+            (0x0200, DeviceType::Tag),
+            (0x0280, DeviceType::Speaker),
+            (0x0340, DeviceType::Sensor),
+            (0x03c1, DeviceType::Keyboard),
+            (0x03c2, DeviceType::Mouse),
+            (0x03c4, DeviceType::Gamepad),
+            (0x03c5, DeviceType::Tablet),
+            (0x0500, DeviceType::Network),
+            (0x0841, DeviceType::Speaker),
+            (0x0880, DeviceType::Microphone),
+            (0x0941, DeviceType::Headset),
+            (0x0943, DeviceType::Headphones),
+        ];
+        for (appearance, expected) in cases {
+            assert_eq!(
+                DeviceType::from_appearance(appearance),
+                expected,
+                "{:#06x}",
+                appearance
+            );
+        }
+        // Unknown, generic HID, and reserved categories
+        for appearance in [0x0000, 0x03c0, 0x0fc0, 0xffff] {
+            assert_eq!(
+                DeviceType::from_appearance(appearance),
+                DeviceType::Unknown,
+                "{:#06x}",
+                appearance
+            );
+        }
+    }
+
+    #[test]
+    fn decode_prefers_class_then_appearance_then_icon() {
+        let decode = DeviceType::decode;
+        assert_eq!(
+            decode(Some(0x240404), Some(0x03c2), Some("phone")),
+            DeviceType::Headset
+        );
+        assert_eq!(
+            decode(Some(0x000000), Some(0x03c2), Some("phone")),
+            DeviceType::Mouse
+        );
+        assert_eq!(
+            decode(None, Some(0x0000), Some("input-gaming")),
+            DeviceType::Gamepad
+        );
+        assert_eq!(decode(None, None, Some("weird-icon")), DeviceType::Unknown);
+        assert_eq!(decode(None, None, None), DeviceType::Unknown);
+    }
+}
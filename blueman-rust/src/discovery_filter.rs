@@ -0,0 +1,528 @@
+use crate::known_devices::CONFIG_DIR;
+use crate::profiles::{profile_label, sig_uuid};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const DISCOVERY_FILTER_FILE: &str = "discovery_filter.toml";
+
+/// Steps of the RSSI and pathloss limits in the Settings view.
+const RSSI_STEP: i16 = 5;
+const PATHLOSS_STEP: u16 = 5;
+
+// Ranges accepted by SetDiscoveryFilter
+const RSSI_RANGE: (i16, i16) = (-127, 20);
+const PATHLOSS_RANGE: (u16, u16) = (0, 137);
+
+// Where a limit starts when it is switched on from the keyboard
+const RSSI_START: i16 = -90;
+const PATHLOSS_START: u16 = 60;
+
+/// Which radio `StartDiscovery` listens on.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Auto, // interleaved, both at once when the adapter can
+    Bredr, // classic only
+    Le,
+}
+
+impl Transport {
+    /// The `Transport` value of the filter dictionary.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transport::Auto => "auto",
+            Transport::Bredr => "bredr",
+            Transport::Le => "le",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Transport::Auto => "Auto (classic and LE)",
+            Transport::Bredr => "Classic (BR/EDR)",
+            Transport::Le => "Low Energy",
+        }
+    }
+
+    pub fn next(&self) -> Transport {
+        match self {
+            Transport::Auto => Transport::Bredr,
+            Transport::Bredr => Transport::Le,
+            Transport::Le => Transport::Auto,
+        }
+    }
+
+    pub fn previous(&self) -> Transport {
+        self.next().next()
+    }
+}
+
+/// The fields of the filter, in the order the Settings view lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FilterField {
+    #[default]
+    Transport,
+    Rssi,
+    Pathloss,
+    Uuids,
+    DuplicateData,
+}
+
+impl FilterField {
+    pub const ALL: [FilterField; 5] = [
+        FilterField::Transport,
+        FilterField::Rssi,
+        FilterField::Pathloss,
+        FilterField::Uuids,
+        FilterField::DuplicateData,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterField::Transport => "Transport",
+            FilterField::Rssi => "Minimum RSSI",
+            FilterField::Pathloss => "Maximum Pathloss",
+            FilterField::Uuids => "Service UUIDs",
+            FilterField::DuplicateData => "Duplicate Data",
+        }
+    }
+
+    pub fn next(&self) -> FilterField {
+        let index = FilterField::ALL.iter().position(|f| f == self).unwrap_or(0);
+        FilterField::ALL[(index + 1) % FilterField::ALL.len()]
+    }
+
+    pub fn previous(&self) -> FilterField {
+        let index = FilterField::ALL.iter().position(|f| f == self).unwrap_or(0);
+        FilterField::ALL[(index + FilterField::ALL.len() - 1) % FilterField::ALL.len()]
+    }
+
+    /// Whether Enter opens a text editor for the field.
+    pub fn is_typed(&self) -> bool {
+        matches!(self, FilterField::Rssi | FilterField::Pathloss | FilterField::Uuids)
+    }
+}
+
+/// Arguments of `Adapter1.SetDiscoveryFilter`, remembered between runs in
+/// `discovery_filter.toml`. The default reports everything, like a scan
+/// without a filter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DiscoveryFilter {
+    pub transport: Transport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rssi: Option<i16>, // dBm; weaker devices are not reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pathloss: Option<u16>, // dB; BlueZ takes either this or rssi, not both
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub uuids: Vec<String>, // full 128-bit; empty reports every device
+    pub duplicate_data: bool, // report every advertisement, not only changes
+}
+
+impl Default for DiscoveryFilter {
+    fn default() -> DiscoveryFilter {
+        DiscoveryFilter {
+            transport: Transport::Auto,
+            rssi: None,
+            pathloss: None,
+            uuids: vec![],
+            duplicate_data: true,
+        }
+    }
+}
+
+impl DiscoveryFilter {
+    /// `$XDG_CONFIG_HOME/bluetooth-manager/discovery_filter.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(DISCOVERY_FILTER_FILE))
+    }
+
+    /// Reads the filter saved at `path`; a missing file is no filter.
+    pub fn load(path: &Path) -> io::Result<DiscoveryFilter> {
+        let filter: DiscoveryFilter = match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(DiscoveryFilter::default()),
+            Err(err) => return Err(err),
+        };
+        filter
+            .validate()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(filter)
+    }
+
+    /// Writes the filter through a temporary file, like the known devices.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)
+    }
+
+    /// Checks what BlueZ would refuse with `InvalidArguments`.
+    fn validate(&self) -> Result<(), String> {
+        if self.rssi.is_some() && self.pathloss.is_some() {
+            return Err("rssi and pathloss cannot both be set".to_string());
+        }
+        if let Some(rssi) = self.rssi {
+            parse_rssi(&rssi.to_string())?;
+        }
+        if let Some(pathloss) = self.pathloss {
+            parse_pathloss(&pathloss.to_string())?;
+        }
+        for uuid in &self.uuids {
+            parse_uuid(uuid)?;
+        }
+        Ok(())
+    }
+
+    /// Whether discovery reports every device, as without a filter.
+    pub fn is_empty(&self) -> bool {
+        *self == DiscoveryFilter::default()
+    }
+
+    /// Raises or lowers the RSSI limit by one step. Lowering it past the
+    /// weakest signal turns it off; turning it on clears the pathloss.
+    pub fn step_rssi(&mut self, up: bool) {
+        self.rssi = match (self.rssi, up) {
+            (None, true) => Some(RSSI_START),
+            (None, false) => None,
+            (Some(rssi), true) => Some((rssi + RSSI_STEP).min(RSSI_RANGE.1)),
+            (Some(rssi), false) if rssi - RSSI_STEP < RSSI_RANGE.0 => None,
+            (Some(rssi), false) => Some(rssi - RSSI_STEP),
+        };
+        if self.rssi.is_some() {
+            self.pathloss = None;
+        }
+    }
+
+    /// Raises or lowers the pathloss limit by one step. Raising it past
+    /// the largest loss turns it off; turning it on clears the RSSI.
+    pub fn step_pathloss(&mut self, up: bool) {
+        self.pathloss = match (self.pathloss, up) {
+            (None, false) => Some(PATHLOSS_START),
+            (None, true) => None,
+            (Some(pathloss), false) => Some(pathloss.saturating_sub(PATHLOSS_STEP)),
+            (Some(pathloss), true) if pathloss + PATHLOSS_STEP > PATHLOSS_RANGE.1 => None,
+            (Some(pathloss), true) => Some(pathloss + PATHLOSS_STEP),
+        };
+        if self.pathloss.is_some() {
+            self.rssi = None;
+        }
+    }
+
+    /// The value of `field` as shown in the Settings view.
+    pub fn field_text(&self, field: FilterField) -> String {
+        match field {
+            FilterField::Transport => self.transport.label().to_string(),
+            FilterField::Rssi => match self.rssi {
+                Some(rssi) => format!("{} dBm", rssi),
+                None => "Off".to_string(),
+            },
+            FilterField::Pathloss => match self.pathloss {
+                Some(pathloss) => format!("{} dB", pathloss),
+                None => "Off".to_string(),
+            },
+            FilterField::Uuids if self.uuids.is_empty() => "Any".to_string(),
+            FilterField::Uuids => self
+                .uuids
+                .iter()
+                .map(|uuid| profile_label(uuid))
+                .collect::<Vec<_>>()
+                .join(", "),
+            FilterField::DuplicateData => {
+                if self.duplicate_data { "Reported" } else { "Dropped" }.to_string()
+            }
+        }
+    }
+
+    /// The value of `field` as the text editor starts with it.
+    pub fn field_input(&self, field: FilterField) -> String {
+        match field {
+            FilterField::Rssi => self.rssi.map(|rssi| rssi.to_string()).unwrap_or_default(),
+            FilterField::Pathloss => self.pathloss.map(|pathloss| pathloss.to_string()).unwrap_or_default(),
+            FilterField::Uuids => self.uuids.join(", "),
+            _ => String::new(),
+        }
+    }
+
+    /// Sets a typed field from the editor text; empty text turns it off.
+    pub fn set_field(&mut self, field: FilterField, text: &str) -> Result<(), String> {
+        let text = text.trim();
+        match field {
+            FilterField::Rssi => {
+                self.rssi = (!text.is_empty()).then(|| parse_rssi(text)).transpose()?;
+                if self.rssi.is_some() {
+                    self.pathloss = None;
+                }
+            }
+            FilterField::Pathloss => {
+                self.pathloss = (!text.is_empty()).then(|| parse_pathloss(text)).transpose()?;
+                if self.pathloss.is_some() {
+                    self.rssi = None;
+                }
+            }
+            FilterField::Uuids => {
+                let mut uuids = vec![];
+                for word in text.split([',', ' ']).filter(|word| !word.is_empty()) {
+                    let uuid = parse_uuid(word)?;
+                    if !uuids.contains(&uuid) {
+                        uuids.push(uuid);
+                    }
+                }
+                self.uuids = uuids;
+            }
+            FilterField::Transport | FilterField::DuplicateData => {}
+        }
+        Ok(())
+    }
+
+    /// One line for the status bar and the event log, e.g.
+    /// `le, RSSI ≥ -70 dBm, 1 UUID`.
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "none".to_string();
+        }
+        let mut parts = vec![self.transport.name().to_string()];
+        if let Some(rssi) = self.rssi {
+            parts.push(format!("RSSI ≥ {} dBm", rssi));
+        }
+        if let Some(pathloss) = self.pathloss {
+            parts.push(format!("pathloss ≤ {} dB", pathloss));
+        }
+        match self.uuids.len() {
+            0 => {}
+            1 => parts.push("1 UUID".to_string()),
+            count => parts.push(format!("{} UUIDs", count)),
+        }
+        if !self.duplicate_data {
+            parts.push("no duplicates".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+fn parse_rssi(text: &str) -> Result<i16, String> {
+    let text = text.trim_end_matches("dBm").trim();
+    match text.parse::<i16>() {
+        Ok(rssi) if (RSSI_RANGE.0..=RSSI_RANGE.1).contains(&rssi) => Ok(rssi),
+        _ => Err(format!(
+            "Invalid RSSI \"{}\" (dBm from {} to {})",
+            text, RSSI_RANGE.0, RSSI_RANGE.1
+        )),
+    }
+}
+
+fn parse_pathloss(text: &str) -> Result<u16, String> {
+    let text = text.trim_end_matches("dB").trim();
+    match text.parse::<u16>() {
+        Ok(pathloss) if (PATHLOSS_RANGE.0..=PATHLOSS_RANGE.1).contains(&pathloss) => Ok(pathloss),
+        _ => Err(format!(
+            "Invalid pathloss \"{}\" (dB from {} to {})",
+            text, PATHLOSS_RANGE.0, PATHLOSS_RANGE.1
+        )),
+    }
+}
+
+/// A full lowercase UUID. SIG services may be given by their 16-bit
+/// number (`180f` or `0x180F`).
+fn parse_uuid(text: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid UUID \"{}\"", text);
+    let short = text.trim_start_matches("0x").trim_start_matches("0X");
+    if short.len() == 4 && short.chars().all(|c| c.is_ascii_hexdigit()) {
+        return u16::from_str_radix(short, 16).map(sig_uuid).map_err(|_| invalid());
+    }
+
+    let uuid = text.to_ascii_lowercase();
+    let groups: Vec<&str> = uuid.split('-').collect();
+    let valid = groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|group| group.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        return Err(invalid());
+    }
+    Ok(uuid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BATTERY_SERVICE: &str = "0000180f-0000-1000-8000-00805f9b34fb";
+    const A2DP_SINK: &str = "0000110b-0000-1000-8000-00805f9b34fb";
+
+    fn temporary_file(name: &str) -> PathBuf {
+        let dir = format!("bluetooth-manager-{}-{}", name, std::process::id());
+        let dir = std::env::temp_dir().join(dir);
+        let _ = fs::remove_dir_all(&dir);
+        dir.join(DISCOVERY_FILTER_FILE)
+    }
+
+    #[test]
+    fn short_uuids_expand_to_the_sig_base() {
+        assert_eq!(parse_uuid("180f").unwrap(), BATTERY_SERVICE);
+        assert_eq!(parse_uuid("0x180F").unwrap(), BATTERY_SERVICE);
+        assert_eq!(parse_uuid("0000110B-0000-1000-8000-00805F9B34FB").unwrap(), A2DP_SINK);
+    }
+
+    #[test]
+    fn malformed_uuids_are_rejected() {
+        let malformed = [
+            "",
+            "18f",
+            "180g",
+            "0x180f1",
+            "0000110b-0000-1000-8000",
+            "0000110b00001000800000805f9b34fb",
+            "0000110b-0000-1000-8000-00805f9b34fz",
+        ];
+        for text in malformed {
+            assert!(parse_uuid(text).is_err(), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn rssi_bounds() {
+        assert_eq!(parse_rssi("-70"), Ok(-70));
+        assert_eq!(parse_rssi("-70 dBm"), Ok(-70));
+        assert_eq!(parse_rssi("-127"), Ok(-127));
+        assert_eq!(parse_rssi("20"), Ok(20));
+        assert!(parse_rssi("-128").is_err());
+        assert!(parse_rssi("21").is_err());
+        assert!(parse_rssi("strong").is_err());
+    }
+
+    #[test]
+    fn pathloss_bounds() {
+        assert_eq!(parse_pathloss("0"), Ok(0));
+        assert_eq!(parse_pathloss("60 dB"), Ok(60));
+        assert_eq!(parse_pathloss("137"), Ok(137));
+        assert!(parse_pathloss("138").is_err());
+        assert!(parse_pathloss("-1").is_err());
+    }
+
+    #[test]
+    fn step_rssi_turns_on_off_and_clamps() {
+        let mut filter = DiscoveryFilter::default();
+        filter.step_rssi(false);
+        assert_eq!(filter.rssi, None);
+        filter.step_rssi(true);
+        assert_eq!(filter.rssi, Some(RSSI_START));
+
+        filter.rssi = Some(RSSI_RANGE.1 - 2);
+        filter.step_rssi(true);
+        assert_eq!(filter.rssi, Some(RSSI_RANGE.1));
+        filter.rssi = Some(RSSI_RANGE.0 + 2);
+        filter.step_rssi(false);
+        assert_eq!(filter.rssi, None);
+    }
+
+    #[test]
+    fn step_pathloss_turns_on_off_and_clamps() {
+        let mut filter = DiscoveryFilter::default();
+        filter.step_pathloss(true);
+        assert_eq!(filter.pathloss, None);
+        filter.step_pathloss(false);
+        assert_eq!(filter.pathloss, Some(PATHLOSS_START));
+
+        filter.pathloss = Some(2);
+        filter.step_pathloss(false);
+        assert_eq!(filter.pathloss, Some(PATHLOSS_RANGE.0));
+        filter.pathloss = Some(PATHLOSS_RANGE.1 - 2);
+        filter.step_pathloss(true);
+        assert_eq!(filter.pathloss, None);
+    }
+
+    #[test]
+    fn rssi_and_pathloss_exclude_each_other() {
+        let mut filter = DiscoveryFilter::default();
+        filter.step_pathloss(false);
+        filter.step_rssi(true);
+        assert_eq!((filter.rssi, filter.pathloss), (Some(RSSI_START), None));
+        filter.step_pathloss(false);
+        assert_eq!((filter.rssi, filter.pathloss), (None, Some(PATHLOSS_START)));
+
+        filter.set_field(FilterField::Rssi, "-60").unwrap();
+        assert_eq!((filter.rssi, filter.pathloss), (Some(-60), None));
+        filter.set_field(FilterField::Pathloss, "40").unwrap();
+        assert_eq!((filter.rssi, filter.pathloss), (None, Some(40)));
+    }
+
+    #[test]
+    fn set_field_parses_the_editor_text() {
+        let mut filter = DiscoveryFilter::default();
+        filter.set_field(FilterField::Rssi, " -70 ").unwrap();
+        assert_eq!(filter.rssi, Some(-70));
+        // A bad value keeps the old one.
+        assert!(filter.set_field(FilterField::Rssi, "-200").is_err());
+        assert_eq!(filter.rssi, Some(-70));
+        filter.set_field(FilterField::Rssi, "").unwrap();
+        assert_eq!(filter.rssi, None);
+
+        let text = format!("180f, 0x180F {}", A2DP_SINK.to_uppercase());
+        filter.set_field(FilterField::Uuids, &text).unwrap();
+        assert_eq!(filter.uuids, vec![BATTERY_SERVICE, A2DP_SINK]);
+        let input = format!("{}, {}", BATTERY_SERVICE, A2DP_SINK);
+        assert_eq!(filter.field_input(FilterField::Uuids), input);
+        assert!(filter.set_field(FilterField::Uuids, "180f, nope").is_err());
+        assert_eq!(filter.uuids.len(), 2);
+        filter.set_field(FilterField::Uuids, "").unwrap();
+        assert!(filter.uuids.is_empty());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = temporary_file("filter-round-trip");
+        let filter = DiscoveryFilter {
+            transport: Transport::Le,
+            rssi: Some(-70),
+            pathloss: None,
+            uuids: vec![BATTERY_SERVICE.to_string()],
+            duplicate_data: false,
+        };
+        filter.save(&path).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("transport = \"le\""));
+        assert!(contents.contains("duplicate-data = false"));
+        assert!(!contents.contains("pathloss"));
+        assert_eq!(DiscoveryFilter::load(&path).unwrap(), filter);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_file_is_no_filter() {
+        let path = temporary_file("filter-missing");
+        let filter = DiscoveryFilter::load(&path).unwrap();
+        assert!(filter.is_empty());
+        assert_eq!(filter.summary(), "none");
+    }
+
+    #[test]
+    fn invalid_files_are_refused() {
+        let path = temporary_file("filter-invalid");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let invalid = [
+            "rssi = -70\npathloss = 40\n",
+            "rssi = -200\n",
+            "uuids = [\"battery\"]\n",
+            "transport = \"radio\"\n",
+            "colour = \"blue\"\n",
+        ];
+        for contents in invalid {
+            fs::write(&path, contents).unwrap();
+            let err = DiscoveryFilter::load(&path).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", contents);
+        }
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
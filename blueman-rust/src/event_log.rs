@@ -0,0 +1,253 @@
+use crate::known_devices::unix_now;
+use std::{collections::VecDeque, fmt, fs, io, path::Path, str::FromStr};
+
+/// Entries kept when the config does not say otherwise.
+pub const DEFAULT_LOG_SIZE: usize = 500;
+
+/// How important an event is. Ordered, so a threshold can be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,    // found, connected, paired...
+    Warning, // low battery, failed auto-connect, rfkill block, adapter removed
+    Error,   // failed D-Bus calls
+}
+
+impl LogLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<LogLevel, String> {
+        match text {
+            "info" => Ok(LogLevel::Info),
+            "warning" => Ok(LogLevel::Warning),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(format!("unknown level \"{}\" (info, warning or error)", text)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: u64, // seconds since the Unix epoch
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// The last events of the session, oldest first. Once full, each new entry
+/// drops the oldest one.
+#[derive(Debug)]
+pub struct EventLog {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> EventLog {
+        EventLog {
+            entries: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Adds an entry stamped with the current time. Returns true when the
+    /// oldest entry was dropped to make room.
+    pub fn push(&mut self, level: LogLevel, message: String) -> bool {
+        let dropped = self.entries.len() == self.capacity;
+        if dropped {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry {
+            time: unix_now(),
+            level,
+            message,
+        });
+        dropped
+    }
+
+    /// Changes how many entries are kept, dropping the oldest ones if needed.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn entries(&self) -> &VecDeque<LogEntry> {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Writes every entry to `path`, one `date time level message` line each,
+    /// with the time in UTC.
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        let contents: String = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} {:<7} {}\n",
+                    UtcTime::at(entry.time),
+                    entry.level.label(),
+                    entry.message
+                )
+            })
+            .collect();
+        fs::write(path, contents)
+    }
+}
+
+/// A Unix time broken down in UTC. Only the standard library is used, and
+/// it knows no time zones, so the log is in UTC rather than local time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UtcTime {
+    pub year: u64,
+    pub month: u64,
+    pub day: u64,
+    pub hour: u64,
+    pub minute: u64,
+    pub second: u64,
+}
+
+impl UtcTime {
+    pub fn at(time: u64) -> UtcTime {
+        let (days, seconds) = (time / 86_400, time % 86_400);
+        // Howard Hinnant's civil_from_days: count from 0000-03-01 so the leap
+        // day ends each 400-year era, then each 4- and 100-year cycle.
+        let days = days + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+        UtcTime {
+            year: era * 400 + year_of_era + u64::from(month <= 2),
+            month,
+            day: day_of_year - (153 * month_from_march + 2) / 5 + 1,
+            hour: seconds / 3_600,
+            minute: seconds / 60 % 60,
+            second: seconds % 60,
+        }
+    }
+
+    /// `14:03:27`, for the log view.
+    pub fn clock(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+
+    /// `20240131-140327`, for file names.
+    pub fn compact(&self) -> String {
+        format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// `2024-01-31 14:03:27`.
+impl fmt::Display for UtcTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {}",
+            self.year,
+            self.month,
+            self.day,
+            self.clock()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_with(capacity: usize, messages: &[&str]) -> EventLog {
+        let mut log = EventLog::new(capacity);
+        for message in messages {
+            log.push(LogLevel::Info, message.to_string());
+        }
+        log
+    }
+
+    fn messages(log: &EventLog) -> Vec<&str> {
+        log.entries().iter().map(|e| e.message.as_str()).collect()
+    }
+
+    #[test]
+    fn a_full_log_drops_the_oldest_entry() {
+        let mut log = log_with(3, &["a", "b"]);
+        assert!(!log.push(LogLevel::Info, "c".to_string()));
+        assert!(log.push(LogLevel::Warning, "d".to_string()));
+        assert_eq!(messages(&log), ["b", "c", "d"]);
+
+        log.set_capacity(2);
+        assert_eq!(messages(&log), ["c", "d"]);
+        log.set_capacity(0);
+        assert_eq!(messages(&log), ["d"]);
+        assert!(log.push(LogLevel::Info, "e".to_string()));
+        assert_eq!(messages(&log), ["e"]);
+    }
+
+    #[test]
+    fn export_writes_one_line_per_entry() {
+        let mut log = EventLog::new(10);
+        log.entries.push_back(LogEntry {
+            time: 1_706_709_807,
+            level: LogLevel::Info,
+            message: "Connected to WH-1000XM4".to_string(),
+        });
+        log.entries.push_back(LogEntry {
+            time: 1_706_709_808,
+            level: LogLevel::Warning,
+            message: "Low battery: WH-1000XM4 is at 18%".to_string(),
+        });
+        let path = std::env::temp_dir()
+            .join(format!("bluetooth-manager-export-{}.log", std::process::id()));
+        log.export(&path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            contents,
+            "2024-01-31 14:03:27 info    Connected to WH-1000XM4\n\
+             2024-01-31 14:03:28 warning Low battery: WH-1000XM4 is at 18%\n"
+        );
+    }
+
+    #[test]
+    fn unix_times_break_down_in_utc() {
+        let cases = [
+            (0, "1970-01-01 00:00:00"),
+            (951_868_799, "2000-02-29 23:59:59"),
+            (1_706_709_807, "2024-01-31 14:03:27"),
+            (4_107_542_400, "2100-03-01 00:00:00"),
+        ];
+        for (time, shown) in cases {
+            assert_eq!(UtcTime::at(time).to_string(), shown);
+        }
+        assert_eq!(UtcTime::at(1_706_709_807).compact(), "20240131-140327");
+        assert_eq!(UtcTime::at(1_706_709_807).clock(), "14:03:27");
+    }
+}
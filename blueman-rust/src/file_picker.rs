@@ -0,0 +1,106 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Directory browser used to choose a file to send. Hidden entries are not
+/// listed; directories come first, then files, both sorted by name.
+#[derive(Debug)]
+pub struct FilePicker {
+    pub dir: PathBuf,
+    pub entries: Vec<FileEntry>,
+    pub selected: usize,
+}
+
+impl FilePicker {
+    pub fn open(dir: &Path) -> io::Result<FilePicker> {
+        let dir = dir.canonicalize()?;
+        let entries = read_entries(&dir)?;
+        Ok(FilePicker {
+            dir,
+            entries,
+            selected: 0,
+        })
+    }
+
+    pub fn selected_entry(&self) -> Option<&FileEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + 1) % self.entries.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+        }
+    }
+
+    /// Opens the selected directory, or returns the selected file.
+    pub fn enter(&mut self) -> io::Result<Option<PathBuf>> {
+        let Some(entry) = self.selected_entry().cloned() else {
+            return Ok(None);
+        };
+        if !entry.is_dir {
+            return Ok(Some(entry.path));
+        }
+        self.change_dir(entry.path)?;
+        Ok(None)
+    }
+
+    /// Goes up one directory, with the cursor on the directory we came from.
+    pub fn parent(&mut self) -> io::Result<()> {
+        let Some(parent) = self.dir.parent().map(Path::to_path_buf) else {
+            return Ok(());
+        };
+        let child = self.change_dir(parent)?;
+        if let Some(index) = self.entries.iter().position(|e| e.path == child) {
+            self.selected = index;
+        }
+        Ok(())
+    }
+
+    /// Lists `dir` and returns the directory shown before.
+    fn change_dir(&mut self, dir: PathBuf) -> io::Result<PathBuf> {
+        self.entries = read_entries(&dir)?;
+        self.selected = 0;
+        Ok(std::mem::replace(&mut self.dir, dir))
+    }
+}
+
+fn read_entries(dir: &Path) -> io::Result<Vec<FileEntry>> {
+    let mut entries: Vec<FileEntry> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                return None;
+            }
+            // Follows symlinks, so a link to a directory can be opened
+            let metadata = fs::metadata(entry.path()).ok()?;
+            Some(FileEntry {
+                name,
+                path: entry.path(),
+                is_dir: metadata.is_dir(),
+                size: metadata.len(),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(entries)
+}
@@ -0,0 +1,242 @@
+use crate::profiles::{profile_name, short_uuid};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Notifications kept in the browser's log; older ones are dropped.
+const NOTIFICATION_LOG_LEN: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GattKind {
+    Service,        // org.bluez.GattService1
+    Characteristic, // org.bluez.GattCharacteristic1
+    Descriptor,     // org.bluez.GattDescriptor1
+}
+
+/// One service, characteristic or descriptor of a connected LE device.
+/// Backends list them sorted by path, so every characteristic follows its
+/// service and every descriptor its characteristic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GattAttribute {
+    pub path: String, // D-Bus object path, e.g. /org/bluez/hci0/dev_../service0010/char0011
+    pub kind: GattKind,
+    pub uuid: String,
+    pub primary: bool,      // services only
+    pub flags: Vec<String>, // "read", "write", "notify", ... (not for services)
+    pub value: Option<Vec<u8>>, // last value read, written or notified
+    pub notifying: bool,
+}
+
+impl GattAttribute {
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    pub fn can_read(&self) -> bool {
+        self.kind != GattKind::Service && self.has_flag("read")
+    }
+
+    pub fn can_write(&self) -> bool {
+        self.kind != GattKind::Service
+            && (self.has_flag("write") || self.has_flag("write-without-response"))
+    }
+
+    pub fn can_notify(&self) -> bool {
+        self.kind == GattKind::Characteristic
+            && (self.has_flag("notify") || self.has_flag("indicate"))
+    }
+}
+
+/// Name of a SIG assigned GATT service, characteristic or descriptor UUID.
+pub fn gatt_name(uuid: &str) -> Option<&'static str> {
+    let name = match short_uuid(uuid)? {
+        0x180d => "Heart Rate",
+        0x1805 => "Current Time",
+        0x181a => "Environmental Sensing",
+        0x2a00 => "Device Name",
+        0x2a01 => "Appearance",
+        0x2a04 => "Peripheral Preferred Connection Parameters",
+        0x2a05 => "Service Changed",
+        0x2a19 => "Battery Level",
+        0x2a23 => "System ID",
+        0x2a24 => "Model Number",
+        0x2a25 => "Serial Number",
+        0x2a26 => "Firmware Revision",
+        0x2a27 => "Hardware Revision",
+        0x2a28 => "Software Revision",
+        0x2a29 => "Manufacturer Name",
+        0x2a37 => "Heart Rate Measurement",
+        0x2a38 => "Body Sensor Location",
+        0x2a6e => "Temperature",
+        0x2a6f => "Humidity",
+        0x2900 => "Characteristic Extended Properties",
+        0x2901 => "Characteristic User Description",
+        0x2902 => "Client Characteristic Configuration",
+        0x2904 => "Characteristic Presentation Format",
+        _ => return profile_name(uuid),
+    };
+    Some(name)
+}
+
+/// Name for display: the assigned name, or the raw UUID for vendor attributes.
+pub fn gatt_label(uuid: &str) -> String {
+    gatt_name(uuid)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid.to_string())
+}
+
+/// `[0x0a, 0xff]` -> `0A FF`.
+pub fn format_hex(value: &[u8]) -> String {
+    value
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The value as text, when it is printable UTF-8.
+pub fn format_text(value: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(value).ok()?;
+    let text = text.trim_end_matches('\0');
+    (!text.is_empty() && !text.chars().any(char::is_control)).then(|| text.to_string())
+}
+
+/// How a typed value is turned into bytes before it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ValueFormat {
+    #[default]
+    Hex, // "01 a0 ff" or "01a0ff", optionally prefixed with 0x
+    Text, // UTF-8
+}
+
+impl ValueFormat {
+    pub fn toggle(self) -> ValueFormat {
+        match self {
+            ValueFormat::Hex => ValueFormat::Text,
+            ValueFormat::Text => ValueFormat::Hex,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ValueFormat::Hex => "Hex",
+            ValueFormat::Text => "UTF-8",
+        }
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Vec<u8>, String> {
+        match self {
+            ValueFormat::Text => Ok(input.as_bytes().to_vec()),
+            ValueFormat::Hex => {
+                let digits: String = input
+                    .trim()
+                    .trim_start_matches("0x")
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+                    return Err(format!("'{}' is not a hex digit", c));
+                }
+                if digits.is_empty() || !digits.len().is_multiple_of(2) {
+                    return Err("expected an even number of hex digits".to_string());
+                }
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|err| err.to_string()))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A value a characteristic sent while notifications were on.
+#[derive(Debug, Clone)]
+pub struct GattNotification {
+    pub elapsed: Duration, // since the browser was opened
+    pub uuid: String,
+    pub value: Vec<u8>,
+}
+
+/// State of the GATT view for one connected device.
+#[derive(Debug)]
+pub struct GattBrowser {
+    pub adapter: String,
+    pub address: String,
+    pub attributes: Vec<GattAttribute>,
+    pub selected: usize, // index into attributes
+    pub notifications: VecDeque<GattNotification>, // oldest first
+    pub write_format: Option<ValueFormat>, // Some while typing a value to write
+    pub write_input: String,
+    opened: Instant,
+}
+
+impl GattBrowser {
+    pub fn new(adapter: String, address: String, attributes: Vec<GattAttribute>) -> GattBrowser {
+        GattBrowser {
+            adapter,
+            address,
+            attributes,
+            selected: 0,
+            notifications: VecDeque::new(),
+            write_format: None,
+            write_input: String::new(),
+            opened: Instant::now(),
+        }
+    }
+
+    pub fn selected_attribute(&self) -> Option<&GattAttribute> {
+        self.attributes.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.attributes.is_empty() {
+            self.selected = (self.selected + 1) % self.attributes.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.attributes.is_empty() {
+            self.selected = (self.selected + self.attributes.len() - 1) % self.attributes.len();
+        }
+    }
+
+    /// Replaces the attribute list after a reload, keeping the cursor and
+    /// the values read so far of attributes that are still there.
+    pub fn replace_attributes(&mut self, attributes: Vec<GattAttribute>) {
+        let selected = self.selected_attribute().map(|a| a.path.clone());
+        let previous = std::mem::replace(&mut self.attributes, attributes);
+        for attribute in &mut self.attributes {
+            if let Some(old) = previous.iter().find(|a| a.path == attribute.path) {
+                attribute.value = attribute.value.take().or_else(|| old.value.clone());
+            }
+        }
+        self.selected = selected
+            .and_then(|path| self.attributes.iter().position(|a| a.path == path))
+            .unwrap_or(0);
+    }
+
+    pub fn attribute_mut(&mut self, path: &str) -> Option<&mut GattAttribute> {
+        self.attributes.iter_mut().find(|a| a.path == path)
+    }
+
+    /// Stores a new value; it goes to the log when notifications are on.
+    pub fn value_changed(&mut self, path: &str, value: Vec<u8>) {
+        let elapsed = self.opened.elapsed();
+        let Some(attribute) = self.attribute_mut(path) else { return };
+        attribute.value = Some(value.clone());
+        if !attribute.notifying {
+            return;
+        }
+
+        let uuid = attribute.uuid.clone();
+        if self.notifications.len() == NOTIFICATION_LOG_LEN {
+            self.notifications.pop_front();
+        }
+        self.notifications.push_back(GattNotification {
+            elapsed,
+            uuid,
+            value,
+        });
+    }
+}
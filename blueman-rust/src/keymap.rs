@@ -0,0 +1,567 @@
+use crate::app::View;
+use crossterm::event::KeyCode;
+use std::{collections::HashMap, fmt};
+
+/// Something a key can be bound to. Which actions exist in a view, and
+/// their default keys, is listed in that view's table below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    SelectPrevious,
+    SelectNext,
+    PageUp,
+    PageDown,
+    SelectFirst,
+    SelectLast,
+    ToggleScan,
+    ToggleBluetooth,
+    CycleTypeFilter,
+    CycleSortMode,
+    Search,
+    ClearSearch,
+    ToggleHideUnnamed,
+    ToggleOnlyKnown,
+    ToggleOnlyConnected,
+    ToggleOnlyBlocked,
+    OpenDetails,
+    OpenSettings,
+    OpenLog,
+    Connect,
+    Pair,
+    Remove,
+    ConnectProfile,
+    DisconnectProfile,
+    ToggleAutoConnect,
+    ToggleTrusted,
+    ToggleBlocked,
+    Rename,
+    OpenGatt,
+    SendFile,
+    NextAdapter,
+    ToggleDiscoverable,
+    TogglePairable,
+    IncreaseTimeout,
+    DecreaseTimeout,
+    LowerFilterValue,
+    RaiseFilterValue,
+    EditFilter,
+    ResetFilter,
+    ReadValue,
+    WriteValue,
+    ToggleNotify,
+    Reload,
+    ClearLog,
+    ExportLog,
+    ToggleDesktopNotifications,
+    Back,
+}
+
+impl Action {
+    /// Name used in the `[keys.*]` tables of the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::SelectPrevious => "up",
+            Action::SelectNext => "down",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::SelectFirst => "first",
+            Action::SelectLast => "last",
+            Action::ToggleScan => "scan",
+            Action::ToggleBluetooth => "bluetooth",
+            Action::CycleTypeFilter => "type-filter",
+            Action::CycleSortMode => "sort",
+            Action::Search => "search",
+            Action::ClearSearch => "clear-search",
+            Action::ToggleHideUnnamed => "hide-unnamed",
+            Action::ToggleOnlyKnown => "only-known",
+            Action::ToggleOnlyConnected => "only-connected",
+            Action::ToggleOnlyBlocked => "only-blocked",
+            Action::OpenDetails => "details",
+            Action::OpenSettings => "settings",
+            Action::OpenLog => "log",
+            Action::Connect => "connect",
+            Action::Pair => "pair",
+            Action::Remove => "remove",
+            Action::ConnectProfile => "connect-profile",
+            Action::DisconnectProfile => "disconnect-profile",
+            Action::ToggleAutoConnect => "auto-connect",
+            Action::ToggleTrusted => "trust",
+            Action::ToggleBlocked => "block",
+            Action::Rename => "rename",
+            Action::OpenGatt => "gatt",
+            Action::SendFile => "send-file",
+            Action::NextAdapter => "next-adapter",
+            Action::ToggleDiscoverable => "discoverable",
+            Action::TogglePairable => "pairable",
+            Action::IncreaseTimeout => "timeout-up",
+            Action::DecreaseTimeout => "timeout-down",
+            Action::LowerFilterValue => "filter-down",
+            Action::RaiseFilterValue => "filter-up",
+            Action::EditFilter => "edit-filter",
+            Action::ResetFilter => "reset-filter",
+            Action::ReadValue => "read",
+            Action::WriteValue => "write",
+            Action::ToggleNotify => "notify",
+            Action::Reload => "reload",
+            Action::ClearLog => "clear-log",
+            Action::ExportLog => "export",
+            Action::ToggleDesktopNotifications => "desktop-notifications",
+            Action::Back => "back",
+        }
+    }
+}
+
+/// One row of a view's table: the action, its help text and default keys.
+type ActionInfo = (Action, &'static str, &'static [&'static str]);
+
+const GLOBAL_ACTIONS: &[ActionInfo] = &[(Action::Quit, "Quit", &["q"])];
+
+const LIST_ACTIONS: &[ActionInfo] = &[
+    (Action::SelectPrevious, "Previous Device", &["Up", "k"]),
+    (Action::SelectNext, "Next Device", &["Down", "j"]),
+    (Action::PageUp, "Page Up", &["PageUp"]),
+    (Action::PageDown, "Page Down", &["PageDown"]),
+    (Action::SelectFirst, "First Device", &["Home"]),
+    (Action::SelectLast, "Last Device", &["End"]),
+    (Action::OpenDetails, "Device Details", &["Enter"]),
+    (Action::ToggleScan, "Start/Stop Scanning", &["s"]),
+    (Action::ToggleBluetooth, "Toggle Bluetooth", &["t"]),
+    (Action::CycleTypeFilter, "Change Type Filter", &["f"]),
+    (Action::CycleSortMode, "Change Sort Order", &["o"]),
+    (Action::Search, "Search", &["/"]),
+    (Action::ClearSearch, "Clear Search", &["Esc"]),
+    (Action::ToggleHideUnnamed, "Hide Unnamed", &["u"]),
+    (Action::ToggleOnlyKnown, "Only Known", &["K"]),
+    (Action::ToggleOnlyConnected, "Only Connected", &["C"]),
+    (Action::ToggleOnlyBlocked, "Only Blocked", &["B"]),
+    (Action::OpenLog, "Event Log", &["l"]),
+    (Action::OpenSettings, "Settings and Help", &["?"]),
+];
+
+const DETAILS_ACTIONS: &[ActionInfo] = &[
+    (Action::Connect, "Connect/Disconnect", &["c"]),
+    (Action::Pair, "Pair Device", &["p"]),
+    (Action::Remove, "Remove Device", &["r"]),
+    (Action::SelectPrevious, "Previous Profile", &["Up", "k"]),
+    (Action::SelectNext, "Next Profile", &["Down", "j"]),
+    (Action::ConnectProfile, "Connect Profile", &["Enter"]),
+    (Action::DisconnectProfile, "Disconnect Profile", &["x"]),
+    (Action::Rename, "Rename", &["n"]),
+    (Action::ToggleAutoConnect, "Toggle Auto-connect", &["a"]),
+    (Action::ToggleTrusted, "Trust/Untrust", &["t"]),
+    (Action::ToggleBlocked, "Block/Unblock", &["b"]),
+    (Action::OpenGatt, "GATT Services", &["g"]),
+    (Action::SendFile, "Send File", &["f"]),
+    (Action::Back, "Back to Device List", &["Esc"]),
+];
+
+const SETTINGS_ACTIONS: &[ActionInfo] = &[
+    (Action::ToggleBluetooth, "Toggle Bluetooth", &["t"]),
+    (Action::ToggleScan, "Start/Stop Scanning", &["s"]),
+    (Action::NextAdapter, "Select Next Adapter", &["a"]),
+    (Action::Rename, "Rename Adapter", &["n"]),
+    (Action::ToggleDiscoverable, "Toggle Discoverable", &["d"]),
+    (Action::TogglePairable, "Toggle Pairable", &["p"]),
+    (Action::IncreaseTimeout, "Longer Discoverable Timeout", &["+"]),
+    (Action::DecreaseTimeout, "Shorter Discoverable Timeout", &["-"]),
+    (Action::SelectPrevious, "Previous Filter Field", &["Up", "k"]),
+    (Action::SelectNext, "Next Filter Field", &["Down", "j"]),
+    (Action::LowerFilterValue, "Lower Value", &["Left", "h"]),
+    (Action::RaiseFilterValue, "Raise Value", &["Right", "l"]),
+    (Action::EditFilter, "Edit Field", &["Enter"]),
+    (Action::ResetFilter, "Clear Filter", &["c"]),
+    (Action::Back, "Back to Device List", &["Esc"]),
+];
+
+const GATT_ACTIONS: &[ActionInfo] = &[
+    (Action::SelectPrevious, "Previous Attribute", &["Up", "k"]),
+    (Action::SelectNext, "Next Attribute", &["Down", "j"]),
+    (Action::ReadValue, "Read", &["r"]),
+    (Action::WriteValue, "Write", &["w"]),
+    (Action::ToggleNotify, "Toggle Notifications", &["n"]),
+    (Action::Reload, "Reload", &["g"]),
+    (Action::ClearLog, "Clear Log", &["c"]),
+    (Action::Back, "Back to Device Details", &["Esc"]),
+];
+
+const LOG_ACTIONS: &[ActionInfo] = &[
+    (Action::SelectPrevious, "Scroll Up", &["Up", "k"]),
+    (Action::SelectNext, "Scroll Down", &["Down", "j"]),
+    (Action::PageUp, "Page Up", &["PageUp"]),
+    (Action::PageDown, "Page Down", &["PageDown"]),
+    (Action::SelectFirst, "Oldest Event", &["Home"]),
+    (Action::SelectLast, "Newest Event (Follow)", &["End"]),
+    (Action::ExportLog, "Export to File", &["e"]),
+    (Action::ToggleDesktopNotifications, "Toggle Desktop Notifications", &["n"]),
+    (Action::ClearLog, "Clear Log", &["c"]),
+    (Action::Back, "Back to Device List", &["Esc"]),
+];
+
+/// A key without modifiers, as written in the config file: a single
+/// character (`"k"`, `"K"`, `"?"`) or a name such as `"Enter"`, `"Up"`,
+/// `"Space"` or `"F5"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key(pub KeyCode);
+
+impl Key {
+    pub fn parse(text: &str) -> Result<Key, String> {
+        let mut chars = text.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Key(KeyCode::Char(c)));
+        }
+
+        let code = match text.to_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => return Err(format!("unknown key \"{}\"", text)),
+            },
+        };
+        Ok(Key(code))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "ESC"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+/// An action with the keys bound to it and its help text.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub action: Action,
+    pub description: &'static str,
+    pub keys: Vec<Key>,
+}
+
+impl Binding {
+    /// `↑/k`, or None when the action has no keys.
+    pub fn keys_label(&self) -> Option<String> {
+        if self.keys.is_empty() {
+            return None;
+        }
+        Some(
+            self.keys
+                .iter()
+                .map(Key::to_string)
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+/// The bindings of one view, in the order they are shown in the help.
+#[derive(Debug, Clone)]
+pub struct Bindings(Vec<Binding>);
+
+impl Bindings {
+    fn defaults(table: &[ActionInfo]) -> Bindings {
+        Bindings(
+            table
+                .iter()
+                .map(|(action, description, keys)| Binding {
+                    action: *action,
+                    description,
+                    keys: keys.iter().map(|key| Key::parse(key).expect("valid default key")).collect(),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Binding> {
+        self.0.iter()
+    }
+
+    pub fn action(&self, key_code: KeyCode) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|binding| binding.keys.contains(&Key(key_code)))
+            .map(|binding| binding.action)
+    }
+
+    /// The first key bound to `action`, for hints like "Press 's' to scan".
+    pub fn key(&self, action: Action) -> Option<Key> {
+        self.0
+            .iter()
+            .find(|binding| binding.action == action)
+            .and_then(|binding| binding.keys.first().copied())
+    }
+
+    /// Replaces the keys of the actions named in `overrides`. A key taken
+    /// by an override is removed from the actions that had it by default;
+    /// the same key on two overridden actions is an error.
+    fn apply(&mut self, section: &str, overrides: &HashMap<String, Vec<String>>) -> Result<(), String> {
+        let mut overridden: Vec<(Action, Key)> = vec![];
+        for (name, keys) in overrides {
+            let Some(binding) = self.0.iter_mut().find(|b| b.action.name() == name) else {
+                return Err(format!("[keys.{}]: unknown action \"{}\"", section, name));
+            };
+            binding.keys = keys
+                .iter()
+                .map(|key| Key::parse(key))
+                .collect::<Result<_, _>>()
+                .map_err(|err| format!("[keys.{}] {}: {}", section, name, err))?;
+            for key in &binding.keys {
+                if let Some((other, _)) = overridden.iter().find(|(_, k)| k == key) {
+                    return Err(format!(
+                        "[keys.{}]: {} is bound to both {} and {}",
+                        section,
+                        key,
+                        other.name(),
+                        name
+                    ));
+                }
+                overridden.push((binding.action, *key));
+            }
+        }
+
+        for binding in &mut self.0 {
+            if overrides.contains_key(binding.action.name()) {
+                continue;
+            }
+            binding
+                .keys
+                .retain(|key| !overridden.iter().any(|(_, k)| k == key));
+        }
+        Ok(())
+    }
+}
+
+/// Key bindings of every view. Keys of the current view win over the
+/// global ones.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    pub global: Bindings,
+    pub list: Bindings,
+    pub details: Bindings,
+    pub settings: Bindings,
+    pub gatt: Bindings,
+    pub log: Bindings,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            global: Bindings::defaults(GLOBAL_ACTIONS),
+            list: Bindings::defaults(LIST_ACTIONS),
+            details: Bindings::defaults(DETAILS_ACTIONS),
+            settings: Bindings::defaults(SETTINGS_ACTIONS),
+            gatt: Bindings::defaults(GATT_ACTIONS),
+            log: Bindings::defaults(LOG_ACTIONS),
+        }
+    }
+}
+
+impl Keymap {
+    /// The defaults with the `[keys.<section>]` tables of the config applied.
+    pub fn with_overrides(
+        overrides: &HashMap<String, HashMap<String, Vec<String>>>,
+    ) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        for (section, actions) in overrides {
+            let bindings = match section.as_str() {
+                "global" => &mut keymap.global,
+                "list" => &mut keymap.list,
+                "details" => &mut keymap.details,
+                "settings" => &mut keymap.settings,
+                "gatt" => &mut keymap.gatt,
+                "log" => &mut keymap.log,
+                _ => return Err(format!("unknown key section [keys.{}]", section)),
+            };
+            bindings.apply(section, actions)?;
+        }
+        Ok(keymap)
+    }
+
+    pub fn view(&self, view: &View) -> &Bindings {
+        match view {
+            View::DeviceList => &self.list,
+            View::DeviceDetails => &self.details,
+            View::Settings => &self.settings,
+            View::Gatt => &self.gatt,
+            View::Log => &self.log,
+        }
+    }
+
+    pub fn action(&self, view: &View, key_code: KeyCode) -> Option<Action> {
+        self.view(view)
+            .action(key_code)
+            .or_else(|| self.global.action(key_code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(
+        section: &str,
+        actions: &[(&str, &[&str])],
+    ) -> HashMap<String, HashMap<String, Vec<String>>> {
+        let actions = actions
+            .iter()
+            .map(|(action, keys)| {
+                (
+                    action.to_string(),
+                    keys.iter().map(|k| k.to_string()).collect(),
+                )
+            })
+            .collect();
+        HashMap::from([(section.to_string(), actions)])
+    }
+
+    #[test]
+    fn keys_parse_and_display() {
+        let cases = [
+            ("k", KeyCode::Char('k'), "k"),
+            ("K", KeyCode::Char('K'), "K"),
+            ("?", KeyCode::Char('?'), "?"),
+            ("Space", KeyCode::Char(' '), "Space"),
+            ("Enter", KeyCode::Enter, "Enter"),
+            ("return", KeyCode::Enter, "Enter"),
+            ("Escape", KeyCode::Esc, "ESC"),
+            ("up", KeyCode::Up, "↑"),
+            ("PgDn", KeyCode::PageDown, "PgDn"),
+            ("Del", KeyCode::Delete, "Del"),
+            ("F5", KeyCode::F(5), "F5"),
+            ("f12", KeyCode::F(12), "F12"),
+        ];
+        for (text, code, shown) in cases {
+            let key = Key::parse(text).unwrap();
+            assert_eq!(key, Key(code), "{}", text);
+            assert_eq!(key.to_string(), shown, "{}", text);
+        }
+    }
+
+    #[test]
+    fn displayed_names_parse_back_to_the_same_key() {
+        // Arrows are shown as glyphs and are the only exception.
+        for text in [
+            "k",
+            "Space",
+            "Enter",
+            "Tab",
+            "Backspace",
+            "Del",
+            "Ins",
+            "Home",
+            "End",
+        ] {
+            let key = Key::parse(text).unwrap();
+            assert_eq!(Key::parse(&key.to_string()), Ok(key), "{}", text);
+        }
+        for n in 1..=12 {
+            let key = Key(KeyCode::F(n));
+            assert_eq!(Key::parse(&key.to_string()), Ok(key));
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for text in ["", "F0", "F13", "Ctrl+c", "shift"] {
+            assert!(Key::parse(text).is_err(), "{}", text);
+        }
+        assert_eq!(
+            Key::parse("Hyper"),
+            Err("unknown key \"Hyper\"".to_string())
+        );
+    }
+
+    #[test]
+    fn an_override_takes_its_keys_from_the_defaults() {
+        let keymap = Keymap::with_overrides(&overrides("list", &[("scan", &["F5", "t"])])).unwrap();
+        assert_eq!(keymap.list.action(KeyCode::F(5)), Some(Action::ToggleScan));
+        assert_eq!(
+            keymap.list.action(KeyCode::Char('t')),
+            Some(Action::ToggleScan)
+        );
+        assert_eq!(keymap.list.action(KeyCode::Char('s')), None);
+        assert_eq!(
+            keymap.list.key(Action::ToggleScan),
+            Some(Key(KeyCode::F(5)))
+        );
+        // Toggling Bluetooth lost its only key to the override.
+        assert_eq!(keymap.list.key(Action::ToggleBluetooth), None);
+        // Other views keep their defaults.
+        assert_eq!(
+            keymap.settings.action(KeyCode::Char('t')),
+            Some(Action::ToggleBluetooth)
+        );
+    }
+
+    #[test]
+    fn overrides_cannot_share_a_key() {
+        let err =
+            Keymap::with_overrides(&overrides("list", &[("scan", &["x"]), ("search", &["x"])]))
+                .unwrap_err();
+        assert!(
+            err.starts_with("[keys.list]: x is bound to both "),
+            "{}",
+            err
+        );
+        assert!(err.contains("scan") && err.contains("search"), "{}", err);
+    }
+
+    #[test]
+    fn bad_overrides_name_their_section() {
+        let err = Keymap::with_overrides(&overrides("list", &[("fly", &["x"])])).unwrap_err();
+        assert_eq!(err, "[keys.list]: unknown action \"fly\"");
+        let err = Keymap::with_overrides(&overrides("list", &[("scan", &["Hyper"])])).unwrap_err();
+        assert_eq!(err, "[keys.list] scan: unknown key \"Hyper\"");
+        let err = Keymap::with_overrides(&overrides("popup", &[("scan", &["x"])])).unwrap_err();
+        assert_eq!(err, "unknown key section [keys.popup]");
+    }
+
+    #[test]
+    fn view_keys_win_over_global_keys() {
+        let keymap = Keymap::with_overrides(&overrides("global", &[("quit", &["g"])])).unwrap();
+        assert_eq!(
+            keymap.action(&View::DeviceDetails, KeyCode::Char('g')),
+            Some(Action::OpenGatt)
+        );
+        assert_eq!(
+            keymap.action(&View::Gatt, KeyCode::Char('g')),
+            Some(Action::Reload)
+        );
+        assert_eq!(
+            keymap.action(&View::Log, KeyCode::Char('g')),
+            Some(Action::Quit)
+        );
+        assert_eq!(keymap.action(&View::Log, KeyCode::Char('q')), None);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::app::BluetoothDevice;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Directory under the XDG config home (`~/.config` by default).
+pub const CONFIG_DIR: &str = "bluetooth-manager";
+const KNOWN_DEVICES_FILE: &str = "known_devices.toml";
+
+/// `last_seen` is only moved forward in steps of this many seconds, so RSSI
+/// updates during a scan do not rewrite the file every time.
+const LAST_SEEN_RESOLUTION: u64 = 60;
+
+/// What we remember about a device between runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnownDevice {
+    pub adapter: String, // hciN the device was used with
+    pub address: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_profile: Option<String>, // UUID connected with ConnectProfile
+    #[serde(default)]
+    pub auto_connect: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<u64>, // seconds since the Unix epoch
+}
+
+impl KnownDevice {
+    pub fn is(&self, adapter: &str, address: &str) -> bool {
+        self.adapter == adapter && self.address == address
+    }
+}
+
+/// On-disk layout: one `[[device]]` table per known device.
+#[derive(Default, Serialize, Deserialize)]
+struct KnownDevicesFile {
+    #[serde(default, rename = "device")]
+    devices: Vec<KnownDevice>,
+}
+
+/// Known devices loaded from `known_devices.toml`. Changes are kept in
+/// memory until `save` is called.
+#[derive(Debug, Default)]
+pub struct KnownDevices {
+    path: Option<PathBuf>, // None keeps the store in memory only
+    devices: Vec<KnownDevice>,
+}
+
+impl KnownDevices {
+    /// A store that is never written to disk.
+    pub fn in_memory() -> KnownDevices {
+        KnownDevices::default()
+    }
+
+    /// `$XDG_CONFIG_HOME/bluetooth-manager/known_devices.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(KNOWN_DEVICES_FILE))
+    }
+
+    /// Reads the store at `path`; a missing file is an empty store.
+    pub fn load(path: &Path) -> io::Result<KnownDevices> {
+        let devices = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let file: KnownDevicesFile = toml::from_str(&contents)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                file.devices
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err),
+        };
+
+        Ok(KnownDevices {
+            path: Some(path.to_path_buf()),
+            devices,
+        })
+    }
+
+    /// Keeps the loaded entries but makes `save` a no-op from now on.
+    pub fn keep_in_memory(&mut self) {
+        self.path = None;
+    }
+
+    /// Writes the store through a temporary file so a crash never leaves
+    /// it half written.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = KnownDevicesFile {
+            devices: self.devices.clone(),
+        };
+        let contents = toml::to_string_pretty(&file)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn devices(&self) -> &[KnownDevice] {
+        &self.devices
+    }
+
+    pub fn get(&self, adapter: &str, address: &str) -> Option<&KnownDevice> {
+        self.devices.iter().find(|d| d.is(adapter, address))
+    }
+
+    /// The entry for `device`, created from its current state if missing.
+    fn entry(&mut self, device: &BluetoothDevice) -> &mut KnownDevice {
+        let index = match self
+            .devices
+            .iter()
+            .position(|d| d.is(&device.adapter, &device.address))
+        {
+            Some(index) => index,
+            None => {
+                self.devices.push(KnownDevice {
+                    adapter: device.adapter.clone(),
+                    address: device.address.clone(),
+                    name: device.name.clone(),
+                    ..KnownDevice::default()
+                });
+                self.devices.len() - 1
+            }
+        };
+        &mut self.devices[index]
+    }
+
+    /// Records the current name and alias of `device`, and when it was last
+    /// in range. Only paired devices and devices already in the store are
+    /// remembered. Returns true when something worth saving changed.
+    pub fn remember(&mut self, device: &BluetoothDevice, now: u64) -> bool {
+        if !device.paired && self.get(&device.adapter, &device.address).is_none() {
+            return false;
+        }
+
+        let in_range = device.connected || device.rssi.is_some();
+        let entry = self.entry(device);
+        let before = entry.clone();
+        entry.name = device.name.clone();
+        if device.alias.is_some() {
+            entry.alias = device.alias.clone();
+        }
+        if in_range
+            && entry
+                .last_seen
+                .is_none_or(|seen| now >= seen + LAST_SEEN_RESOLUTION)
+        {
+            entry.last_seen = Some(now);
+        }
+        *entry != before
+    }
+
+    pub fn forget(&mut self, adapter: &str, address: &str) -> bool {
+        let count = self.devices.len();
+        self.devices.retain(|d| !d.is(adapter, address));
+        self.devices.len() != count
+    }
+
+    pub fn set_auto_connect(&mut self, device: &BluetoothDevice, enabled: bool) {
+        self.entry(device).auto_connect = enabled;
+    }
+
+    pub fn set_alias(&mut self, device: &BluetoothDevice, alias: Option<String>) {
+        self.entry(device).alias = alias;
+    }
+
+    pub fn set_preferred_profile(&mut self, device: &BluetoothDevice, uuid: &str) -> bool {
+        let entry = self.entry(device);
+        let changed = entry.preferred_profile.as_deref() != Some(uuid);
+        entry.preferred_profile = Some(uuid.to_string());
+        changed
+    }
+}
+
+/// Seconds since the Unix epoch, as stored in `last_seen`.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// "just now", "5 min ago", "3 h ago" or "2 days ago".
+pub fn format_last_seen(seen: u64, now: u64) -> String {
+    let elapsed = now.saturating_sub(seen);
+    if elapsed < 60 {
+        "just now".to_string()
+    } else if elapsed < 60 * 60 {
+        format!("{} min ago", elapsed / 60)
+    } else if elapsed < 24 * 60 * 60 {
+        format!("{} h ago", elapsed / (60 * 60))
+    } else {
+        format!("{} days ago", elapsed / (24 * 60 * 60))
+    }
+}
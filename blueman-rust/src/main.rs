@@ -1,20 +1,103 @@
+mod agent;
 mod app;
+mod backend;
+mod bluez;
+mod cli;
+mod config;
+mod device_type;
+mod discovery_filter;
+mod event_log;
+mod file_picker;
+mod gatt;
+mod keymap;
+mod known_devices;
+mod notifications;
+mod obex;
+mod profiles;
+mod rfkill;
+mod simulated;
+mod theme;
 mod ui;
 
 use app::{App, View};
+use backend::{BackendError, BackendResult, BluetoothBackend};
+use bluez::{BluezClient, BluezEvent};
+use cli::Options;
+use config::Config;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode, KeyEventKind, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use discovery_filter::DiscoveryFilter;
 use ratatui::{
     backend::CrosstermBackend,
+    layout::Rect,
     Terminal,
 };
-use std::io;
+use futures_util::StreamExt;
+use keymap::Action;
+use known_devices::KnownDevices;
+use rfkill::{Rfkill, RFKILL_ENV_VAR};
+use simulated::SimulatedBackend;
+use std::{
+    io,
+    path::PathBuf,
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::mpsc::UnboundedReceiver, time::MissedTickBehavior};
+use ui::ClickTarget;
+
+/// How often the UI is redrawn when nothing else happens.
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Two clicks on the same device row within this time open its details.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
 
 #[tokio::main]
-async fn main() -> Result<(), io::Error> {
+async fn main() -> Result<ExitCode, io::Error> {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("bluetooth-manager: {}\n\n{}", err, cli::USAGE);
+            return Ok(ExitCode::from(cli::EXIT_USAGE));
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut app = App::new();
+    match load_known_devices(options.simulate) {
+        Ok(known_devices) => app.known_devices = known_devices,
+        Err(err) => app.report_error(err),
+    }
+    match load_discovery_filter(options.simulate) {
+        Ok((filter, path)) => {
+            app.discovery_filter = filter;
+            app.discovery_filter_path = path;
+        }
+        Err(err) => app.report_error(err),
+    }
+    app.rfkill = open_rfkill(options.simulate);
+    let bluetooth = open_backend(options.simulate).await;
+    if let Some(command) = options.command.clone() {
+        return Ok(cli::run(command, &options, app, bluetooth).await);
+    }
+    match load_config() {
+        Ok(config) => app.apply_config(config),
+        Err(err) => app.report_error(err),
+    }
+    if app.desktop_notifications {
+        app.connect_notifier().await;
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -22,8 +105,20 @@ async fn main() -> Result<(), io::Error> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app and run it
-    let res = run_app(&mut terminal);
+    // Run the app
+    let bluez_events = match bluetooth {
+        Ok(backend) => {
+            let events = app.attach_backend(backend).await;
+            app.register_agent().await;
+            app.register_obex_agent().await;
+            Some(events)
+        }
+        Err(err) => {
+            app.report_error(format!("BlueZ unavailable: {}", err));
+            None
+        }
+    };
+    let res = run_app(&mut terminal, &mut app, bluez_events).await;
 
     // Restore terminal
     disable_raw_mode()?;
@@ -38,83 +133,642 @@ async fn main() -> Result<(), io::Error> {
         println!("{:?}", err);
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
-fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>) -> io::Result<()> {
-    let mut app = App::new();
+/// The simulated backend, or a connection to BlueZ.
+async fn open_backend(simulate: bool) -> BackendResult<Arc<dyn BluetoothBackend>> {
+    if simulate {
+        return Ok(Arc::new(SimulatedBackend::demo()));
+    }
+    let client = BluezClient::connect().await.map_err(BackendError::from)?;
+    Ok(Arc::new(client))
+}
+
+/// The host's rfkill switches. The simulated adapter has none, unless a
+/// fake tree is given with `BLUETOOTH_MANAGER_RFKILL`.
+fn open_rfkill(simulate: bool) -> Rfkill {
+    if simulate && std::env::var_os(RFKILL_ENV_VAR).is_none() {
+        return Rfkill::disabled();
+    }
+    Rfkill::system()
+}
+
+/// Reads the known devices store. On error the store stays in memory, so the
+/// broken file is left for the user to fix instead of being overwritten.
+fn load_known_devices(simulate: bool) -> Result<KnownDevices, String> {
+    if simulate {
+        return Ok(SimulatedBackend::demo_known_devices());
+    }
+    let Some(path) = KnownDevices::default_path() else {
+        return Ok(KnownDevices::in_memory());
+    };
+    KnownDevices::load(&path).map_err(|err| format!("Failed to load {}: {}", path.display(), err))
+}
+
+/// Reads the filter the last scan used, with the path to save changes to.
+/// The simulated backend starts unfiltered and saves nothing; on error the
+/// broken file is left alone, like the known devices.
+fn load_discovery_filter(simulate: bool) -> Result<(DiscoveryFilter, Option<PathBuf>), String> {
+    let path = match DiscoveryFilter::default_path() {
+        Some(path) if !simulate => path,
+        _ => return Ok((DiscoveryFilter::default(), None)),
+    };
+    let filter = DiscoveryFilter::load(&path)
+        .map_err(|err| format!("Failed to load {}: {}", path.display(), err))?;
+    Ok((filter, Some(path)))
+}
+
+/// Reads the key bindings and theme. On error the defaults are used.
+fn load_config() -> Result<Config, String> {
+    let Some(path) = Config::default_path() else {
+        return Ok(Config::default());
+    };
+    Config::load(&path).map_err(|err| format!("Failed to load {}: {}", path.display(), err))
+}
+
+async fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut bluez_events: Option<UnboundedReceiver<BluezEvent>>,
+) -> io::Result<()> {
+    let mut terminal_events = EventStream::new();
+    let mut ticker = tokio::time::interval(TICK_RATE);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut last_click: Option<(Instant, usize)> = None; // device row clicked last
 
     loop {
-        terminal.draw(|f| ui::draw(f, &app))?;
+        terminal.draw(|f| ui::draw(f, app))?;
 
-        if let Event::Key(key) = crossterm::event::read()? {
-            match app.current_view {
-                View::DeviceList => handle_device_list_input(&mut app, key.code),
-                View::DeviceDetails => handle_device_details_input(&mut app, key.code),
-                View::Settings => handle_settings_input(&mut app, key.code),
+        tokio::select! {
+            event = terminal_events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    if handle_key(app, key.code).await {
+                        return Ok(());
+                    }
+                }
+                Some(Ok(Event::Mouse(mouse))) => {
+                    let area = terminal.size()?;
+                    handle_mouse(app, mouse, area, &mut last_click).await;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+                None => return Ok(()),
+            },
+            event = next_bluez_event(&mut bluez_events) => match event {
+                Some(event) => app.handle_bluez_event(event).await,
+                // BlueZ watchers are gone; keep the UI running without them
+                None => bluez_events = None,
+            },
+            _ = ticker.tick() => app.on_tick(),
+        }
+    }
+}
+
+/// Waits for the next BlueZ event, or forever when there is no connection.
+async fn next_bluez_event(
+    events: &mut Option<UnboundedReceiver<BluezEvent>>,
+) -> Option<BluezEvent> {
+    match events {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Dispatches a key press to the current view. Returns true when the app should quit.
+async fn handle_key(app: &mut App, key_code: KeyCode) -> bool {
+    if app.unblock_prompt {
+        handle_unblock_input(app, key_code).await;
+        return false;
+    }
+    // BlueZ is waiting for the answer, whichever view the dialog is over.
+    if app.agent_request.is_some() {
+        handle_agent_input(app, key_code);
+        return false;
+    }
+
+    let typing = app.alias_edit.is_some()
+        || app.filter_edit.is_some()
+        || app.searching
+        || app.file_picker.is_some()
+        || app.gatt.as_ref().is_some_and(|b| b.write_format.is_some());
+    let action = app.keymap.action(&app.current_view, key_code);
+    match app.current_view {
+        View::DeviceList => handle_device_list_input(app, key_code, action).await,
+        View::DeviceDetails => handle_device_details_input(app, key_code, action).await,
+        View::Settings => handle_settings_input(app, key_code, action).await,
+        View::Gatt => handle_gatt_input(app, key_code, action).await,
+        View::Log => {
+            if let Some(action) = action {
+                log_action(app, action).await;
             }
+        }
+    }
 
-            // Global quit
-            if key.code == KeyCode::Char('q') {
-                return Ok(());
+    // Global quit (not while typing into an alias, a search or a value, nor while
+    // picking a file)
+    action == Some(Action::Quit) && !typing
+}
+
+/// Clicks select device rows and run the actions shown in the actions box;
+/// the wheel moves the selection. Ignored while a dialog or editor is open.
+async fn handle_mouse(app: &mut App, mouse: MouseEvent, area: Rect, last_click: &mut Option<(Instant, usize)>) {
+    if app.agent_request.is_some()
+        || app.unblock_prompt
+        || app.alias_edit.is_some()
+        || app.filter_edit.is_some()
+        || app.gatt.as_ref().is_some_and(|b| b.write_format.is_some())
+    {
+        return;
+    }
+
+    match mouse.kind {
+        MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+            let up = mouse.kind == MouseEventKind::ScrollUp;
+            if let Some(picker) = &mut app.file_picker {
+                if up {
+                    picker.select_previous();
+                } else {
+                    picker.select_next();
+                }
+                return;
+            }
+            let action = if up { Action::SelectPrevious } else { Action::SelectNext };
+            match app.current_view {
+                View::DeviceList => device_list_action(app, action).await,
+                View::DeviceDetails => device_details_action(app, action).await,
+                View::Gatt => gatt_action(app, action).await,
+                View::Log => log_action(app, action).await,
+                View::Settings => {}
+            }
+        }
+        MouseEventKind::Down(MouseButton::Left) if app.file_picker.is_none() => {
+            match ui::click_target(app, area, mouse.column, mouse.row) {
+                Some(ClickTarget::DeviceRow(index)) => {
+                    let now = Instant::now();
+                    let double = last_click
+                        .is_some_and(|(at, row)| row == index && now.duration_since(at) < DOUBLE_CLICK);
+                    app.select_index(index);
+                    if double {
+                        *last_click = None;
+                        if app.searching {
+                            app.finish_search();
+                        }
+                        device_list_action(app, Action::OpenDetails).await;
+                    } else {
+                        *last_click = Some((now, index));
+                    }
+                }
+                Some(ClickTarget::Action(action)) => match app.current_view {
+                    View::DeviceDetails => device_details_action(app, action).await,
+                    View::Gatt => gatt_action(app, action).await,
+                    View::Log => log_action(app, action).await,
+                    _ => {}
+                },
+                None => {}
             }
         }
+        _ => {}
     }
 }
 
-fn handle_device_list_input(app: &mut App, key_code: KeyCode) {
-    match key_code {
-        KeyCode::Up | KeyCode::Char('k') => {
+async fn handle_device_list_input(app: &mut App, key_code: KeyCode, action: Option<Action>) {
+    if app.searching {
+        handle_search_input(app, key_code);
+        return;
+    }
+
+    if let Some(action) = action {
+        device_list_action(app, action).await;
+    }
+}
+
+async fn device_list_action(app: &mut App, action: Action) {
+    match action {
+        Action::SelectPrevious => {
             app.select_previous();
         }
-        KeyCode::Down | KeyCode::Char('j') => {
+        Action::SelectNext => {
             app.select_next();
         }
-        KeyCode::Char('s') => {
-            app.toggle_scanning();
+        Action::PageUp => {
+            app.select_page_up();
         }
-        KeyCode::Char('t') => {
-            app.toggle_bluetooth();
+        Action::PageDown => {
+            app.select_page_down();
         }
-        KeyCode::Enter => {
-            if app.get_selected_device().is_some() {
-                app.change_view(View::DeviceDetails);
-            }
+        Action::SelectFirst => {
+            app.select_first();
+        }
+        Action::SelectLast => {
+            app.select_last();
+        }
+        Action::ToggleScan => {
+            app.toggle_scanning().await;
+        }
+        Action::ToggleBluetooth => {
+            app.toggle_bluetooth().await;
+        }
+        Action::CycleTypeFilter => {
+            app.cycle_type_filter();
+        }
+        Action::CycleSortMode => {
+            app.cycle_sort_mode();
+        }
+        Action::Search => {
+            app.start_search();
+        }
+        Action::ToggleHideUnnamed => {
+            app.toggle_hide_unnamed();
+        }
+        Action::ToggleOnlyKnown => {
+            app.toggle_only_known();
+        }
+        Action::ToggleOnlyConnected => {
+            app.toggle_only_connected();
+        }
+        Action::ToggleOnlyBlocked => {
+            app.toggle_only_blocked();
         }
-        KeyCode::Char('?') => {
+        Action::ClearSearch if !app.search_query.is_empty() => {
+            app.clear_search();
+        }
+        Action::OpenDetails if app.get_selected_device().is_some() => {
+            app.change_view(View::DeviceDetails);
+        }
+        Action::OpenSettings => {
             app.change_view(View::Settings);
         }
+        Action::OpenLog => {
+            app.change_view(View::Log);
+        }
         _ => {}
     }
 }
 
-fn handle_device_details_input(app: &mut App, key_code: KeyCode) {
+/// Typing into the `/` search box filters the list as you type.
+fn handle_search_input(app: &mut App, key_code: KeyCode) {
     match key_code {
-        KeyCode::Char('c') => {
+        KeyCode::Enter => {
+            app.finish_search();
+        }
+        KeyCode::Esc => {
+            app.clear_search();
+        }
+        KeyCode::Backspace => {
+            app.pop_search_char();
+        }
+        KeyCode::Up => {
+            app.select_previous();
+        }
+        KeyCode::Down => {
+            app.select_next();
+        }
+        KeyCode::Char(c) => {
+            app.push_search_char(c);
+        }
+        _ => {}
+    }
+}
+
+async fn handle_device_details_input(app: &mut App, key_code: KeyCode, action: Option<Action>) {
+    if app.alias_edit.is_some() {
+        handle_alias_input(app, key_code).await;
+        return;
+    }
+    if app.file_picker.is_some() {
+        handle_file_picker_input(app, key_code).await;
+        return;
+    }
+
+    if let Some(action) = action {
+        device_details_action(app, action).await;
+    }
+}
+
+async fn device_details_action(app: &mut App, action: Action) {
+    match action {
+        Action::Connect => {
             app.connect_device();
         }
-        KeyCode::Char('p') => {
+        Action::Pair => {
             app.pair_device();
         }
-        KeyCode::Char('r') => {
-            app.remove_device();
+        Action::Remove => {
+            app.remove_device().await;
         }
-        KeyCode::Esc => {
+        Action::SelectPrevious => {
+            app.select_previous_profile();
+        }
+        Action::SelectNext => {
+            app.select_next_profile();
+        }
+        Action::ConnectProfile => {
+            app.connect_profile();
+        }
+        Action::DisconnectProfile => {
+            app.disconnect_profile();
+        }
+        Action::ToggleAutoConnect => {
+            app.toggle_auto_connect();
+        }
+        Action::ToggleTrusted => {
+            app.toggle_trusted().await;
+        }
+        Action::ToggleBlocked => {
+            app.toggle_blocked().await;
+        }
+        Action::Rename => {
+            app.edit_device_alias();
+        }
+        Action::OpenGatt => {
+            app.open_gatt_browser().await;
+        }
+        Action::SendFile => {
+            app.open_file_picker();
+        }
+        Action::Back => {
             app.change_view(View::DeviceList);
         }
         _ => {}
     }
 }
 
-fn handle_settings_input(app: &mut App, key_code: KeyCode) {
+/// Fixed keys (arrows and j/k/h/l), not part of the keymap; see the README.
+async fn handle_file_picker_input(app: &mut App, key_code: KeyCode) {
+    match key_code {
+        KeyCode::Up | KeyCode::Char('k') => {
+            if let Some(picker) = &mut app.file_picker {
+                picker.select_previous();
+            }
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            if let Some(picker) = &mut app.file_picker {
+                picker.select_next();
+            }
+        }
+        KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+            app.file_picker_enter().await;
+        }
+        KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+            app.file_picker_parent();
+        }
+        KeyCode::Esc => {
+            app.close_file_picker();
+        }
+        _ => {}
+    }
+}
+
+async fn handle_gatt_input(app: &mut App, key_code: KeyCode, action: Option<Action>) {
+    if app.gatt.as_ref().is_some_and(|b| b.write_format.is_some()) {
+        handle_gatt_write_input(app, key_code).await;
+        return;
+    }
+
+    if let Some(action) = action {
+        gatt_action(app, action).await;
+    }
+}
+
+async fn gatt_action(app: &mut App, action: Action) {
+    match action {
+        Action::SelectPrevious => {
+            if let Some(browser) = &mut app.gatt {
+                browser.select_previous();
+            }
+        }
+        Action::SelectNext => {
+            if let Some(browser) = &mut app.gatt {
+                browser.select_next();
+            }
+        }
+        Action::ReadValue => {
+            app.read_gatt_value().await;
+        }
+        Action::WriteValue => {
+            app.start_gatt_write();
+        }
+        Action::ToggleNotify => {
+            app.toggle_gatt_notify().await;
+        }
+        Action::Reload => {
+            app.reload_gatt().await;
+        }
+        Action::ClearLog => {
+            app.clear_gatt_log();
+        }
+        Action::Back => {
+            app.close_gatt_browser().await;
+        }
+        _ => {}
+    }
+}
+
+/// Typing a value to write; Tab switches between hex and UTF-8.
+async fn handle_gatt_write_input(app: &mut App, key_code: KeyCode) {
+    match key_code {
+        KeyCode::Enter => {
+            app.commit_gatt_write().await;
+        }
+        KeyCode::Esc => {
+            app.cancel_gatt_write();
+        }
+        KeyCode::Tab => {
+            app.toggle_gatt_write_format();
+        }
+        KeyCode::Backspace => {
+            if let Some(browser) = &mut app.gatt {
+                browser.write_input.pop();
+            }
+        }
+        KeyCode::Char(c) => {
+            if let Some(browser) = &mut app.gatt {
+                browser.write_input.push(c);
+            }
+        }
+        _ => {}
+    }
+}
+
+async fn handle_alias_input(app: &mut App, key_code: KeyCode) {
+    match key_code {
+        KeyCode::Enter => {
+            app.commit_alias_edit().await;
+        }
+        KeyCode::Esc => {
+            app.cancel_alias_edit();
+        }
+        KeyCode::Backspace => {
+            app.alias_input.pop();
+        }
+        KeyCode::Char(c) => {
+            app.alias_input.push(c);
+        }
+        _ => {}
+    }
+}
+
+/// Fixed keys: y/Enter accepts and n/Esc rejects, unless a PIN or passkey is
+/// being typed, in which case only Enter and Esc do.
+fn handle_agent_input(app: &mut App, key_code: KeyCode) {
+    let needs_input = app
+        .agent_request
+        .as_ref()
+        .is_some_and(|request| request.prompt.needs_input());
+
+    match key_code {
+        KeyCode::Enter => {
+            app.accept_agent_request();
+        }
+        KeyCode::Esc => {
+            app.reject_agent_request();
+        }
+        KeyCode::Backspace if needs_input => {
+            app.agent_input.pop();
+        }
+        KeyCode::Char(c) if needs_input => {
+            app.agent_input.push(c);
+        }
+        KeyCode::Char('y') => {
+            app.accept_agent_request();
+        }
+        KeyCode::Char('n') => {
+            app.reject_agent_request();
+        }
+        _ => {}
+    }
+}
+
+/// The rfkill question asked before powering on a soft-blocked adapter. y/n,
+/// Enter and Esc are fixed keys.
+async fn handle_unblock_input(app: &mut App, key_code: KeyCode) {
     match key_code {
-        KeyCode::Char('t') => {
-            app.toggle_bluetooth();
+        KeyCode::Enter | KeyCode::Char('y') => {
+            app.confirm_unblock().await;
+        }
+        KeyCode::Esc | KeyCode::Char('n') => {
+            app.cancel_unblock();
+        }
+        _ => {}
+    }
+}
+
+async fn handle_settings_input(app: &mut App, key_code: KeyCode, action: Option<Action>) {
+    if app.alias_edit.is_some() {
+        handle_alias_input(app, key_code).await;
+        return;
+    }
+    if app.filter_edit.is_some() {
+        handle_filter_input(app, key_code).await;
+        return;
+    }
+
+    if let Some(action) = action {
+        settings_action(app, action).await;
+    }
+}
+
+async fn settings_action(app: &mut App, action: Action) {
+    match action {
+        Action::ToggleBluetooth => {
+            app.toggle_bluetooth().await;
+        }
+        Action::ToggleScan => {
+            app.toggle_scanning().await;
+        }
+        Action::NextAdapter => {
+            app.select_next_adapter().await;
+        }
+        Action::Rename => {
+            app.edit_adapter_alias();
+        }
+        Action::ToggleDiscoverable => {
+            app.toggle_discoverable().await;
+        }
+        Action::TogglePairable => {
+            app.toggle_pairable().await;
+        }
+        Action::IncreaseTimeout => {
+            app.adjust_discoverable_timeout(true).await;
+        }
+        Action::DecreaseTimeout => {
+            app.adjust_discoverable_timeout(false).await;
+        }
+        Action::SelectPrevious => {
+            app.select_previous_filter_field();
+        }
+        Action::SelectNext => {
+            app.select_next_filter_field();
         }
-        KeyCode::Char('s') => {
-            app.toggle_scanning();
+        Action::LowerFilterValue => {
+            app.change_filter_value(false).await;
+        }
+        Action::RaiseFilterValue => {
+            app.change_filter_value(true).await;
+        }
+        Action::EditFilter => {
+            app.edit_filter_field().await;
+        }
+        Action::ResetFilter => {
+            app.reset_discovery_filter().await;
+        }
+        Action::Back => {
+            app.change_view(View::DeviceList);
+        }
+        _ => {}
+    }
+}
+
+/// Typing an RSSI, a pathloss or a list of service UUIDs.
+async fn handle_filter_input(app: &mut App, key_code: KeyCode) {
+    match key_code {
+        KeyCode::Enter => {
+            app.commit_filter_edit().await;
         }
         KeyCode::Esc => {
+            app.cancel_filter_edit();
+        }
+        KeyCode::Backspace => {
+            app.filter_input.pop();
+        }
+        KeyCode::Char(c) => {
+            app.filter_input.push(c);
+        }
+        _ => {}
+    }
+}
+
+async fn log_action(app: &mut App, action: Action) {
+    match action {
+        Action::SelectPrevious => {
+            app.scroll_log(-1);
+        }
+        Action::SelectNext => {
+            app.scroll_log(1);
+        }
+        Action::PageUp => {
+            app.scroll_log_page(false);
+        }
+        Action::PageDown => {
+            app.scroll_log_page(true);
+        }
+        Action::SelectFirst => {
+            app.scroll_log_to_oldest();
+        }
+        Action::SelectLast => {
+            app.follow_log();
+        }
+        Action::ExportLog => {
+            app.export_event_log();
+        }
+        Action::ToggleDesktopNotifications => {
+            app.toggle_desktop_notifications().await;
+        }
+        Action::ClearLog => {
+            app.clear_event_log();
+        }
+        Action::Back => {
             app.change_view(View::DeviceList);
         }
         _ => {}
@@ -0,0 +1,60 @@
+use crate::event_log::{LogEntry, LogLevel};
+use std::collections::HashMap;
+use zbus::{proxy, zvariant::Value, Connection};
+
+const APP_NAME: &str = "Bluetooth Manager";
+
+/// Milliseconds a notification stays up; -1 lets the server decide.
+const EXPIRE_TIMEOUT: i32 = -1;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Mirrors log entries to the desktop through the notification server on
+/// the session bus.
+#[derive(Clone)]
+pub struct DesktopNotifier {
+    proxy: NotificationsProxy<'static>,
+}
+
+impl DesktopNotifier {
+    pub async fn connect() -> zbus::Result<DesktopNotifier> {
+        let connection = Connection::session().await?;
+        let proxy = NotificationsProxy::new(&connection).await?;
+        Ok(DesktopNotifier { proxy })
+    }
+
+    /// Shows `entry` in the background; a server that went away is ignored.
+    pub fn show(&self, entry: &LogEntry) {
+        let proxy = self.proxy.clone();
+        let (icon, urgency, summary) = match entry.level {
+            LogLevel::Info => ("bluetooth", 1u8, APP_NAME),
+            LogLevel::Warning => ("dialog-warning", 1, "Bluetooth warning"),
+            LogLevel::Error => ("dialog-error", 2, "Bluetooth error"),
+        };
+        let body = entry.message.clone();
+        tokio::spawn(async move {
+            let hints = HashMap::from([("urgency", Value::from(urgency))]);
+            let _ = proxy
+                .notify(APP_NAME, 0, icon, summary, &body, &[], hints, EXPIRE_TIMEOUT)
+                .await;
+        });
+    }
+}
@@ -0,0 +1,392 @@
+use crate::agent::{AgentPrompt, AgentReply, AgentRequest, CurrentRequest};
+use crate::backend::BluetoothBackend;
+use crate::bluez::{prop_string, BluezClient, BluezEvent, Properties};
+use futures_util::StreamExt;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tokio::sync::mpsc::UnboundedSender;
+use zbus::{
+    fdo::PropertiesProxy,
+    interface,
+    message::Type as MessageType,
+    names::InterfaceName,
+    proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, Value},
+    Connection, DBusError, MatchRule, MessageStream,
+};
+
+pub const OBEX_AGENT_PATH: &str = "/org/bluez/obex/agent/bluetooth_manager";
+
+const OBEX_SERVICE: &str = "org.bluez.obex";
+const TRANSFER_INTERFACE: &str = "org.bluez.obex.Transfer1";
+const SESSION_INTERFACE: &str = "org.bluez.obex.Session1";
+
+#[proxy(
+    interface = "org.bluez.obex.Client1",
+    default_service = "org.bluez.obex",
+    default_path = "/org/bluez/obex"
+)]
+trait Client1 {
+    fn create_session(
+        &self,
+        destination: &str,
+        args: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+    fn remove_session(&self, session: &ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.bluez.obex.ObjectPush1", default_service = "org.bluez.obex")]
+trait ObjectPush1 {
+    fn send_file(&self, sourcefile: &str) -> zbus::Result<(OwnedObjectPath, Properties)>;
+}
+
+#[proxy(
+    interface = "org.bluez.obex.AgentManager1",
+    default_service = "org.bluez.obex",
+    default_path = "/org/bluez/obex"
+)]
+trait ObexAgentManager1 {
+    fn register_agent(&self, agent: &ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+/// `Transfer1.Status`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferStatus {
+    Queued,
+    Active,
+    Suspended,
+    Complete,
+    Error,
+}
+
+impl TransferStatus {
+    fn parse(status: &str) -> Option<TransferStatus> {
+        match status {
+            "queued" => Some(TransferStatus::Queued),
+            "active" => Some(TransferStatus::Active),
+            "suspended" => Some(TransferStatus::Suspended),
+            "complete" => Some(TransferStatus::Complete),
+            "error" => Some(TransferStatus::Error),
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, TransferStatus::Complete | TransferStatus::Error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferDirection {
+    Outgoing,
+    Incoming,
+}
+
+/// A file being pushed to or received from a device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileTransfer {
+    pub id: String, // Transfer1 object path
+    pub adapter: String,
+    pub address: String,
+    pub direction: TransferDirection,
+    pub name: String, // file name as sent over OBEX
+    pub size: Option<u64>,
+    pub transferred: u64,
+    pub status: TransferStatus,
+    pub filename: Option<PathBuf>, // where an incoming file is saved
+}
+
+impl FileTransfer {
+    /// Progress from 0 to 100; unknown sizes only reach 100 when complete.
+    pub fn percent(&self) -> u16 {
+        match (self.status, self.size) {
+            (TransferStatus::Complete, _) => 100,
+            (_, Some(size)) if size > 0 => (self.transferred.min(size) * 100 / size) as u16,
+            _ => 0,
+        }
+    }
+
+    fn update(&mut self, props: &Properties) {
+        if let Some(status) = prop_string(props, "Status").and_then(|s| TransferStatus::parse(&s)) {
+            self.status = status;
+        }
+        if let Some(name) = prop_string(props, "Name") {
+            self.name = name;
+        }
+        if let Some(size) = props.get("Size").and_then(|v| v.downcast_ref::<u64>().ok()) {
+            self.size = Some(size);
+        }
+        if let Some(transferred) = props
+            .get("Transferred")
+            .and_then(|v| v.downcast_ref::<u64>().ok())
+        {
+            self.transferred = transferred;
+        }
+    }
+}
+
+/// Opens an Object Push session from the adapter at `source` to the device
+/// at `destination` and queues `file`. Returns the transfer and the session,
+/// which the caller removes once the transfer is finished.
+pub async fn send_file(
+    connection: &Connection,
+    source: &str,
+    destination: &str,
+    file: &Path,
+) -> zbus::Result<(FileTransfer, OwnedObjectPath)> {
+    let client = Client1Proxy::new(connection).await?;
+    let args = HashMap::from([("Target", Value::from("opp")), ("Source", Value::from(source))]);
+    let session = client.create_session(destination, args).await?;
+
+    let push = ObjectPush1Proxy::builder(connection)
+        .path(session.clone())?
+        .build()
+        .await?;
+    let (path, props) = match push.send_file(&file.to_string_lossy()).await {
+        Ok(transfer) => transfer,
+        Err(err) => {
+            let _ = client.remove_session(&session).await;
+            return Err(err);
+        }
+    };
+
+    let mut transfer = FileTransfer {
+        id: path.to_string(),
+        adapter: String::new(),
+        address: destination.to_string(),
+        direction: TransferDirection::Outgoing,
+        name: file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        size: None,
+        transferred: 0,
+        status: TransferStatus::Queued,
+        filename: None,
+    };
+    transfer.update(&props);
+    Ok((transfer, session))
+}
+
+/// Sends `TransferChanged` for every progress update of `transfer` until it
+/// is complete or failed, then removes the client session if there is one.
+pub fn follow_transfer(
+    connection: Connection,
+    mut transfer: FileTransfer,
+    session: Option<OwnedObjectPath>,
+    events: UnboundedSender<BluezEvent>,
+) {
+    tokio::spawn(async move {
+        let changes = transfer_changes(&connection, &transfer.id).await;
+        // Catch up on what changed before the subscription was in place
+        match properties(&connection, &transfer.id, TRANSFER_INTERFACE).await {
+            Ok(props) => transfer.update(&props),
+            Err(_) => transfer.status = TransferStatus::Error,
+        }
+        let _ = events.send(BluezEvent::TransferChanged(transfer.clone()));
+
+        if let Ok(mut changes) = changes {
+            while !transfer.status.is_finished() {
+                let Some(Ok(message)) = changes.next().await else { break };
+                let Ok((_, changed, _)) = message
+                    .body()
+                    .deserialize::<(String, Properties, Vec<String>)>()
+                else {
+                    continue;
+                };
+                transfer.update(&changed);
+                if events.send(BluezEvent::TransferChanged(transfer.clone())).is_err() {
+                    break;
+                }
+            }
+        }
+
+        if let Some(session) = session {
+            if let Ok(client) = Client1Proxy::new(&connection).await {
+                let _ = client.remove_session(&session).await;
+            }
+        }
+    });
+}
+
+async fn transfer_changes(connection: &Connection, transfer: &str) -> zbus::Result<MessageStream> {
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(transfer.to_string())?
+        .arg(0, TRANSFER_INTERFACE)?
+        .build();
+    MessageStream::for_match_rule(rule, connection, None).await
+}
+
+async fn properties(
+    connection: &Connection,
+    path: &str,
+    interface: &'static str,
+) -> zbus::Result<Properties> {
+    PropertiesProxy::builder(connection)
+        .destination(OBEX_SERVICE)?
+        .path(path.to_string())?
+        .build()
+        .await?
+        .get_all(Some(InterfaceName::from_static_str_unchecked(interface)).into())
+        .await
+        .map_err(Into::into)
+}
+
+/// Exports our `org.bluez.obex.Agent1` object and registers it with obexd.
+pub async fn register_agent(
+    connection: &Connection,
+    bluez: BluezClient,
+    events: UnboundedSender<BluezEvent>,
+) -> zbus::Result<()> {
+    let agent = ObexAgent {
+        events,
+        bluez,
+        connection: connection.clone(),
+        current: CurrentRequest::default(),
+    };
+    connection.object_server().at(OBEX_AGENT_PATH, agent).await?;
+
+    let manager = ObexAgentManager1Proxy::new(connection).await?;
+    manager
+        .register_agent(&ObjectPath::from_static_str_unchecked(OBEX_AGENT_PATH))
+        .await
+}
+
+/// Where an incoming file is saved: the download directory, with a number
+/// added when a file of that name already exists.
+pub fn incoming_path(name: &str) -> PathBuf {
+    let dir = dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+    // Never let the sender pick a directory
+    let name = Path::new(name)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "received".to_string());
+
+    let path = dir.join(&name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem.to_string(), format!(".{}", extension)),
+        _ => (name.clone(), String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(path)
+}
+
+/// "512 B", "3.4 KB", "2.6 MB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.bluez.obex.Error")]
+pub enum ObexAgentError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Rejected(String),
+    Canceled(String),
+}
+
+/// `org.bluez.obex.Agent1` implementation that asks the TUI before
+/// accepting a pushed file, through the same dialog as pairing requests.
+struct ObexAgent {
+    events: UnboundedSender<BluezEvent>,
+    bluez: BluezClient,
+    connection: Connection,
+    current: CurrentRequest,
+}
+
+impl ObexAgent {
+    /// The adapter name and device address of the session a transfer belongs to.
+    async fn transfer_owner(&self, session: &str) -> Option<(String, String)> {
+        let props = properties(&self.connection, session, SESSION_INTERFACE)
+            .await
+            .ok()?;
+        let source = prop_string(&props, "Source")?;
+        let destination = prop_string(&props, "Destination")?;
+        let adapter = self
+            .bluez
+            .adapters()
+            .await
+            .ok()?
+            .into_iter()
+            .find(|a| a.address.eq_ignore_ascii_case(&source))?;
+        Some((adapter.name, destination))
+    }
+}
+
+#[interface(name = "org.bluez.obex.Agent1")]
+impl ObexAgent {
+    async fn release(&self) {}
+
+    async fn authorize_push(&self, transfer: OwnedObjectPath) -> Result<String, ObexAgentError> {
+        let props = properties(&self.connection, transfer.as_str(), TRANSFER_INTERFACE).await?;
+        let session = props
+            .get("Session")
+            .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+            .map(|path| path.to_string())
+            .unwrap_or_default();
+        let (adapter, address) = self.transfer_owner(&session).await.unwrap_or_default();
+
+        let mut incoming = FileTransfer {
+            id: transfer.to_string(),
+            adapter: adapter.clone(),
+            address: address.clone(),
+            direction: TransferDirection::Incoming,
+            name: String::new(),
+            size: None,
+            transferred: 0,
+            status: TransferStatus::Queued,
+            filename: None,
+        };
+        incoming.update(&props);
+
+        let prompt = AgentPrompt::AuthorizePush {
+            name: incoming.name.clone(),
+            size: incoming.size,
+        };
+        let (request, reply) = AgentRequest::new(adapter, address, prompt);
+        let id = request.id;
+        self.current.start(&request);
+        self.events
+            .send(BluezEvent::AgentRequest(request))
+            .map_err(|_| ObexAgentError::Canceled("Bluetooth manager is shutting down".to_string()))?;
+
+        let reply = reply.await;
+        self.current.finish(id);
+        match reply {
+            Ok(AgentReply::Reject) => Err(ObexAgentError::Rejected("Rejected by user".to_string())),
+            Ok(_) => {
+                let filename = incoming_path(&incoming.name);
+                incoming.filename = Some(filename.clone());
+                follow_transfer(self.connection.clone(), incoming, None, self.events.clone());
+                Ok(filename.to_string_lossy().into_owned())
+            }
+            Err(_) => Err(ObexAgentError::Canceled("Request canceled".to_string())),
+        }
+    }
+
+    async fn cancel(&self) {
+        self.current.cancel(&self.events);
+    }
+}
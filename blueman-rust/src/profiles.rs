@@ -0,0 +1,60 @@
+/// Bluetooth SIG service class UUIDs are `0000xxxx-0000-1000-8000-00805f9b34fb`;
+/// only the 16-bit `xxxx` part differs.
+const BASE_UUID_SUFFIX: &str = "-0000-1000-8000-00805f9b34fb";
+
+pub const A2DP_SINK_UUID: &str = "0000110b-0000-1000-8000-00805f9b34fb";
+pub const AVRCP_UUID: &str = "0000110e-0000-1000-8000-00805f9b34fb";
+pub const HSP_HEADSET_UUID: &str = "00001108-0000-1000-8000-00805f9b34fb";
+pub const HFP_HANDSFREE_UUID: &str = "0000111e-0000-1000-8000-00805f9b34fb";
+pub const BATTERY_SERVICE_UUID: &str = "0000180f-0000-1000-8000-00805f9b34fb";
+
+/// Short 16-bit form of a SIG UUID, if it is one.
+pub fn short_uuid(uuid: &str) -> Option<u16> {
+    let uuid = uuid.to_ascii_lowercase();
+    let short = uuid.strip_prefix("0000")?.strip_suffix(BASE_UUID_SUFFIX)?;
+    u16::from_str_radix(short, 16).ok()
+}
+
+/// Full UUID of a SIG assigned 16-bit UUID.
+pub fn sig_uuid(short: u16) -> String {
+    format!("0000{:04x}{}", short, BASE_UUID_SUFFIX)
+}
+
+/// Human readable name for a profile or service UUID from `Device1.UUIDs`.
+pub fn profile_name(uuid: &str) -> Option<&'static str> {
+    let name = match short_uuid(uuid)? {
+        0x1101 => "Serial Port",
+        0x1105 => "Object Push",
+        0x1106 => "File Transfer",
+        0x1108 => "Headset (HSP)",
+        0x110a => "Audio Source (A2DP)",
+        0x110b => "Audio Sink (A2DP)",
+        0x110c => "Remote Control Target (AVRCP)",
+        0x110d => "Advanced Audio (A2DP)",
+        0x110e => "Remote Control (AVRCP)",
+        0x110f => "Remote Control Controller (AVRCP)",
+        0x1112 => "Headset Gateway (HSP)",
+        0x1115 => "Network User (PANU)",
+        0x1116 => "Network Access Point (NAP)",
+        0x111e => "Handsfree (HFP)",
+        0x111f => "Handsfree Gateway (HFP)",
+        0x1124 => "Human Interface Device",
+        0x112f => "Phonebook Access (PBAP)",
+        0x1132 => "Message Access (MAP)",
+        0x1200 => "PnP Information",
+        0x1800 => "Generic Access",
+        0x1801 => "Generic Attribute",
+        0x180a => "Device Information",
+        0x180f => "Battery Service",
+        0x1812 => "HID over GATT",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Name for display: the profile name, or the raw UUID when it is not known.
+pub fn profile_label(uuid: &str) -> String {
+    profile_name(uuid)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid.to_string())
+}
@@ -0,0 +1,278 @@
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Reads rfkill state from this directory instead of `/sys/class/rfkill`:
+/// a tree of `rfkillN/{type,name,soft,hard}` files, as the kernel lays it out.
+pub const RFKILL_ENV_VAR: &str = "BLUETOOTH_MANAGER_RFKILL";
+
+const SYSFS_RFKILL: &str = "/sys/class/rfkill";
+const RFKILL_DEVICE: &str = "/dev/rfkill";
+
+// struct rfkill_event from <linux/rfkill.h>
+const RFKILL_TYPE_BLUETOOTH: u8 = 2;
+const RFKILL_OP_CHANGE: u8 = 2;
+
+/// One Bluetooth rfkill switch, usually one per adapter.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RfkillSwitch {
+    pub index: u32, // N of rfkillN
+    pub name: String, // hciN for adapters
+    pub soft: bool, // blocked from software (rfkill, airplane mode)
+    pub hard: bool, // blocked by a hardware switch or the firmware
+}
+
+/// What the Bluetooth switches together allow.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BlockState {
+    #[default]
+    Unblocked,
+    SoftBlocked,
+    HardBlocked, // whatever the soft state, only the hardware can lift it
+}
+
+/// The switches that block `adapter`: its own, which are named after it
+/// (hciN), or all of them when it has none, e.g. only a laptop's platform
+/// switch.
+pub fn adapter_switches(switches: &[RfkillSwitch], adapter: &str) -> Vec<RfkillSwitch> {
+    let own: Vec<RfkillSwitch> = switches.iter().filter(|s| s.name == adapter).cloned().collect();
+    if own.is_empty() {
+        switches.to_vec()
+    } else {
+        own
+    }
+}
+
+impl BlockState {
+    pub fn of(switches: &[RfkillSwitch]) -> BlockState {
+        if switches.iter().any(|s| s.hard) {
+            BlockState::HardBlocked
+        } else if switches.iter().any(|s| s.soft) {
+            BlockState::SoftBlocked
+        } else {
+            BlockState::Unblocked
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BlockState::Unblocked => "Unblocked",
+            BlockState::SoftBlocked => "Soft-blocked",
+            BlockState::HardBlocked => "Hard-blocked",
+        }
+    }
+}
+
+/// Access to the kernel's rfkill switches through sysfs.
+#[derive(Debug, Clone, Default)]
+pub struct Rfkill {
+    root: Option<PathBuf>, // None: rfkill is not looked at
+    device: Option<PathBuf>, // control device for when sysfs is not writable
+}
+
+impl Rfkill {
+    /// The real switches, or the tree named by `BLUETOOTH_MANAGER_RFKILL`.
+    pub fn system() -> Rfkill {
+        match std::env::var_os(RFKILL_ENV_VAR) {
+            Some(root) => Rfkill::with_root(PathBuf::from(root)),
+            None => Rfkill {
+                root: Some(PathBuf::from(SYSFS_RFKILL)),
+                device: Some(PathBuf::from(RFKILL_DEVICE)),
+            },
+        }
+    }
+
+    /// Switches read from, and unblocked in, a sysfs-like tree at `root`.
+    pub fn with_root(root: PathBuf) -> Rfkill {
+        Rfkill {
+            root: Some(root),
+            device: None,
+        }
+    }
+
+    /// Never reports a switch, for the simulated backend.
+    pub fn disabled() -> Rfkill {
+        Rfkill::default()
+    }
+
+    /// The Bluetooth switches, by index. A kernel without rfkill has none.
+    pub fn switches(&self) -> io::Result<Vec<RfkillSwitch>> {
+        let Some(root) = &self.root else { return Ok(vec![]) };
+        let entries = match fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+
+        let mut switches = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            let Some(index) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("rfkill"))
+                .and_then(|index| index.parse().ok())
+            else {
+                continue;
+            };
+            if read_value(&path, "type")? != "bluetooth" {
+                continue;
+            }
+            switches.push(RfkillSwitch {
+                index,
+                name: read_value(&path, "name")?,
+                soft: read_value(&path, "soft")? == "1",
+                hard: read_value(&path, "hard")? == "1",
+            });
+        }
+        switches.sort_by_key(|s| s.index);
+        Ok(switches)
+    }
+
+    /// Lifts the soft block of the switches of `adapter` (see
+    /// [`adapter_switches`]), like `rfkill unblock N`. Hard blocks, and the
+    /// switches of other adapters, are left as they are.
+    pub fn unblock(&self, adapter: &str) -> io::Result<()> {
+        let Some(root) = &self.root else { return Ok(()) };
+        let switches = adapter_switches(&self.switches()?, adapter);
+        for switch in switches.iter().filter(|s| s.soft) {
+            let soft = root.join(format!("rfkill{}", switch.index)).join("soft");
+            match fs::write(&soft, "0") {
+                Ok(()) => {}
+                // sysfs is root-only; /dev/rfkill is usually open to the
+                // logged-in user.
+                Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                    let Some(device) = &self.device else { return Err(err) };
+                    unblock_switch(device, switch.index)?;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_value(switch: &Path, file: &str) -> io::Result<String> {
+    fs::read_to_string(switch.join(file)).map(|value| value.trim().to_string())
+}
+
+/// Writes an RFKILL_OP_CHANGE event with soft = 0 for switch `index`.
+fn unblock_switch(device: &Path, index: u32) -> io::Result<()> {
+    fs::OpenOptions::new()
+        .write(true)
+        .open(device)?
+        .write_all(&unblock_event(index))
+}
+
+/// idx (u32, native endian), type, op, soft, hard
+fn unblock_event(index: u32) -> [u8; 8] {
+    let mut event = [0u8; 8];
+    event[..4].copy_from_slice(&index.to_ne_bytes());
+    event[4] = RFKILL_TYPE_BLUETOOTH;
+    event[5] = RFKILL_OP_CHANGE;
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh sysfs-like tree under the temporary directory.
+    fn tree(name: &str) -> PathBuf {
+        let dir = format!("bluetooth-manager-{}-{}", name, std::process::id());
+        let root = std::env::temp_dir().join(dir);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn add_switch(root: &Path, index: u32, kind: &str, name: &str, soft: bool, hard: bool) {
+        let dir = root.join(format!("rfkill{}", index));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+        fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+        fs::write(dir.join("soft"), format!("{}\n", u8::from(soft))).unwrap();
+        fs::write(dir.join("hard"), format!("{}\n", u8::from(hard))).unwrap();
+    }
+
+    fn switch(index: u32, soft: bool, hard: bool) -> RfkillSwitch {
+        RfkillSwitch {
+            index,
+            name: format!("hci{}", index),
+            soft,
+            hard,
+        }
+    }
+
+    #[test]
+    fn switches_lists_bluetooth_only_by_index() {
+        let root = tree("switches");
+        add_switch(&root, 10, "bluetooth", "hci1", true, false);
+        add_switch(&root, 2, "bluetooth", "hci0", false, false);
+        add_switch(&root, 3, "wlan", "phy0", true, false);
+        fs::create_dir_all(root.join("other")).unwrap();
+
+        let switches = Rfkill::with_root(root.clone()).switches().unwrap();
+        assert_eq!(
+            switches,
+            vec![
+                RfkillSwitch { index: 2, name: "hci0".to_string(), soft: false, hard: false },
+                RfkillSwitch { index: 10, name: "hci1".to_string(), soft: true, hard: false },
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn no_rfkill_means_no_switches() {
+        let missing = std::env::temp_dir().join("bluetooth-manager-no-such-rfkill");
+        assert!(Rfkill::with_root(missing).switches().unwrap().is_empty());
+        assert!(Rfkill::disabled().switches().unwrap().is_empty());
+    }
+
+    #[test]
+    fn hard_block_wins_over_soft() {
+        assert_eq!(BlockState::of(&[]), BlockState::Unblocked);
+        assert_eq!(BlockState::of(&[switch(0, false, false)]), BlockState::Unblocked);
+        let soft = [switch(0, false, false), switch(1, true, false)];
+        assert_eq!(BlockState::of(&soft), BlockState::SoftBlocked);
+        let hard = [switch(0, true, false), switch(1, false, true)];
+        assert_eq!(BlockState::of(&hard), BlockState::HardBlocked);
+    }
+
+    #[test]
+    fn adapters_are_blocked_by_their_own_switch() {
+        let switches = [switch(0, false, false), switch(1, true, false)];
+        assert_eq!(adapter_switches(&switches, "hci1"), vec![switch(1, true, false)]);
+        assert_eq!(BlockState::of(&adapter_switches(&switches, "hci0")), BlockState::Unblocked);
+        // Without a switch of its own, every switch counts.
+        assert_eq!(adapter_switches(&switches, "hci2"), switches.to_vec());
+    }
+
+    #[test]
+    fn unblock_clears_the_soft_block_of_one_adapter() {
+        let root = tree("unblock");
+        add_switch(&root, 0, "bluetooth", "hci0", true, false);
+        add_switch(&root, 1, "bluetooth", "hci1", true, true);
+        add_switch(&root, 2, "wlan", "phy0", true, false);
+
+        let rfkill = Rfkill::with_root(root.clone());
+        rfkill.unblock("hci0").unwrap();
+        assert_eq!(rfkill.switches().unwrap(), vec![switch(0, false, false), switch(1, true, true)]);
+        rfkill.unblock("hci1").unwrap();
+        assert_eq!(rfkill.switches().unwrap(), vec![switch(0, false, false), switch(1, false, true)]);
+        assert_eq!(read_value(&root.join("rfkill1"), "hard").unwrap(), "1");
+        assert_eq!(read_value(&root.join("rfkill2"), "soft").unwrap(), "1");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn unblock_event_targets_one_switch() {
+        let event = unblock_event(5);
+        assert_eq!(u32::from_ne_bytes(event[..4].try_into().unwrap()), 5);
+        assert_eq!(event[4..], [RFKILL_TYPE_BLUETOOTH, RFKILL_OP_CHANGE, 0, 0]);
+    }
+}
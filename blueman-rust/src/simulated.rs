@@ -0,0 +1,1038 @@
+use crate::agent::{AgentPrompt, AgentReply, AgentRequest};
+use crate::app::{BluetoothAdapter, BluetoothDevice};
+use crate::backend::{BackendError, BackendResult, BluetoothBackend};
+use crate::bluez::{BluezEvent, DeviceOperation};
+use crate::device_type::DeviceType;
+use crate::discovery_filter::DiscoveryFilter;
+use crate::gatt::{GattAttribute, GattKind};
+use crate::known_devices::KnownDevices;
+use crate::obex::{incoming_path, FileTransfer, TransferDirection, TransferStatus};
+use crate::profiles::{
+    sig_uuid, A2DP_SINK_UUID, AVRCP_UUID, BATTERY_SERVICE_UUID, HFP_HANDSFREE_UUID,
+    HSP_HEADSET_UUID,
+};
+use async_trait::async_trait;
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Delay between two scripted steps while an adapter is discovering.
+const STEP_INTERVAL: Duration = Duration::from_millis(800);
+/// How long a simulated connect/disconnect/pair takes.
+const OPERATION_DELAY: Duration = Duration::from_millis(500);
+/// Number of progress updates a simulated file transfer goes through.
+const TRANSFER_STEPS: u64 = 5;
+/// Passkey shown in the confirmation prompt of a simulated pairing.
+const PAIRING_PASSKEY: u32 = 123_456;
+/// Profiles that carry the audio stream; only one of them is active at a time.
+const AUDIO_PROFILES: [&str; 3] = [A2DP_SINK_UUID, HFP_HANDSFREE_UUID, HSP_HEADSET_UUID];
+/// Vendor service of the demo headphones, with a control point that echoes
+/// every write back as a notification.
+const VENDOR_SERVICE_UUID: &str = "8a5c0000-2b4e-4f1a-9d3c-5e6f7a8b9c0d";
+const VENDOR_CONTROL_POINT_UUID: &str = "8a5c0001-2b4e-4f1a-9d3c-5e6f7a8b9c0d";
+const BATTERY_LEVEL_UUID: u16 = 0x2a19;
+const CLIENT_CONFIGURATION_UUID: u16 = 0x2902;
+
+/// A change played back, in order, while discovery is running.
+#[derive(Debug, Clone)]
+pub enum SimulatedStep {
+    Appear(BluetoothDevice),
+    Rssi {
+        adapter: String,
+        address: String,
+        rssi: i16,
+    },
+    Battery {
+        adapter: String,
+        address: String,
+        percentage: u8,
+    },
+    Vanish {
+        adapter: String,
+        address: String,
+    },
+    /// The device pushes a file, offered to the OBEX agent.
+    IncomingFile {
+        adapter: String,
+        address: String,
+        name: String,
+        size: u64,
+    },
+}
+
+/// A Device1 call that always fails with the given BlueZ error.
+#[derive(Debug, Clone)]
+struct SimulatedFailure {
+    address: String,
+    operation: DeviceOperation,
+    error: String,
+}
+
+#[derive(Default)]
+struct SimulatedState {
+    adapters: Vec<BluetoothAdapter>,
+    devices: Vec<BluetoothDevice>,
+    script: Vec<SimulatedStep>,
+    next_step: usize,
+    failures: Vec<SimulatedFailure>,
+    trust_failures: Vec<(String, String)>, // (address, error) of Trusted writes that fail
+    discovery_filter: DiscoveryFilter,
+    gatt: Vec<GattAttribute>, // of every device, told apart by path
+    events: Option<UnboundedSender<BluezEvent>>,
+    agent: Option<UnboundedSender<BluezEvent>>,
+    obex_agent: Option<UnboundedSender<BluezEvent>>,
+    transfer_count: u32, // numbers the transfer object paths
+    playing: bool,
+}
+
+impl SimulatedState {
+    fn emit(&self, event: BluezEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    fn adapter_mut(&mut self, name: &str) -> BackendResult<&mut BluetoothAdapter> {
+        self.adapters
+            .iter_mut()
+            .find(|a| a.name == name)
+            .ok_or_else(|| does_not_exist(name))
+    }
+
+    fn device_mut(&mut self, adapter: &str, address: &str) -> BackendResult<&mut BluetoothDevice> {
+        self.devices
+            .iter_mut()
+            .find(|d| d.is(adapter, address))
+            .ok_or_else(|| does_not_exist(address))
+    }
+
+    fn gatt_mut(&mut self, path: &str) -> BackendResult<&mut GattAttribute> {
+        self.gatt
+            .iter_mut()
+            .find(|a| a.path == path)
+            .ok_or_else(|| does_not_exist(path))
+    }
+
+    /// The device a GATT attribute belongs to, which has to be connected.
+    fn gatt_device(&self, path: &str) -> BackendResult<&BluetoothDevice> {
+        let device = self
+            .devices
+            .iter()
+            .find(|d| path.starts_with(&format!("{}/", simulated_path(&d.adapter, &d.address))))
+            .ok_or_else(|| does_not_exist(path))?;
+        if !device.connected {
+            return Err(BackendError("org.bluez.Error.Failed: Not connected".to_string()));
+        }
+        Ok(device)
+    }
+
+    /// Stores a characteristic value and reports it like a notification
+    /// when notifications are on.
+    fn notify(&mut self, path: &str, value: Vec<u8>) {
+        let Ok(attribute) = self.gatt_mut(path) else { return };
+        attribute.value = Some(value.clone());
+        if attribute.notifying {
+            self.emit(BluezEvent::GattValueChanged {
+                path: path.to_string(),
+                value,
+            });
+        }
+    }
+
+    fn new_transfer(
+        &mut self,
+        adapter: &str,
+        address: &str,
+        direction: TransferDirection,
+        name: String,
+        size: u64,
+    ) -> FileTransfer {
+        self.transfer_count += 1;
+        let side = match direction {
+            TransferDirection::Outgoing => "client",
+            TransferDirection::Incoming => "server",
+        };
+        FileTransfer {
+            id: format!(
+                "/org/bluez/obex/{}/session{}/transfer{}",
+                side, self.transfer_count, self.transfer_count
+            ),
+            adapter: adapter.to_string(),
+            address: address.to_string(),
+            direction,
+            name,
+            size: Some(size),
+            transferred: 0,
+            status: TransferStatus::Queued,
+            filename: None,
+        }
+    }
+
+    fn is_discovering(&self) -> bool {
+        self.adapters.iter().any(|a| a.discovering)
+    }
+
+    /// Applies the next scripted step. Returns false once the script is exhausted.
+    fn step(&mut self) -> bool {
+        let Some(step) = self.script.get(self.next_step).cloned() else {
+            return false;
+        };
+        self.next_step += 1;
+
+        match step {
+            SimulatedStep::Appear(device) if !reported(&self.discovery_filter, &device) => {}
+            SimulatedStep::Appear(device) => {
+                self.devices
+                    .retain(|d| !d.is(&device.adapter, &device.address));
+                self.devices.push(device.clone());
+                self.emit(BluezEvent::DeviceAdded(device));
+            }
+            SimulatedStep::Rssi {
+                adapter,
+                address,
+                rssi,
+            } => {
+                if let Ok(device) = self.device_mut(&adapter, &address) {
+                    device.rssi = Some(rssi);
+                    let device = device.clone();
+                    self.emit(BluezEvent::DeviceChanged(device));
+                }
+            }
+            SimulatedStep::Battery {
+                adapter,
+                address,
+                percentage,
+            } => {
+                if let Ok(device) = self.device_mut(&adapter, &address) {
+                    device.battery = Some(percentage);
+                    let device = device.clone();
+                    self.emit(BluezEvent::DeviceChanged(device));
+                }
+                let battery_level = sig_uuid(BATTERY_LEVEL_UUID);
+                let prefix = format!("{}/", simulated_path(&adapter, &address));
+                let paths: Vec<String> = self
+                    .gatt
+                    .iter()
+                    .filter(|a| a.path.starts_with(&prefix) && a.uuid == battery_level)
+                    .map(|a| a.path.clone())
+                    .collect();
+                for path in paths {
+                    self.notify(&path, vec![percentage]);
+                }
+            }
+            SimulatedStep::Vanish { adapter, address } => {
+                self.devices.retain(|d| !d.is(&adapter, &address));
+                self.emit(BluezEvent::DeviceRemoved { adapter, address });
+            }
+            SimulatedStep::IncomingFile {
+                adapter,
+                address,
+                name,
+                size,
+            } => {
+                let (Some(agent), Some(events)) = (self.obex_agent.clone(), self.events.clone())
+                else {
+                    return true;
+                };
+                let mut transfer = self.new_transfer(
+                    &adapter,
+                    &address,
+                    TransferDirection::Incoming,
+                    name.clone(),
+                    size,
+                );
+                tokio::spawn(async move {
+                    let prompt = AgentPrompt::AuthorizePush {
+                        name: name.clone(),
+                        size: Some(size),
+                    };
+                    let (request, reply) = AgentRequest::new(adapter, address, prompt);
+                    if agent.send(BluezEvent::AgentRequest(request)).is_err() {
+                        return;
+                    }
+                    // Nothing is written; the path is only what obexd would be told
+                    if let Ok(AgentReply::Accept) = reply.await {
+                        transfer.filename = Some(incoming_path(&name));
+                        play_transfer(events, transfer).await;
+                    }
+                });
+            }
+        }
+        true
+    }
+}
+
+/// In-memory backend that plays a fixed script of devices, RSSI changes and
+/// failures. Nothing depends on timing other than the order of the script,
+/// so it behaves the same on every run and needs no Bluetooth hardware.
+#[derive(Clone, Default)]
+pub struct SimulatedBackend {
+    state: Arc<Mutex<SimulatedState>>,
+}
+
+impl SimulatedBackend {
+    pub fn new() -> SimulatedBackend {
+        SimulatedBackend::default()
+    }
+
+    /// Two adapters (one powered off), connected headphones running low on
+    /// battery and with a small GATT database, and a short discovery script
+    /// that includes a device that refuses connections.
+    pub fn demo() -> SimulatedBackend {
+        let mut headphones = simulated_device(
+            "hci0",
+            "00:1B:66:A1:B2:C3",
+            "WH-1000XM4",
+            DeviceType::Headphones,
+            None,
+        );
+        headphones.paired = true;
+        headphones.trusted = true;
+        headphones.known = true;
+        headphones.connected = true;
+        headphones.battery = Some(24);
+        headphones.uuids = [
+            HSP_HEADSET_UUID,
+            A2DP_SINK_UUID,
+            AVRCP_UUID,
+            HFP_HANDSFREE_UUID,
+            BATTERY_SERVICE_UUID,
+        ]
+        .map(str::to_string)
+        .to_vec();
+        headphones.active_profiles = vec![A2DP_SINK_UUID.to_string()];
+
+        let mut speaker = simulated_device(
+            "hci0",
+            "11:22:33:44:55:66",
+            "JBL Flip 5",
+            DeviceType::Speaker,
+            Some(-62),
+        );
+        speaker.paired = true;
+        speaker.trusted = true;
+        speaker.known = true;
+        speaker.uuids = [A2DP_SINK_UUID, AVRCP_UUID, HFP_HANDSFREE_UUID]
+            .map(str::to_string)
+            .to_vec();
+
+        SimulatedBackend::new()
+            .with_adapter(simulated_adapter("hci0", "00:1A:7D:DA:71:13", true))
+            .with_adapter(simulated_adapter("hci1", "5C:F3:70:8B:12:04", false))
+            .with_gatt(demo_gatt("hci0", "00:1B:66:A1:B2:C3", "WH-1000XM4", 24))
+            .with_device(headphones)
+            .with_step(SimulatedStep::Appear(speaker))
+            .with_step(SimulatedStep::Appear(simulated_device(
+                "hci0",
+                "AA:BB:CC:DD:EE:FF",
+                "MX Keys",
+                DeviceType::Keyboard,
+                Some(-48),
+            )))
+            .with_step(SimulatedStep::Rssi {
+                adapter: "hci0".to_string(),
+                address: "11:22:33:44:55:66".to_string(),
+                rssi: -55,
+            })
+            .with_step(SimulatedStep::Appear(simulated_device(
+                "hci0",
+                "7C:96:D2:10:4E:8A",
+                "Pixel 7",
+                DeviceType::Phone,
+                Some(-71),
+            )))
+            .with_step(SimulatedStep::Rssi {
+                adapter: "hci0".to_string(),
+                address: "AA:BB:CC:DD:EE:FF".to_string(),
+                rssi: -41,
+            })
+            .with_step(SimulatedStep::Appear(simulated_device(
+                "hci0",
+                "C8:3A:1F:77:02:B5",
+                "MX Master 3",
+                DeviceType::Mouse,
+                Some(-58),
+            )))
+            .with_step(SimulatedStep::Appear(simulated_device(
+                "hci0",
+                "E4:5F:01:3C:9D:20",
+                "E4-5F-01-3C-9D-20",
+                DeviceType::Unknown,
+                Some(-88),
+            )))
+            .with_step(SimulatedStep::Vanish {
+                adapter: "hci0".to_string(),
+                address: "E4:5F:01:3C:9D:20".to_string(),
+            })
+            .with_step(SimulatedStep::Rssi {
+                adapter: "hci0".to_string(),
+                address: "11:22:33:44:55:66".to_string(),
+                rssi: -49,
+            })
+            .with_step(SimulatedStep::Rssi {
+                adapter: "hci0".to_string(),
+                address: "11:22:33:44:55:66".to_string(),
+                rssi: -44,
+            })
+            .with_step(SimulatedStep::Battery {
+                adapter: "hci0".to_string(),
+                address: "00:1B:66:A1:B2:C3".to_string(),
+                percentage: 18,
+            })
+            .with_step(SimulatedStep::IncomingFile {
+                adapter: "hci0".to_string(),
+                address: "7C:96:D2:10:4E:8A".to_string(),
+                name: "IMG_20240612_181503.jpg".to_string(),
+                size: 2_457_600,
+            })
+            .with_failure(
+                "7C:96:D2:10:4E:8A",
+                DeviceOperation::Connect,
+                "org.bluez.Error.Failed: br-connection-profile-unavailable",
+            )
+    }
+
+    /// Known devices to run `demo` with: the speaker is set to auto-connect
+    /// with A2DP, so it is reconnected as soon as the scan finds it.
+    pub fn demo_known_devices() -> KnownDevices {
+        let speaker = simulated_device(
+            "hci0",
+            "11:22:33:44:55:66",
+            "JBL Flip 5",
+            DeviceType::Speaker,
+            None,
+        );
+        let mut known_devices = KnownDevices::in_memory();
+        known_devices.set_auto_connect(&speaker, true);
+        known_devices.set_preferred_profile(&speaker, A2DP_SINK_UUID);
+        known_devices
+    }
+
+    pub fn with_adapter(self, adapter: BluetoothAdapter) -> SimulatedBackend {
+        self.lock().adapters.push(adapter);
+        self
+    }
+
+    pub fn with_device(self, device: BluetoothDevice) -> SimulatedBackend {
+        self.lock().devices.push(device);
+        self
+    }
+
+    pub fn with_gatt(self, attributes: Vec<GattAttribute>) -> SimulatedBackend {
+        self.lock().gatt.extend(attributes);
+        self
+    }
+
+    pub fn with_step(self, step: SimulatedStep) -> SimulatedBackend {
+        self.lock().script.push(step);
+        self
+    }
+
+    pub fn with_failure(
+        self,
+        address: &str,
+        operation: DeviceOperation,
+        error: &str,
+    ) -> SimulatedBackend {
+        self.lock().failures.push(SimulatedFailure {
+            address: address.to_string(),
+            operation,
+            error: error.to_string(),
+        });
+        self
+    }
+
+    /// Makes setting `Trusted` on the device fail with the given BlueZ error.
+    #[cfg(test)]
+    pub fn with_trust_failure(self, address: &str, error: &str) -> SimulatedBackend {
+        self.lock()
+            .trust_failures
+            .push((address.to_string(), error.to_string()));
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SimulatedState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Plays the script while any adapter is discovering.
+    fn start_playing(&self) {
+        let mut state = self.lock();
+        if state.playing {
+            return;
+        }
+        state.playing = true;
+        drop(state);
+
+        let backend = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(STEP_INTERVAL).await;
+                let mut state = backend.lock();
+                if !state.is_discovering() || !state.step() {
+                    state.playing = false;
+                    break;
+                }
+            }
+        });
+    }
+
+    fn update_adapter(
+        &self,
+        name: &str,
+        change: impl FnOnce(&mut BluetoothAdapter) -> BackendResult<()>,
+    ) -> BackendResult<()> {
+        let mut state = self.lock();
+        let adapter = state.adapter_mut(name)?;
+        change(adapter)?;
+        let adapter = adapter.clone();
+        state.emit(BluezEvent::AdapterChanged(adapter));
+        Ok(())
+    }
+
+    /// Asks the registered agent to confirm the pairing passkey.
+    async fn confirm_pairing(&self, adapter: &str, address: &str) -> BackendResult<()> {
+        let Some(agent) = self.lock().agent.clone() else {
+            return Ok(());
+        };
+
+        let (request, reply) = AgentRequest::new(
+            adapter.to_string(),
+            address.to_string(),
+            AgentPrompt::RequestConfirmation(PAIRING_PASSKEY),
+        );
+        if agent.send(BluezEvent::AgentRequest(request)).is_err() {
+            return Err(authentication_canceled());
+        }
+
+        match reply.await {
+            Ok(AgentReply::Reject) => Err(BackendError(
+                "org.bluez.Error.AuthenticationRejected: Authentication Rejected".to_string(),
+            )),
+            Ok(_) => Ok(()),
+            Err(_) => Err(authentication_canceled()),
+        }
+    }
+}
+
+#[async_trait]
+impl BluetoothBackend for SimulatedBackend {
+    async fn adapters(&self) -> BackendResult<Vec<BluetoothAdapter>> {
+        let mut adapters = self.lock().adapters.clone();
+        adapters.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(adapters)
+    }
+
+    async fn devices(&self) -> BackendResult<Vec<BluetoothDevice>> {
+        let mut devices = self.lock().devices.clone();
+        devices.sort_by(|a, b| (&a.adapter, &a.address).cmp(&(&b.adapter, &b.address)));
+        Ok(devices)
+    }
+
+    async fn device(&self, adapter: &str, address: &str) -> BackendResult<BluetoothDevice> {
+        self.lock().device_mut(adapter, address).map(|d| d.clone())
+    }
+
+    async fn set_discovery_filter(&self, adapter: &str, filter: &DiscoveryFilter) -> BackendResult<()> {
+        let mut state = self.lock();
+        state.adapter_mut(adapter)?;
+        state.discovery_filter = filter.clone();
+        Ok(())
+    }
+
+    async fn start_discovery(&self, adapter: &str) -> BackendResult<()> {
+        self.update_adapter(adapter, |a| {
+            if !a.powered {
+                return Err(not_ready());
+            }
+            a.discovering = true;
+            Ok(())
+        })?;
+        self.start_playing();
+        Ok(())
+    }
+
+    async fn stop_discovery(&self, adapter: &str) -> BackendResult<()> {
+        self.update_adapter(adapter, |a| {
+            if !a.discovering {
+                return Err(BackendError(
+                    "org.bluez.Error.Failed: No discovery started".to_string(),
+                ));
+            }
+            a.discovering = false;
+            Ok(())
+        })
+    }
+
+    async fn set_powered(&self, adapter: &str, powered: bool) -> BackendResult<()> {
+        self.update_adapter(adapter, |a| {
+            a.powered = powered;
+            if !powered {
+                a.discovering = false;
+                a.discoverable = false;
+            }
+            Ok(())
+        })?;
+
+        if !powered {
+            let mut state = self.lock();
+            let dropped: Vec<BluetoothDevice> = state
+                .devices
+                .iter_mut()
+                .filter(|d| d.adapter == adapter && d.connected)
+                .map(|d| {
+                    d.connected = false;
+                    d.clone()
+                })
+                .collect();
+            for device in dropped {
+                state.emit(BluezEvent::DeviceChanged(device));
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_discoverable(&self, adapter: &str, discoverable: bool) -> BackendResult<()> {
+        self.update_adapter(adapter, |a| {
+            a.discoverable = discoverable;
+            Ok(())
+        })
+    }
+
+    async fn set_pairable(&self, adapter: &str, pairable: bool) -> BackendResult<()> {
+        self.update_adapter(adapter, |a| {
+            a.pairable = pairable;
+            Ok(())
+        })
+    }
+
+    async fn set_discoverable_timeout(&self, adapter: &str, seconds: u32) -> BackendResult<()> {
+        self.update_adapter(adapter, |a| {
+            a.discoverable_timeout = seconds;
+            Ok(())
+        })
+    }
+
+    async fn set_adapter_alias(&self, adapter: &str, alias: &str) -> BackendResult<()> {
+        self.update_adapter(adapter, |a| {
+            a.alias = if alias.is_empty() {
+                format!("Simulated {}", a.name)
+            } else {
+                alias.to_string()
+            };
+            Ok(())
+        })
+    }
+
+    async fn set_device_alias(&self, adapter: &str, address: &str, alias: &str) -> BackendResult<()> {
+        let mut state = self.lock();
+        let device = state.device_mut(adapter, address)?;
+        device.alias = (!alias.is_empty()).then(|| alias.to_string());
+        let device = device.clone();
+        state.emit(BluezEvent::DeviceChanged(device));
+        Ok(())
+    }
+
+    async fn set_device_trusted(&self, adapter: &str, address: &str, trusted: bool) -> BackendResult<()> {
+        let mut state = self.lock();
+        if let Some((_, error)) = state.trust_failures.iter().find(|(a, _)| a == address) {
+            return Err(BackendError(error.clone()));
+        }
+        let device = state.device_mut(adapter, address)?;
+        device.trusted = trusted;
+        let device = device.clone();
+        state.emit(BluezEvent::DeviceChanged(device));
+        Ok(())
+    }
+
+    async fn set_device_blocked(&self, adapter: &str, address: &str, blocked: bool) -> BackendResult<()> {
+        let mut state = self.lock();
+        let device = state.device_mut(adapter, address)?;
+        device.blocked = blocked;
+        if blocked {
+            device.connected = false;
+            device.active_profiles.clear();
+        }
+        let device = device.clone();
+        state.emit(BluezEvent::DeviceChanged(device));
+        Ok(())
+    }
+
+    async fn device_operation(
+        &self,
+        adapter: &str,
+        address: &str,
+        operation: &DeviceOperation,
+    ) -> BackendResult<()> {
+        {
+            let mut state = self.lock();
+            if !state.adapter_mut(adapter)?.powered {
+                return Err(not_ready());
+            }
+            if state.device_mut(adapter, address)?.blocked
+                && matches!(operation, DeviceOperation::Connect | DeviceOperation::ConnectProfile(_))
+            {
+                return Err(BackendError("org.bluez.Error.Failed: Device is blocked".to_string()));
+            }
+        }
+
+        tokio::time::sleep(OPERATION_DELAY).await;
+        if *operation == DeviceOperation::Pair {
+            self.confirm_pairing(adapter, address).await?;
+        }
+
+        let mut state = self.lock();
+        if let Some(failure) = state
+            .failures
+            .iter()
+            .find(|f| f.address == address && f.operation == *operation)
+        {
+            return Err(BackendError(failure.error.clone()));
+        }
+
+        let device = state.device_mut(adapter, address)?;
+        match operation {
+            DeviceOperation::Connect => {
+                device.connected = true;
+                if device.uuids.iter().any(|uuid| uuid == A2DP_SINK_UUID) {
+                    device.active_profiles = vec![A2DP_SINK_UUID.to_string()];
+                }
+            }
+            DeviceOperation::Disconnect => {
+                device.connected = false;
+                device.active_profiles.clear();
+            }
+            DeviceOperation::Pair => {
+                device.paired = true;
+                device.known = true;
+            }
+            DeviceOperation::ConnectProfile(uuid) => {
+                if !device.uuids.contains(uuid) {
+                    return Err(BackendError(
+                        "org.bluez.Error.InvalidArguments: Invalid arguments in method call"
+                            .to_string(),
+                    ));
+                }
+                // A headset streams either high quality audio or call audio.
+                if AUDIO_PROFILES.contains(&uuid.as_str()) {
+                    device
+                        .active_profiles
+                        .retain(|active| !AUDIO_PROFILES.contains(&active.as_str()));
+                }
+                device.active_profiles.push(uuid.clone());
+                device.connected = true;
+            }
+            DeviceOperation::DisconnectProfile(uuid) => {
+                if !device.active_profiles.contains(uuid) {
+                    return Err(BackendError(
+                        "org.bluez.Error.NotConnected: Not Connected".to_string(),
+                    ));
+                }
+                device.active_profiles.retain(|active| active != uuid);
+            }
+        }
+        let device = device.clone();
+        state.emit(BluezEvent::DeviceChanged(device));
+        Ok(())
+    }
+
+    async fn remove_device(&self, adapter: &str, address: &str) -> BackendResult<()> {
+        let mut state = self.lock();
+        state.device_mut(adapter, address)?;
+        state.devices.retain(|d| !d.is(adapter, address));
+        state.emit(BluezEvent::DeviceRemoved {
+            adapter: adapter.to_string(),
+            address: address.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn gatt_attributes(&self, adapter: &str, address: &str) -> BackendResult<Vec<GattAttribute>> {
+        let mut state = self.lock();
+        state.device_mut(adapter, address)?;
+        let prefix = format!("{}/", simulated_path(adapter, address));
+        let mut attributes: Vec<GattAttribute> = state
+            .gatt
+            .iter()
+            .filter(|a| a.path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        attributes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(attributes)
+    }
+
+    async fn read_gatt_value(&self, path: &str) -> BackendResult<Vec<u8>> {
+        let mut state = self.lock();
+        state.gatt_device(path)?;
+        let attribute = state.gatt_mut(path)?;
+        if !attribute.can_read() {
+            return Err(not_permitted("Read"));
+        }
+        let value = attribute.value.clone().unwrap_or_default();
+        if attribute.kind == GattKind::Characteristic {
+            state.emit(BluezEvent::GattValueChanged {
+                path: path.to_string(),
+                value: value.clone(),
+            });
+        }
+        Ok(value)
+    }
+
+    async fn write_gatt_value(&self, path: &str, value: &[u8]) -> BackendResult<()> {
+        let mut state = self.lock();
+        state.gatt_device(path)?;
+        let attribute = state.gatt_mut(path)?;
+        if !attribute.can_write() {
+            return Err(not_permitted("Write"));
+        }
+        attribute.value = Some(value.to_vec());
+        if attribute.uuid == VENDOR_CONTROL_POINT_UUID {
+            state.notify(path, value.to_vec());
+        }
+        Ok(())
+    }
+
+    async fn set_gatt_notify(&self, path: &str, enabled: bool) -> BackendResult<()> {
+        let mut state = self.lock();
+        state.gatt_device(path)?;
+        let attribute = state.gatt_mut(path)?;
+        if !attribute.can_notify() {
+            return Err(BackendError(
+                "org.bluez.Error.NotSupported: Operation is not supported".to_string(),
+            ));
+        }
+        attribute.notifying = enabled;
+
+        // BlueZ writes the Client Characteristic Configuration descriptor for us.
+        let configuration = sig_uuid(CLIENT_CONFIGURATION_UUID);
+        let prefix = format!("{}/", path);
+        for descriptor in state
+            .gatt
+            .iter_mut()
+            .filter(|a| a.path.starts_with(&prefix) && a.uuid == configuration)
+        {
+            descriptor.value = Some(vec![u8::from(enabled), 0]);
+        }
+        Ok(())
+    }
+
+    async fn send_file(
+        &self,
+        adapter: &str,
+        address: &str,
+        file: &Path,
+        events: UnboundedSender<BluezEvent>,
+    ) -> BackendResult<FileTransfer> {
+        let metadata = fs::metadata(file).map_err(|err| {
+            BackendError(format!("org.bluez.obex.Error.InvalidArguments: {}", err))
+        })?;
+        if !metadata.is_file() {
+            return Err(BackendError(
+                "org.bluez.obex.Error.InvalidArguments: Invalid arguments in method call"
+                    .to_string(),
+            ));
+        }
+
+        let mut state = self.lock();
+        if !state.adapter_mut(adapter)?.powered {
+            return Err(not_ready());
+        }
+        state.device_mut(adapter, address)?;
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let transfer = state.new_transfer(
+            adapter,
+            address,
+            TransferDirection::Outgoing,
+            name,
+            metadata.len(),
+        );
+        tokio::spawn(play_transfer(events, transfer.clone()));
+        Ok(transfer)
+    }
+
+    async fn register_obex_agent(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()> {
+        self.lock().obex_agent = Some(events);
+        Ok(())
+    }
+
+    async fn watch(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()> {
+        self.lock().events = Some(events);
+        Ok(())
+    }
+
+    async fn register_agent(&self, events: UnboundedSender<BluezEvent>) -> BackendResult<()> {
+        self.lock().agent = Some(events);
+        Ok(())
+    }
+}
+
+fn simulated_adapter(name: &str, address: &str, powered: bool) -> BluetoothAdapter {
+    BluetoothAdapter {
+        name: name.to_string(),
+        address: address.to_string(),
+        alias: format!("Simulated {}", name),
+        powered,
+        pairable: true,
+        discoverable_timeout: 180,
+        ..BluetoothAdapter::default()
+    }
+}
+
+fn simulated_device(
+    adapter: &str,
+    address: &str,
+    name: &str,
+    device_type: DeviceType,
+    rssi: Option<i16>,
+) -> BluetoothDevice {
+    let mut device = BluetoothDevice::new(address.to_string(), name.to_string());
+    device.adapter = adapter.to_string();
+    device.device_type = device_type;
+    device.rssi = rssi;
+    device
+}
+
+/// Reports a transfer going from queued to complete in a few even steps.
+async fn play_transfer(events: UnboundedSender<BluezEvent>, mut transfer: FileTransfer) {
+    let size = transfer.size.unwrap_or(0);
+    for step in 1..=TRANSFER_STEPS {
+        tokio::time::sleep(OPERATION_DELAY).await;
+        transfer.status = TransferStatus::Active;
+        transfer.transferred = size * step / TRANSFER_STEPS;
+        if events.send(BluezEvent::TransferChanged(transfer.clone())).is_err() {
+            return;
+        }
+    }
+    transfer.status = TransferStatus::Complete;
+    let _ = events.send(BluezEvent::TransferChanged(transfer));
+}
+
+/// Whether a discovery under `filter` reports `device`. The demo devices
+/// carry no transport or TX power, so only the RSSI and UUIDs are checked.
+fn reported(filter: &DiscoveryFilter, device: &BluetoothDevice) -> bool {
+    let strong_enough = match (filter.rssi, device.rssi) {
+        (Some(limit), Some(rssi)) => rssi >= limit,
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+    let offers_service = filter.uuids.is_empty()
+        || filter.uuids.iter().any(|uuid| device.uuids.contains(uuid));
+    strong_enough && offers_service
+}
+
+/// Object path BlueZ would give the device.
+fn simulated_path(adapter: &str, address: &str) -> String {
+    format!("/org/bluez/{}/dev_{}", adapter, address.replace(':', "_"))
+}
+
+/// Generic Access, Battery and Device Information services plus a vendor
+/// service, laid out the way BlueZ exports them below the device.
+fn demo_gatt(adapter: &str, address: &str, name: &str, battery: u8) -> Vec<GattAttribute> {
+    let device = simulated_path(adapter, address);
+    let attribute = |path: &str, kind: GattKind, uuid: String, flags: &[&str], value: &[u8]| {
+        GattAttribute {
+            path: format!("{}/{}", device, path),
+            kind,
+            uuid,
+            primary: kind == GattKind::Service,
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            value: (kind != GattKind::Service).then(|| value.to_vec()),
+            notifying: false,
+        }
+    };
+    let configuration = sig_uuid(CLIENT_CONFIGURATION_UUID);
+
+    vec![
+        attribute("service0001", GattKind::Service, sig_uuid(0x1800), &[], &[]),
+        attribute(
+            "service0001/char0002",
+            GattKind::Characteristic,
+            sig_uuid(0x2a00),
+            &["read"],
+            name.as_bytes(),
+        ),
+        attribute("service0010", GattKind::Service, BATTERY_SERVICE_UUID.to_string(), &[], &[]),
+        attribute(
+            "service0010/char0011",
+            GattKind::Characteristic,
+            sig_uuid(BATTERY_LEVEL_UUID),
+            &["read", "notify"],
+            &[battery],
+        ),
+        attribute(
+            "service0010/char0011/desc0013",
+            GattKind::Descriptor,
+            configuration.clone(),
+            &["read", "write"],
+            &[0, 0],
+        ),
+        attribute("service0020", GattKind::Service, sig_uuid(0x180a), &[], &[]),
+        attribute(
+            "service0020/char0021",
+            GattKind::Characteristic,
+            sig_uuid(0x2a29),
+            &["read"],
+            b"Sony",
+        ),
+        attribute(
+            "service0020/char0023",
+            GattKind::Characteristic,
+            sig_uuid(0x2a26),
+            &["read"],
+            b"2.1.0",
+        ),
+        attribute("service0030", GattKind::Service, VENDOR_SERVICE_UUID.to_string(), &[], &[]),
+        attribute(
+            "service0030/char0031",
+            GattKind::Characteristic,
+            VENDOR_CONTROL_POINT_UUID.to_string(),
+            &["read", "write", "notify"],
+            &[0],
+        ),
+        attribute(
+            "service0030/char0031/desc0033",
+            GattKind::Descriptor,
+            sig_uuid(0x2901),
+            &["read"],
+            b"Control Point",
+        ),
+        attribute(
+            "service0030/char0031/desc0034",
+            GattKind::Descriptor,
+            configuration,
+            &["read", "write"],
+            &[0, 0],
+        ),
+    ]
+}
+
+fn does_not_exist(what: &str) -> BackendError {
+    BackendError(format!(
+        "org.bluez.Error.DoesNotExist: {} does not exist",
+        what
+    ))
+}
+
+fn authentication_canceled() -> BackendError {
+    BackendError("org.bluez.Error.AuthenticationCanceled: Authentication Canceled".to_string())
+}
+
+fn not_permitted(operation: &str) -> BackendError {
+    BackendError(format!(
+        "org.bluez.Error.NotPermitted: {} not permitted",
+        operation
+    ))
+}
+
+fn not_ready() -> BackendError {
+    BackendError("org.bluez.Error.NotReady: Resource Not Ready".to_string())
+}
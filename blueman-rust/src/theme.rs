@@ -0,0 +1,102 @@
+use ratatui::style::Color;
+use std::{collections::HashMap, str::FromStr};
+
+/// Colours of the interface, by role. Set from the `[theme]` table of the
+/// config file; each entry is a colour name (`"cyan"`, `"light-blue"`), an
+/// ANSI index (`"208"`) or `"#rrggbb"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub accent: Color,       // titles, headings and the selection marker
+    pub label: Color,        // field names such as "Address:"
+    pub text: Color,         // key help and typed text
+    pub secondary: Color,    // counts in the header, cursors
+    pub muted: Color,        // hints, empty lists, inactive markers
+    pub success: Color,      // connected, enabled, complete
+    pub warning: Color,      // scanning, paired, low battery
+    pub error: Color,        // errors, disconnected, destructive actions
+    pub known: Color,        // previously known devices
+    pub selection_fg: Color, // selected table row
+    pub selection_bg: Color,
+    pub gauge_bg: Color, // unfilled part of the gauges
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            accent: Color::Cyan,
+            label: Color::Yellow,
+            text: Color::White,
+            secondary: Color::Gray,
+            muted: Color::DarkGray,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+            known: Color::Blue,
+            selection_fg: Color::Black,
+            selection_bg: Color::White,
+            gauge_bg: Color::Black,
+        }
+    }
+}
+
+impl Theme {
+    /// The default theme with the colours named in `overrides` replaced.
+    pub fn with_overrides(overrides: &HashMap<String, String>) -> Result<Theme, String> {
+        let mut theme = Theme::default();
+        for (role, value) in overrides {
+            let color = Color::from_str(value)
+                .map_err(|_| format!("[theme] {}: unknown colour \"{}\"", role, value))?;
+            let slot = match role.as_str() {
+                "accent" => &mut theme.accent,
+                "label" => &mut theme.label,
+                "text" => &mut theme.text,
+                "secondary" => &mut theme.secondary,
+                "muted" => &mut theme.muted,
+                "success" => &mut theme.success,
+                "warning" => &mut theme.warning,
+                "error" => &mut theme.error,
+                "known" => &mut theme.known,
+                "selection-fg" => &mut theme.selection_fg,
+                "selection-bg" => &mut theme.selection_bg,
+                "gauge-bg" => &mut theme.gauge_bg,
+                _ => return Err(format!("[theme]: unknown colour role \"{}\"", role)),
+            };
+            *slot = color;
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(role, value)| (role.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn colours_accept_names_indexes_and_hex() {
+        let theme = Theme::with_overrides(&overrides(&[
+            ("accent", "light-blue"),
+            ("muted", "208"),
+            ("selection-bg", "#44475a"),
+        ]))
+        .unwrap();
+        assert_eq!(theme.accent, Color::LightBlue);
+        assert_eq!(theme.muted, Color::Indexed(208));
+        assert_eq!(theme.selection_bg, Color::Rgb(0x44, 0x47, 0x5a));
+        assert_eq!(theme.error, Theme::default().error);
+    }
+
+    #[test]
+    fn bad_entries_are_rejected() {
+        let err = Theme::with_overrides(&overrides(&[("accent", "chartreuse")])).unwrap_err();
+        assert_eq!(err, "[theme] accent: unknown colour \"chartreuse\"");
+        let err = Theme::with_overrides(&overrides(&[("border", "red")])).unwrap_err();
+        assert_eq!(err, "[theme]: unknown colour role \"border\"");
+    }
+}
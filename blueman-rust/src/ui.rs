@@ -1,21 +1,48 @@
-use crate::app::{App, View};
+use crate::agent::AgentPrompt;
+use crate::app::{format_timeout, AliasTarget, App, BluetoothDevice, SortMode, View, LOW_BATTERY_THRESHOLD};
+use crate::config::Config;
+use crate::discovery_filter::FilterField;
+use crate::event_log::{UtcTime, LogLevel};
+use crate::file_picker::FilePicker;
+use crate::gatt::{format_hex, format_text, gatt_label, GattBrowser, GattKind};
+use crate::keymap::{Action, Bindings};
+use crate::known_devices::{format_last_seen, unix_now};
+use crate::obex::{format_size, FileTransfer, TransferDirection, TransferStatus};
+use crate::profiles::profile_label;
+use crate::rfkill::BlockState;
+use crate::theme::Theme;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table, TableState},
+    widgets::{
+        Block, Borders, Clear, Gauge, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline,
+        Table, TableState, Wrap,
+    },
     Frame,
 };
 
-pub fn draw(f: &mut Frame, app: &App) {
+pub fn draw(f: &mut Frame, app: &mut App) {
     match app.current_view {
         View::DeviceList => draw_device_list(f, app),
         View::DeviceDetails => draw_device_details(f, app),
         View::Settings => draw_settings(f, app),
+        View::Gatt => draw_gatt(f, app),
+        View::Log => draw_log(f, app),
+    }
+
+    if let Some(picker) = &app.file_picker {
+        draw_file_picker(f, app, picker);
+    }
+    if app.agent_request.is_some() {
+        draw_agent_dialog(f, app);
+    }
+    if app.unblock_prompt {
+        draw_unblock_dialog(f, app);
     }
 }
 
-fn draw_device_list(f: &mut Frame, app: &App) {
+fn draw_device_list(f: &mut Frame, app: &mut App) {
     let chunks = create_main_layout(f.size());
     
     draw_header(f, app, chunks[0]);
@@ -24,11 +51,12 @@ fn draw_device_list(f: &mut Frame, app: &App) {
 }
 
 fn draw_device_details(f: &mut Frame, app: &App) {
-    let chunks = create_main_layout(f.size());
+    let chunks = create_details_layout(f.size());
     
     draw_header(f, app, chunks[0]);
     draw_device_info(f, app, chunks[1]);
     draw_device_actions(f, app, chunks[2]);
+    draw_status_bar(f, app, chunks[3]);
 }
 
 fn draw_settings(f: &mut Frame, app: &App) {
@@ -39,6 +67,26 @@ fn draw_settings(f: &mut Frame, app: &App) {
     draw_status_bar(f, app, chunks[2]);
 }
 
+fn draw_gatt(f: &mut Frame, app: &App) {
+    let chunks = create_details_layout(f.size());
+
+    draw_header(f, app, chunks[0]);
+    if let Some(browser) = &app.gatt {
+        draw_gatt_browser(f, app, browser, chunks[1]);
+    }
+    f.render_widget(create_gatt_actions_widget(app, chunks[2].width), chunks[2]);
+    draw_status_bar(f, app, chunks[3]);
+}
+
+fn draw_log(f: &mut Frame, app: &mut App) {
+    let chunks = create_details_layout(f.size());
+
+    draw_header(f, app, chunks[0]);
+    draw_event_table(f, app, chunks[1]);
+    f.render_widget(create_log_actions_widget(app, chunks[2].width), chunks[2]);
+    draw_status_bar(f, app, chunks[3]);
+}
+
 fn create_main_layout(area: Rect) -> Vec<Rect> {
     Layout::default()
         .direction(Direction::Vertical)
@@ -55,29 +103,223 @@ fn create_main_layout(area: Rect) -> Vec<Rect> {
         .to_vec()
 }
 
+fn create_details_layout(area: Rect) -> Vec<Rect> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(10),
+                Constraint::Length(6),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
+        .split(area)
+        .to_vec()
+}
+
+fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
 fn draw_header(f: &mut Frame, app: &App, area: Rect) {
     let title = create_header_widget(app);
     f.render_widget(title, area);
 }
 
-fn draw_device_table(f: &mut Frame, app: &App, area: Rect) {
+/// The table scrolls to keep the selected device in view; the offset it
+/// ends up at is kept in `App` for the next frame and for mouse clicks.
+fn draw_device_table(f: &mut Frame, app: &mut App, area: Rect) {
+    // Borders and the header row
+    let rows = area.height.saturating_sub(3) as usize;
+    let count = app.visible_devices().len();
+    let mut state = TableState::default()
+        .with_offset(app.table_offset)
+        .with_selected((count > 0).then_some(app.selected_index));
+
     let table = create_device_table_widget(app);
-    f.render_widget(table, area);
+    f.render_stateful_widget(table, area, &mut state);
+    app.table_offset = state.offset();
+    app.table_rows = rows;
+
+    if count > rows {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .style(Style::default().fg(app.theme.muted));
+        let mut scrollbar_state = ScrollbarState::new(count.saturating_sub(rows))
+            .viewport_content_length(rows)
+            .position(app.table_offset);
+        let track = Rect::new(area.x, area.y + 2, area.width, area.height.saturating_sub(3));
+        f.render_stateful_widget(scrollbar, track, &mut scrollbar_state);
+    }
+}
+
+/// While following, the newest events stay in view with no cursor; once
+/// scrolled, the table keeps the cursor in view like the device table.
+fn draw_event_table(f: &mut Frame, app: &mut App, area: Rect) {
+    // Borders and the header row
+    let rows = area.height.saturating_sub(3) as usize;
+    let count = app.event_log.len();
+    let mut state = match app.log_index {
+        Some(index) => TableState::default()
+            .with_offset(app.log_offset)
+            .with_selected(Some(index)),
+        None => TableState::default().with_offset(count.saturating_sub(rows)),
+    };
+
+    let table = create_event_table_widget(app);
+    f.render_stateful_widget(table, area, &mut state);
+    app.log_offset = state.offset();
+    app.log_rows = rows;
+
+    if count > rows {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .style(Style::default().fg(app.theme.muted));
+        let mut scrollbar_state = ScrollbarState::new(count.saturating_sub(rows))
+            .viewport_content_length(rows)
+            .position(app.log_offset);
+        let track = Rect::new(area.x, area.y + 2, area.width, area.height.saturating_sub(3));
+        f.render_stateful_widget(scrollbar, track, &mut scrollbar_state);
+    }
 }
 
 fn draw_device_info(f: &mut Frame, app: &App, area: Rect) {
-    let info = create_device_info_widget(app);
-    f.render_widget(info, area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
+    f.render_widget(create_device_info_widget(app), columns[0]);
+
+    let battery = app.get_selected_device().filter(|d| d.battery.is_some());
+    let transfer = app.get_selected_device().and_then(|d| app.transfer_for(d));
+    let mut constraints = vec![Constraint::Min(0), Constraint::Length(5)];
+    if battery.is_some() {
+        constraints.push(Constraint::Length(3));
+    }
+    if transfer.is_some() {
+        constraints.push(Constraint::Length(3));
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(columns[1]);
+    f.render_widget(create_device_profiles_widget(app), chunks[0]);
+    draw_signal_history(f, app, chunks[1]);
+    if let Some(device) = battery {
+        f.render_widget(create_battery_gauge_widget(device, &app.theme), chunks[2]);
+    }
+    if let Some(transfer) = transfer {
+        f.render_widget(create_transfer_gauge_widget(transfer, &app.theme), chunks[chunks.len() - 1]);
+    }
+}
+
+/// RSSI of the selected device over time, newest on the right. Bars grow
+/// from -100 dBm (empty) to -30 dBm (full).
+fn draw_signal_history(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let Some(device) = app.get_selected_device() else { return };
+
+    let samples: Vec<u64> = app
+        .rssi_history(device)
+        .map(|history| {
+            history
+                .iter()
+                .map(|rssi| ((*rssi).clamp(-100, -30) + 100) as u64)
+                .collect()
+        })
+        .unwrap_or_default();
+    let width = area.width.saturating_sub(2) as usize;
+    let recent = &samples[samples.len().saturating_sub(width)..];
+
+    let title = match (device.rssi, app.rssi_history(device)) {
+        (Some(rssi), Some(history)) => format!(
+            "Signal - {} dBm (min {}, max {})",
+            rssi,
+            history.iter().min().copied().unwrap_or(rssi),
+            history.iter().max().copied().unwrap_or(rssi)
+        ),
+        (Some(rssi), None) => format!("Signal - {} dBm", rssi),
+        (None, _) => "Signal - out of range".to_string(),
+    };
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(Style::default().fg(theme.accent))
+        .max(70)
+        .data(recent);
+    f.render_widget(sparkline, area);
+}
+
+/// Attribute tree on the left; the selected attribute and the notification
+/// log on the right.
+fn draw_gatt_browser(f: &mut Frame, app: &App, browser: &GattBrowser, area: Rect) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    f.render_widget(create_gatt_tree_widget(app, browser, columns[0].height), columns[0]);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(9), Constraint::Min(0)])
+        .split(columns[1]);
+    f.render_widget(create_gatt_attribute_widget(browser, &app.theme), chunks[0]);
+    f.render_widget(create_gatt_log_widget(browser, &app.theme, chunks[1].height), chunks[1]);
 }
 
 fn draw_device_actions(f: &mut Frame, app: &App, area: Rect) {
-    let actions = create_device_actions_widget(app);
+    let actions = create_device_actions_widget(app, area.width);
     f.render_widget(actions, area);
 }
 
+/// Adapter settings on the left, the keys of the device list on the right.
 fn draw_settings_panel(f: &mut Frame, app: &App, area: Rect) {
-    let settings = create_settings_widget(app);
-    f.render_widget(settings, area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(area);
+    let help = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(FilterField::ALL.len() as u16 + 2),
+            Constraint::Min(0),
+        ])
+        .split(columns[1]);
+    f.render_widget(create_settings_widget(app, columns[0].width), columns[0]);
+    f.render_widget(create_discovery_filter_widget(app), help[0]);
+    f.render_widget(create_key_help_widget(app, help[1].width), help[1]);
+}
+
+fn draw_agent_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 7, f.size());
+    let dialog = create_agent_dialog_widget(app);
+    f.render_widget(Clear, area);
+    f.render_widget(dialog, area);
+}
+
+fn draw_unblock_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 7, f.size());
+    f.render_widget(Clear, area);
+    f.render_widget(create_unblock_dialog_widget(app), area);
+}
+
+fn draw_file_picker(f: &mut Frame, app: &App, picker: &FilePicker) {
+    let area = centered_rect(70, 20, f.size());
+    let picker = create_file_picker_widget(app, picker, area.height);
+    f.render_widget(Clear, area);
+    f.render_widget(picker, area);
 }
 
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
@@ -85,9 +327,10 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(status, area);
 }
 
-fn create_header_widget(app: &App) -> Paragraph {
+fn create_header_widget(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
     let title_style = Style::default()
-        .fg(Color::Cyan)
+        .fg(theme.accent)
         .add_modifier(Modifier::BOLD);
     
     let bluetooth_status = if app.bluetooth_enabled {
@@ -96,16 +339,40 @@ fn create_header_widget(app: &App) -> Paragraph {
         "○".to_string()
     };
     
+    let adapter_name = app
+        .current_adapter()
+        .map(|a| format!(" {}", a.name))
+        .unwrap_or_default();
+    
     let scanning_status = if app.scanning {
-        " [SCANNING]".to_string()
+        format!(" [SCANNING {}]", app.spinner())
     } else {
         "".to_string()
     };
+
+    let rfkill_status = match app.adapter_block_state() {
+        BlockState::Unblocked => "".to_string(),
+        state => format!(" [{}]", state.label().to_uppercase()),
+    };
+    
+    // Counts follow the search and filters, with the total when they hide something
+    let mut counts = format!(
+        " | {} devices ({} known, {} available)",
+        app.get_device_count(),
+        app.get_known_devices_count(),
+        app.get_available_devices_count()
+    );
+    if app.is_filtered() {
+        counts.push_str(&format!(" of {}", app.devices.len()));
+    }
     
     let title = Line::from(vec![
         Span::styled("Bluetooth Manager ", title_style),
-        Span::styled(bluetooth_status, Style::default().fg(if app.bluetooth_enabled { Color::Green } else { Color::Red })),
-        Span::styled(scanning_status, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::styled(bluetooth_status, Style::default().fg(if app.bluetooth_enabled { theme.success } else { theme.error })),
+        Span::styled(adapter_name, Style::default().fg(theme.text)),
+        Span::styled(counts, Style::default().fg(theme.secondary)),
+        Span::styled(scanning_status, Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)),
+        Span::styled(rfkill_status, Style::default().fg(theme.error).add_modifier(Modifier::BOLD)),
     ]);
     
     Paragraph::new(title)
@@ -113,17 +380,20 @@ fn create_header_widget(app: &App) -> Paragraph {
         .alignment(ratatui::layout::Alignment::Center)
 }
 
-fn create_device_table_widget(app: &App) -> Table {
+fn create_device_table_widget(app: &App) -> Table<'_> {
+    let theme = &app.theme;
     let header_style = Style::default()
-        .fg(Color::Cyan)
+        .fg(theme.accent)
         .add_modifier(Modifier::BOLD);
     
-    let headers = vec![
+    let headers = [
         "Name",
         "Address", 
+        "Adapter",
         "Type",
         "Status",
         "RSSI",
+        "Batt",
         "Known",
     ];
     
@@ -131,19 +401,33 @@ fn create_device_table_widget(app: &App) -> Table {
         headers.iter().map(|h| Span::styled(*h, header_style))
     );
     
-    let rows: Vec<Row> = if app.devices.is_empty() {
+    let devices = app.visible_devices();
+    let rows: Vec<Row> = if devices.is_empty() {
         // Show a helpful message when no devices are found
+        let message = if app.devices.is_empty() {
+            "No devices found"
+        } else if !app.search_query.is_empty() {
+            "No devices match the search"
+        } else if app.type_filter.is_some() {
+            "No devices of this type"
+        } else {
+            "No devices match the filters"
+        };
         vec![Row::new(vec![
-            "No devices found".to_string(),
+            message.to_string(),
+            "".to_string(),
             "".to_string(),
             "".to_string(),
             "".to_string(),
             "".to_string(),
             "".to_string(),
-        ]).style(Style::default().fg(Color::Yellow))]
+            "".to_string(),
+        ]).style(Style::default().fg(theme.warning))]
     } else {
-        app.devices.iter().enumerate().map(|(i, device)| {
-            let status = if device.connected {
+        devices.iter().map(|device| {
+            let status = if device.blocked {
+                "Blocked"
+            } else if device.connected {
                 "Connected"
             } else if device.paired {
                 "Paired"
@@ -152,25 +436,20 @@ fn create_device_table_widget(app: &App) -> Table {
             };
             
             let _status_color = if device.connected {
-                Color::Green
+                theme.success
             } else if device.paired {
-                Color::Yellow
+                theme.warning
             } else {
-                Color::White
+                theme.text
             };
             
             let rssi_text = device.rssi.map(|r| format!("{} dBm", r)).unwrap_or_else(|| "N/A".to_string());
+            let battery_text = device.battery.map(|b| format!("{}%", b)).unwrap_or_else(|| "-".to_string());
             let known_text = if device.known { "Yes" } else { "No" };
             
-            let row_style = if i == app.selected_index {
-                Style::default().fg(Color::Black).bg(Color::White)
-            } else {
-                Style::default()
-            };
-            
             // Color code known devices differently
             let _name_style = if device.known {
-                Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)
+                Style::default().fg(theme.known).add_modifier(Modifier::BOLD)
             } else {
                 Style::default()
             };
@@ -178,88 +457,163 @@ fn create_device_table_widget(app: &App) -> Table {
             Row::new(vec![
                 device.display_name(),
                 device.address.clone(),
-                device.device_type.clone(),
+                device.adapter.clone(),
+                format!("{} {}", device.device_type.glyph(), device.device_type.label()),
                 status.to_string(),
                 rssi_text,
+                battery_text,
                 known_text.to_string(),
-            ]).style(row_style)
+            ])
         }).collect()
     };
     
     let widths = [
         Constraint::Percentage(20),
-        Constraint::Percentage(20),
-        Constraint::Percentage(15),
-        Constraint::Percentage(15),
-        Constraint::Percentage(15),
+        Constraint::Percentage(17),
+        Constraint::Percentage(7),
         Constraint::Percentage(15),
+        Constraint::Percentage(12),
+        Constraint::Percentage(11),
+        Constraint::Percentage(8),
+        Constraint::Percentage(10),
     ];
     
-    let title = if app.devices.is_empty() {
-        "Devices (Press 's' to scan)"
-    } else {
-        "Devices"
+    let scan_key = app.keymap.list.key(Action::ToggleScan);
+    let filter_key = app.keymap.list.key(Action::CycleTypeFilter);
+    let title = match (app.devices.is_empty(), app.type_filter) {
+        (true, _) => match scan_key {
+            Some(key) => format!("Devices (Press '{}' to scan)", key),
+            None => "Devices".to_string(),
+        },
+        (false, Some(kind)) => match filter_key {
+            Some(key) => format!("Devices - {} {} (Press '{}' to change filter)", kind.glyph(), kind.label(), key),
+            None => format!("Devices - {} {}", kind.glyph(), kind.label()),
+        },
+        (false, None) => "Devices".to_string(),
     };
+    let mut title = title;
+    if !app.search_query.is_empty() {
+        title.push_str(&format!(" - Search \"{}\"", app.search_query));
+    }
+    let toggles: Vec<&str> = [
+        (app.hide_unnamed, "named"),
+        (app.only_known, "known"),
+        (app.only_connected, "connected"),
+        (app.only_blocked, "blocked"),
+    ]
+    .into_iter()
+    .filter_map(|(active, label)| active.then_some(label))
+    .collect();
+    if !toggles.is_empty() {
+        title.push_str(&format!(" - Only {}", toggles.join(", ")));
+    }
+    if app.sort_mode != SortMode::Discovery {
+        title.push_str(&format!(" - Sorted by {}", app.sort_mode.label()));
+    }
     
     Table::new(rows, widths)
         .header(header_row)
         .block(Block::default().borders(Borders::ALL).title(title))
-        .highlight_style(Style::default().fg(Color::Black).bg(Color::White))
+        .highlight_style(Style::default().fg(theme.selection_fg).bg(theme.selection_bg))
 }
 
-fn create_device_info_widget(app: &App) -> Paragraph {
+fn create_device_info_widget(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
     if let Some(device) = app.get_selected_device() {
         let device_name = device.display_name();
         let device_address = device.address.clone();
-        let device_type = device.device_type.clone();
+        let editing = app.alias_edit
+            == Some(AliasTarget::Device {
+                adapter: device.adapter.clone(),
+                address: device.address.clone(),
+            });
+        let name_line = if editing {
+            let mut spans = vec![Span::styled("Alias: ", Style::default().fg(theme.label))];
+            spans.extend(alias_input_spans(app));
+            Line::from(spans)
+        } else {
+            Line::from(vec![
+                Span::styled("Name: ", Style::default().fg(theme.label)),
+                Span::styled(device_name, Style::default()),
+            ])
+        };
+        let device_type = format!("{} {}", device.device_type.glyph(), device.device_type.label());
         let rssi_text = device.rssi.map(|r| format!("{} dBm", r)).unwrap_or_else(|| "N/A".to_string());
+        let auto_connect = app.auto_connect_enabled(device);
+        let last_seen = app
+            .last_seen(device)
+            .map(|seen| format_last_seen(seen, unix_now()))
+            .unwrap_or_else(|| "N/A".to_string());
         
         let info_text = vec![
             Line::from(vec![
-                Span::styled("Device Information:", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                Span::styled("Device Information:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
             ]),
+            name_line,
             Line::from(vec![
-                Span::styled("Name: ", Style::default().fg(Color::Yellow)),
-                Span::styled(device_name, Style::default()),
+                Span::styled("Address: ", Style::default().fg(theme.label)),
+                Span::styled(device_address, Style::default()),
             ]),
             Line::from(vec![
-                Span::styled("Address: ", Style::default().fg(Color::Yellow)),
-                Span::styled(device_address, Style::default()),
+                Span::styled("Adapter: ", Style::default().fg(theme.label)),
+                Span::styled(device.adapter.clone(), Style::default()),
             ]),
             Line::from(vec![
-                Span::styled("Type: ", Style::default().fg(Color::Yellow)),
+                Span::styled("Type: ", Style::default().fg(theme.label)),
                 Span::styled(device_type, Style::default()),
             ]),
             Line::from(vec![
-                Span::styled("Connected: ", Style::default().fg(Color::Yellow)),
+                Span::styled("Connected: ", Style::default().fg(theme.label)),
                 Span::styled(
                     if device.connected { "Yes" } else { "No" },
-                    Style::default().fg(if device.connected { Color::Green } else { Color::Red })
+                    Style::default().fg(if device.connected { theme.success } else { theme.error })
                 ),
             ]),
             Line::from(vec![
-                Span::styled("Paired: ", Style::default().fg(Color::Yellow)),
+                Span::styled("Paired: ", Style::default().fg(theme.label)),
                 Span::styled(
                     if device.paired { "Yes" } else { "No" },
-                    Style::default().fg(if device.paired { Color::Green } else { Color::Red })
+                    Style::default().fg(if device.paired { theme.success } else { theme.error })
                 ),
             ]),
             Line::from(vec![
-                Span::styled("Trusted: ", Style::default().fg(Color::Yellow)),
+                Span::styled("Trusted: ", Style::default().fg(theme.label)),
                 Span::styled(
                     if device.trusted { "Yes" } else { "No" },
-                    Style::default().fg(if device.trusted { Color::Green } else { Color::Red })
+                    Style::default().fg(if device.trusted { theme.success } else { theme.error })
                 ),
             ]),
             Line::from(vec![
-                Span::styled("Previously Known: ", Style::default().fg(Color::Yellow)),
+                Span::styled("Blocked: ", Style::default().fg(theme.label)),
+                Span::styled(
+                    if device.blocked { "Yes" } else { "No" },
+                    Style::default().fg(if device.blocked { theme.error } else { theme.text })
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Previously Known: ", Style::default().fg(theme.label)),
                 Span::styled(
                     if device.known { "Yes" } else { "No" },
-                    Style::default().fg(if device.known { Color::Blue } else { Color::White })
+                    Style::default().fg(if device.known { theme.known } else { theme.text })
                 ),
             ]),
             Line::from(vec![
-                Span::styled("Signal Strength: ", Style::default().fg(Color::Yellow)),
+                Span::styled("Auto-connect: ", Style::default().fg(theme.label)),
+                Span::styled(
+                    match (auto_connect, device.blocked) {
+                        (true, true) => "Yes (paused while blocked)",
+                        (true, false) => "Yes",
+                        (false, _) => "No",
+                    },
+                    Style::default().fg(if auto_connect && !device.blocked { theme.success } else { theme.text })
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Last Seen: ", Style::default().fg(theme.label)),
+                Span::styled(last_seen, Style::default()),
+            ]),
+            Line::from(vec![
+                Span::styled("Signal Strength: ", Style::default().fg(theme.label)),
                 Span::styled(rssi_text, Style::default()),
             ]),
         ];
@@ -273,82 +627,736 @@ fn create_device_info_widget(app: &App) -> Paragraph {
     }
 }
 
-fn create_device_actions_widget(_app: &App) -> Paragraph {
-    let actions_text = vec![
-        Line::from(vec![
-            Span::styled("Device Actions:", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-        ]),
+fn create_device_profiles_widget(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let Some(device) = app.get_selected_device() else {
+        return Paragraph::new("").block(Block::default().borders(Borders::ALL).title("Profiles"));
+    };
+
+    let preferred_profile = app
+        .known_devices
+        .get(&device.adapter, &device.address)
+        .and_then(|known| known.preferred_profile.as_ref());
+    let mut lines: Vec<Line> = device
+        .uuids
+        .iter()
+        .enumerate()
+        .map(|(i, uuid)| {
+            let selected = i == app.profile_index;
+            let active = device.active_profiles.contains(uuid);
+            let preferred = preferred_profile == Some(uuid);
+            Line::from(vec![
+                Span::styled(if selected { "▶ " } else { "  " }, Style::default().fg(theme.accent)),
+                Span::styled(
+                    if active { "● " } else { "○ " },
+                    Style::default().fg(if active { theme.success } else { theme.muted }),
+                ),
+                Span::styled(
+                    profile_label(uuid),
+                    if selected { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() },
+                ),
+                Span::styled(if preferred { " ★" } else { "" }, Style::default().fg(theme.warning)),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled("No profiles reported", Style::default().fg(theme.muted))));
+    }
+
+    let active = device
+        .active_profiles
+        .iter()
+        .map(|uuid| profile_label(uuid))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let title = if active.is_empty() {
+        "Profiles".to_string()
+    } else {
+        format!("Profiles - Active: {}", active)
+    };
+
+    Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .alignment(ratatui::layout::Alignment::Left)
+}
+
+/// Directories first, ending in `/`; files with their size.
+fn create_file_picker_widget<'a>(app: &App, picker: &'a FilePicker, height: u16) -> Paragraph<'a> {
+    let theme = &app.theme;
+    let mut lines: Vec<Line> = picker
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let selected = i == picker.selected;
+            let (name, style, size) = if entry.is_dir {
+                (format!("{}/", entry.name), Style::default().fg(theme.accent), String::new())
+            } else {
+                (entry.name.clone(), Style::default(), format!("  {}", format_size(entry.size)))
+            };
+            Line::from(vec![
+                Span::styled(if selected { "▶ " } else { "  " }, Style::default().fg(theme.accent)),
+                Span::styled(name, if selected { style.add_modifier(Modifier::BOLD) } else { style }),
+                Span::styled(size, Style::default().fg(theme.muted)),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "Empty directory",
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    let rows = height.saturating_sub(2) as usize;
+    let scroll = picker.selected.saturating_sub(rows.saturating_sub(1));
+    let device = app
+        .get_selected_device()
+        .map(|d| d.display_name())
+        .unwrap_or_default();
+    let title = format!("Send File to {} - {}", device, picker.dir.display());
+
+    Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(theme.accent)),
+        )
+        .scroll((scroll as u16, 0))
+}
+
+fn create_gatt_tree_widget<'a>(app: &App, browser: &'a GattBrowser, height: u16) -> Paragraph<'a> {
+    let theme = &app.theme;
+    let mut lines: Vec<Line> = browser
+        .attributes
+        .iter()
+        .enumerate()
+        .map(|(i, attribute)| {
+            let selected = i == browser.selected;
+            let indent = match attribute.kind {
+                GattKind::Service => "",
+                GattKind::Characteristic => "  ",
+                GattKind::Descriptor => "    ",
+            };
+            let label_style = match attribute.kind {
+                GattKind::Service => Style::default().fg(theme.accent),
+                GattKind::Characteristic => Style::default(),
+                GattKind::Descriptor => Style::default().fg(theme.muted),
+            };
+            let access: String = [
+                (attribute.can_read(), 'R'),
+                (attribute.can_write(), 'W'),
+                (attribute.can_notify(), 'N'),
+            ]
+            .into_iter()
+            .filter_map(|(allowed, letter)| allowed.then_some(letter))
+            .collect();
+
+            Line::from(vec![
+                Span::styled(if selected { "▶ " } else { "  " }, Style::default().fg(theme.accent)),
+                Span::raw(indent),
+                Span::styled(
+                    gatt_label(&attribute.uuid),
+                    if selected { label_style.add_modifier(Modifier::BOLD) } else { label_style },
+                ),
+                Span::styled(
+                    if access.is_empty() { String::new() } else { format!(" [{}]", access) },
+                    Style::default().fg(theme.muted),
+                ),
+                Span::styled(if attribute.notifying { " ●" } else { "" }, Style::default().fg(theme.success)),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No GATT services resolved yet",
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    // Keep the cursor in view on devices with many attributes
+    let rows = height.saturating_sub(2) as usize;
+    let scroll = browser.selected.saturating_sub(rows.saturating_sub(1));
+    let device = app
+        .devices
+        .iter()
+        .find(|d| d.is(&browser.adapter, &browser.address));
+    let title = match device {
+        Some(device) if device.connected => format!("GATT - {}", device.display_name()),
+        Some(device) => format!("GATT - {} (disconnected)", device.display_name()),
+        None => "GATT".to_string(),
+    };
+
+    Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .scroll((scroll as u16, 0))
+}
+
+fn create_gatt_attribute_widget<'a>(browser: &'a GattBrowser, theme: &Theme) -> Paragraph<'a> {
+    let block = Block::default().borders(Borders::ALL).title("Attribute");
+    let Some(attribute) = browser.selected_attribute() else {
+        return Paragraph::new("").block(block);
+    };
+
+    let kind = match attribute.kind {
+        GattKind::Service if attribute.primary => "Primary Service",
+        GattKind::Service => "Secondary Service",
+        GattKind::Characteristic => "Characteristic",
+        GattKind::Descriptor => "Descriptor",
+    };
+    let value = attribute
+        .value
+        .as_ref()
+        .map(|value| if value.is_empty() { "(empty)".to_string() } else { format_hex(value) })
+        .unwrap_or_else(|| "Not read yet".to_string());
+    let text = attribute
+        .value
+        .as_ref()
+        .and_then(|value| format_text(value))
+        .unwrap_or_else(|| "-".to_string());
+
+    let mut lines = vec![
         Line::from(vec![
-            Span::styled("c - Connect/Disconnect", Style::default().fg(Color::White)),
+            Span::styled("Type: ", Style::default().fg(theme.label)),
+            Span::raw(kind),
         ]),
         Line::from(vec![
-            Span::styled("p - Pair Device", Style::default().fg(Color::White)),
+            Span::styled("UUID: ", Style::default().fg(theme.label)),
+            Span::raw(attribute.uuid.clone()),
         ]),
         Line::from(vec![
-            Span::styled("r - Remove Device", Style::default().fg(Color::Red)),
+            Span::styled("Flags: ", Style::default().fg(theme.label)),
+            Span::raw(attribute.flags.join(", ")),
         ]),
+    ];
+    if let Some(format) = browser.write_format {
+        lines.push(Line::from(vec![
+            Span::styled(format!("Write ({}): ", format.label()), Style::default().fg(theme.label)),
+            Span::styled(browser.write_input.as_str(), Style::default().fg(theme.text)),
+            Span::styled("█", Style::default().fg(theme.text)),
+        ]));
+    } else if attribute.kind != GattKind::Service {
+        lines.push(Line::from(vec![
+            Span::styled("Value: ", Style::default().fg(theme.label)),
+            Span::raw(value),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Text: ", Style::default().fg(theme.label)),
+            Span::raw(text),
+        ]));
+    }
+    if attribute.can_notify() {
+        lines.push(Line::from(vec![
+            Span::styled("Notifying: ", Style::default().fg(theme.label)),
+            Span::styled(
+                if attribute.notifying { "Yes" } else { "No" },
+                Style::default().fg(if attribute.notifying { theme.success } else { theme.text }),
+            ),
+        ]));
+    }
+
+    Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+}
+
+/// Newest notifications at the bottom, as many as fit.
+fn create_gatt_log_widget<'a>(browser: &'a GattBrowser, theme: &Theme, height: u16) -> Paragraph<'a> {
+    let rows = height.saturating_sub(2) as usize;
+    let skip = browser.notifications.len().saturating_sub(rows);
+    let lines: Vec<Line> = browser
+        .notifications
+        .iter()
+        .skip(skip)
+        .map(|notification| {
+            let mut spans = vec![
+                Span::styled(
+                    format!("{:>7.1}s ", notification.elapsed.as_secs_f64()),
+                    Style::default().fg(theme.muted),
+                ),
+                Span::styled(format!("{} ", gatt_label(&notification.uuid)), Style::default().fg(theme.accent)),
+                Span::raw(format_hex(&notification.value)),
+            ];
+            if let Some(text) = format_text(&notification.value) {
+                spans.push(Span::styled(format!(" \"{}\"", text), Style::default().fg(theme.muted)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    let title = format!("Notifications ({})", browser.notifications.len());
+    Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title))
+}
+
+fn create_event_table_widget(app: &App) -> Table<'_> {
+    let theme = &app.theme;
+    let header_style = Style::default()
+        .fg(theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let header_row = Row::new(
+        ["Time", "Level", "Event"].map(|h| Span::styled(h, header_style))
+    );
+
+    let rows: Vec<Row> = if app.event_log.is_empty() {
+        vec![Row::new(vec![
+            Span::raw(""),
+            Span::raw(""),
+            Span::styled("No events yet", Style::default().fg(theme.muted)),
+        ])]
+    } else {
+        app.event_log
+            .entries()
+            .iter()
+            .map(|entry| {
+                let level_color = match entry.level {
+                    LogLevel::Info => theme.muted,
+                    LogLevel::Warning => theme.warning,
+                    LogLevel::Error => theme.error,
+                };
+                Row::new(vec![
+                    Span::styled(UtcTime::at(entry.time).clock(), Style::default().fg(theme.secondary)),
+                    Span::styled(entry.level.label(), Style::default().fg(level_color)),
+                    Span::raw(entry.message.as_str()),
+                ])
+            })
+            .collect()
+    };
+
+    let following = if app.log_index.is_none() { ", following" } else { "" };
+    let notifications = if app.desktop_notifications { " - desktop notifications on" } else { "" };
+    let title = format!("Event Log ({}{}){}", app.event_log.len(), following, notifications);
+    Table::new(
+        rows,
+        [Constraint::Length(8), Constraint::Length(7), Constraint::Min(20)],
+    )
+    .header(header_row)
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .highlight_style(Style::default().fg(theme.selection_fg).bg(theme.selection_bg))
+}
+
+fn create_log_actions_widget(app: &App, width: u16) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let mut lines = vec![Line::from(vec![
+        Span::styled("Log Actions:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+    ])];
+    lines.extend(key_help_lines(&app.keymap.log, theme, width.saturating_sub(2)));
+
+    Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Actions"))
+        .alignment(ratatui::layout::Alignment::Left)
+}
+
+fn create_gatt_actions_widget(app: &App, width: u16) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let writing = app.gatt.as_ref().is_some_and(|b| b.write_format.is_some());
+    let actions_text = if writing {
+        vec![
+            Line::from(vec![
+                Span::styled("Write Value:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            ]),
+            Line::from(vec![
+                Span::styled("Hex bytes (\"01 a0 ff\") or UTF-8 text", Style::default().fg(theme.text)),
+            ]),
+            Line::from(vec![
+                Span::styled("Enter - Write   ", Style::default().fg(theme.text)),
+                Span::styled("Tab - Hex/UTF-8   ", Style::default().fg(theme.text)),
+                Span::styled("ESC - Cancel", Style::default().fg(theme.text)),
+            ]),
+        ]
+    } else {
+        let mut lines = vec![Line::from(vec![
+            Span::styled("GATT Actions:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+        ])];
+        lines.extend(key_help_lines(&app.keymap.gatt, theme, width.saturating_sub(2)));
+        lines
+    };
+
+    Paragraph::new(actions_text)
+        .block(Block::default().borders(Borders::ALL).title("Actions"))
+        .alignment(ratatui::layout::Alignment::Left)
+}
+
+fn create_battery_gauge_widget<'a>(device: &'a BluetoothDevice, theme: &Theme) -> Gauge<'a> {
+    let level = device.battery.unwrap_or(0).min(100);
+    let color = if level < LOW_BATTERY_THRESHOLD {
+        theme.error
+    } else if level < 50 {
+        theme.warning
+    } else {
+        theme.success
+    };
+
+    Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title("Battery"))
+        .gauge_style(Style::default().fg(color).bg(theme.gauge_bg))
+        .percent(level.into())
+        .label(format!("{}%", level))
+}
+
+fn create_transfer_gauge_widget<'a>(transfer: &'a FileTransfer, theme: &Theme) -> Gauge<'a> {
+    let (verb, color) = match (transfer.status, transfer.direction) {
+        (TransferStatus::Complete, TransferDirection::Outgoing) => ("Sent", theme.success),
+        (TransferStatus::Complete, TransferDirection::Incoming) => ("Received", theme.success),
+        (TransferStatus::Error, _) => ("Failed", theme.error),
+        (_, TransferDirection::Outgoing) => ("Sending", theme.accent),
+        (_, TransferDirection::Incoming) => ("Receiving", theme.accent),
+    };
+    let label = match transfer.size {
+        Some(size) => format!(
+            "{}% ({} of {})",
+            transfer.percent(),
+            format_size(transfer.transferred),
+            format_size(size)
+        ),
+        None => format_size(transfer.transferred),
+    };
+
+    Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} {}", verb, transfer.name)),
+        )
+        .gauge_style(Style::default().fg(color).bg(theme.gauge_bg))
+        .percent(transfer.percent())
+        .label(label)
+}
+
+fn create_device_actions_widget(app: &App, width: u16) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let mut actions_text = vec![
         Line::from(vec![
-            Span::styled("ESC - Back to Device List", Style::default().fg(Color::White)),
+            Span::styled("Device Actions:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
         ]),
     ];
+    actions_text.extend(key_help_lines(&app.keymap.details, theme, width.saturating_sub(2)));
     
     Paragraph::new(actions_text)
         .block(Block::default().borders(Borders::ALL).title("Actions"))
         .alignment(ratatui::layout::Alignment::Left)
 }
 
-fn create_settings_widget(app: &App) -> Paragraph {
-    let settings_text = vec![
+/// One `key - description` entry of the packed key help, and where it goes.
+struct HelpEntry {
+    action: Action,
+    text: String,
+    line: usize,
+    x: u16, // columns from the left edge of the text area
+}
+
+const HELP_SEPARATOR: &str = "   ";
+
+/// Packs the entries of `bindings` into lines of at most `width` columns.
+/// Actions without keys are left out.
+fn layout_key_help(bindings: &Bindings, width: u16) -> Vec<HelpEntry> {
+    let mut entries: Vec<HelpEntry> = vec![];
+    let (mut line, mut x) = (0, 0);
+    for binding in bindings.iter() {
+        let Some(keys) = binding.keys_label() else { continue };
+        let text = format!("{} - {}", keys, binding.description);
+        let text_width = text.chars().count() as u16;
+        if !entries.is_empty() {
+            x += HELP_SEPARATOR.len() as u16;
+            if x + text_width > width {
+                line += 1;
+                x = 0;
+            }
+        }
+        let next_x = x + text_width;
+        entries.push(HelpEntry {
+            action: binding.action,
+            text,
+            line,
+            x,
+        });
+        x = next_x;
+    }
+    entries
+}
+
+fn key_help_lines(bindings: &Bindings, theme: &Theme, width: u16) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line> = vec![];
+    for entry in layout_key_help(bindings, width) {
+        if entry.line == lines.len() {
+            lines.push(Line::default());
+        } else {
+            lines[entry.line].spans.push(Span::raw(HELP_SEPARATOR));
+        }
+        // Destructive actions stand out
+        let color = if entry.action == Action::Remove { theme.error } else { theme.text };
+        lines[entry.line].spans.push(Span::styled(entry.text, Style::default().fg(color)));
+    }
+    lines
+}
+
+/// The help entry at `(column, row)` of a key help drawn inside `area`,
+/// starting `first_line` lines below the top border.
+fn key_help_hit(bindings: &Bindings, area: Rect, first_line: u16, column: u16, row: u16) -> Option<Action> {
+    let inner = Rect::new(area.x + 1, area.y + 1, area.width.saturating_sub(2), area.height.saturating_sub(2));
+    if !inner.intersects(Rect::new(column, row, 1, 1)) || row < inner.y + first_line {
+        return None;
+    }
+    let line = (row - inner.y - first_line) as usize;
+    let x = column - inner.x;
+    layout_key_help(bindings, inner.width)
+        .into_iter()
+        .find(|entry| entry.line == line && (entry.x..entry.x + entry.text.chars().count() as u16).contains(&x))
+        .map(|entry| entry.action)
+}
+
+/// What a mouse click lands on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClickTarget {
+    DeviceRow(usize), // index into visible_devices()
+    Action(Action),   // an entry of the actions box of the current view
+}
+
+/// Finds what was drawn at `(column, row)` of a frame of size `area`, using
+/// the same layouts as `draw`.
+pub fn click_target(app: &App, area: Rect, column: u16, row: u16) -> Option<ClickTarget> {
+    match app.current_view {
+        View::DeviceList => {
+            let table = create_main_layout(area)[1];
+            // Border and header row come before the first device
+            let rows = Rect::new(table.x + 1, table.y + 2, table.width.saturating_sub(2), table.height.saturating_sub(3));
+            if !rows.intersects(Rect::new(column, row, 1, 1)) {
+                return None;
+            }
+            let index = app.table_offset + (row - rows.y) as usize;
+            (index < app.visible_devices().len()).then_some(ClickTarget::DeviceRow(index))
+        }
+        View::DeviceDetails => {
+            let actions = create_details_layout(area)[2];
+            key_help_hit(&app.keymap.details, actions, 1, column, row).map(ClickTarget::Action)
+        }
+        View::Gatt if app.gatt.as_ref().is_some_and(|b| b.write_format.is_none()) => {
+            let actions = create_details_layout(area)[2];
+            key_help_hit(&app.keymap.gatt, actions, 1, column, row).map(ClickTarget::Action)
+        }
+        View::Log => {
+            let actions = create_details_layout(area)[2];
+            key_help_hit(&app.keymap.log, actions, 1, column, row).map(ClickTarget::Action)
+        }
+        _ => None,
+    }
+}
+
+fn create_settings_widget(app: &App, width: u16) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let adapter = app.current_adapter().cloned().unwrap_or_default();
+    let discoverable = adapter.discoverable;
+    let pairable = adapter.pairable;
+
+    let mut settings_text = vec![
         Line::from(vec![
-            Span::styled("Settings:", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled("Adapters:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
         ]),
+    ];
+
+    if app.adapters.is_empty() {
+        settings_text.push(Line::from(Span::styled("No adapters found", Style::default().fg(theme.error))));
+    }
+    for item in &app.adapters {
+        let selected = item.name == adapter.name;
+        let mut spans = vec![Span::styled(
+            if selected { "▶ " } else { "  " },
+            Style::default().fg(theme.accent),
+        )];
+        if app.alias_edit == Some(AliasTarget::Adapter(item.name.clone())) {
+            spans.push(Span::styled(format!("{:<6} {} ", item.name, item.address), Style::default()));
+            spans.extend(alias_input_spans(app));
+        } else {
+            spans.extend([
+                Span::styled(
+                    format!("{:<6} {} {:<20} ", item.name, item.address, item.alias),
+                    if selected { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() },
+                ),
+                Span::styled(
+                    if item.powered { "On" } else { "Off" },
+                    Style::default().fg(if item.powered { theme.success } else { theme.error }),
+                ),
+            ]);
+        }
+        settings_text.push(Line::from(spans));
+    }
+
+    settings_text.extend([
         Line::from(vec![
-            Span::styled("Bluetooth: ", Style::default().fg(Color::Yellow)),
-            Span::styled(
-                if app.bluetooth_enabled { "Enabled" } else { "Disabled" },
-                Style::default().fg(if app.bluetooth_enabled { Color::Green } else { Color::Red })
-            ),
+            Span::styled("", Style::default()),
         ]),
         Line::from(vec![
-            Span::styled("Total Devices: ", Style::default().fg(Color::Yellow)),
-            Span::styled(app.get_device_count().to_string(), Style::default()),
+            Span::styled("Settings:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
         ]),
         Line::from(vec![
-            Span::styled("Known Devices: ", Style::default().fg(Color::Yellow)),
-            Span::styled(app.get_known_devices_count().to_string(), Style::default().fg(Color::Blue)),
+            Span::styled("Bluetooth: ", Style::default().fg(theme.label)),
+            Span::styled(
+                if app.bluetooth_enabled { "Enabled" } else { "Disabled" },
+                Style::default().fg(if app.bluetooth_enabled { theme.success } else { theme.error })
+            ),
         ]),
         Line::from(vec![
-            Span::styled("Available Devices: ", Style::default().fg(Color::Yellow)),
-            Span::styled(app.get_available_devices_count().to_string(), Style::default()),
+            Span::styled("RF Kill: ", Style::default().fg(theme.label)),
+            Span::styled(
+                rfkill_text(app),
+                Style::default().fg(if app.adapter_block_state() == BlockState::Unblocked {
+                    theme.success
+                } else {
+                    theme.error
+                }),
+            ),
         ]),
         Line::from(vec![
-            Span::styled("Connected Devices: ", Style::default().fg(Color::Yellow)),
-            Span::styled(app.get_connected_count().to_string(), Style::default()),
+            Span::styled("Discoverable: ", Style::default().fg(theme.label)),
+            Span::styled(
+                if discoverable { "Yes" } else { "No" },
+                Style::default().fg(if discoverable { theme.success } else { theme.error })
+            ),
         ]),
         Line::from(vec![
-            Span::styled("", Style::default()),
+            Span::styled("Discoverable Timeout: ", Style::default().fg(theme.label)),
+            Span::styled(format_timeout(adapter.discoverable_timeout), Style::default()),
         ]),
         Line::from(vec![
-            Span::styled("t - Toggle Bluetooth", Style::default().fg(Color::White)),
+            Span::styled("Pairable: ", Style::default().fg(theme.label)),
+            Span::styled(
+                if pairable { "Yes" } else { "No" },
+                Style::default().fg(if pairable { theme.success } else { theme.error })
+            ),
         ]),
         Line::from(vec![
-            Span::styled("s - Start/Stop Scanning", Style::default().fg(Color::White)),
+            Span::styled("Devices: ", Style::default().fg(theme.label)),
+            Span::styled(app.get_device_count().to_string(), Style::default()),
+            Span::styled(" (", Style::default()),
+            Span::styled(format!("{} known", app.get_known_devices_count()), Style::default().fg(theme.known)),
+            Span::styled(
+                format!(
+                    ", {} available, {} connected)",
+                    app.get_available_devices_count(),
+                    app.get_connected_count()
+                ),
+                Style::default(),
+            ),
         ]),
         Line::from(vec![
-            Span::styled("ESC - Back to Device List", Style::default().fg(Color::White)),
+            Span::styled("", Style::default()),
         ]),
-    ];
+    ]);
+    settings_text.extend(key_help_lines(&app.keymap.settings, theme, width.saturating_sub(2)));
     
     Paragraph::new(settings_text)
         .block(Block::default().borders(Borders::ALL).title("Settings"))
         .alignment(ratatui::layout::Alignment::Left)
 }
 
-fn create_status_widget(app: &App) -> Paragraph {
+/// One line per field, with the cursor and the editor of the Settings view.
+fn create_discovery_filter_widget(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let filter = &app.discovery_filter;
+    let lines: Vec<Line> = FilterField::ALL
+        .iter()
+        .map(|&field| {
+            let selected = field == app.filter_field;
+            let mut spans = vec![
+                Span::styled(if selected { "▶ " } else { "  " }, Style::default().fg(theme.accent)),
+                Span::styled(format!("{}: ", field.label()), Style::default().fg(theme.label)),
+            ];
+            if app.filter_edit == Some(field) {
+                spans.extend([
+                    Span::styled("> ", Style::default().fg(theme.accent)),
+                    Span::styled(app.filter_input.clone(), Style::default().fg(theme.label).add_modifier(Modifier::BOLD)),
+                    Span::styled("_", Style::default().fg(theme.secondary)),
+                    Span::styled(
+                        if field == FilterField::Uuids { "  (empty - Any)" } else { "  (empty - Off)" },
+                        Style::default().fg(theme.muted),
+                    ),
+                ]);
+            } else {
+                let unset = match field {
+                    FilterField::Rssi => filter.rssi.is_none(),
+                    FilterField::Pathloss => filter.pathloss.is_none(),
+                    FilterField::Uuids => filter.uuids.is_empty(),
+                    _ => false,
+                };
+                spans.push(Span::styled(
+                    filter.field_text(field),
+                    if unset { Style::default().fg(theme.muted) } else { Style::default() },
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Discovery Filter"))
+}
+
+/// "Unblocked", or the blocked switches of the selected adapter:
+/// "Soft-blocked (hci0 soft)".
+fn rfkill_text(app: &App) -> String {
+    let switches = app.adapter_switches();
+    if switches.is_empty() {
+        return "No switches".to_string();
+    }
+    let state = BlockState::of(&switches);
+    if state == BlockState::Unblocked {
+        return state.label().to_string();
+    }
+    let blocked: Vec<String> = switches
+        .iter()
+        .filter(|s| s.soft || s.hard)
+        .map(|s| {
+            let kind = match (s.soft, s.hard) {
+                (true, true) => "soft+hard",
+                (false, true) => "hard",
+                _ => "soft",
+            };
+            format!("{} {}", s.name, kind)
+        })
+        .collect();
+    format!("{} ({})", state.label(), blocked.join(", "))
+}
+
+fn create_key_help_widget(app: &App, width: u16) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let heading = |text| {
+        Line::from(Span::styled(text, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)))
+    };
+
+    let mut lines = vec![heading("Device List:")];
+    lines.extend(key_help_lines(&app.keymap.list, theme, width.saturating_sub(2)));
+    lines.push(Line::from(""));
+    lines.push(heading("Everywhere:"));
+    lines.extend(key_help_lines(&app.keymap.global, theme, width.saturating_sub(2)));
+    if let Some(path) = Config::default_path() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("Keys and colours: {}", path.display()),
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Keys"))
+        .wrap(Wrap { trim: false })
+}
+
+/// The alias being typed, with a cursor and the keys that end the edit.
+fn alias_input_spans(app: &App) -> Vec<Span<'_>> {
+    let theme = &app.theme;
+    vec![
+        Span::styled("> ", Style::default().fg(theme.accent)),
+        Span::styled(app.alias_input.clone(), Style::default().fg(theme.label).add_modifier(Modifier::BOLD)),
+        Span::styled("_", Style::default().fg(theme.secondary)),
+        Span::styled("  (Enter - Save, ESC - Cancel, empty - Reset)", Style::default().fg(theme.muted)),
+    ]
+}
+
+fn create_status_widget(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
     let status_style = if app.error_message.is_some() {
-        Style::default().fg(Color::Red)
+        Style::default().fg(theme.error)
     } else {
-        Style::default().fg(Color::Green)
+        Style::default().fg(theme.success)
     };
     
     let status_text = if let Some(error) = &app.error_message {
@@ -357,12 +1365,306 @@ fn create_status_widget(app: &App) -> Paragraph {
         app.status_message.clone()
     };
     
-    let status = Line::from(vec![
-        Span::styled("Status: ", Style::default().fg(Color::Cyan)),
-        Span::styled(status_text, status_style),
-    ]);
-    
+    let status = if app.searching {
+        Line::from(vec![
+            Span::styled("Search: /", Style::default().fg(theme.accent)),
+            Span::styled(app.search_query.as_str(), Style::default().fg(theme.text)),
+            Span::styled("█", Style::default().fg(theme.text)),
+        ])
+    } else {
+        Line::from(vec![
+            Span::styled("Status: ", Style::default().fg(theme.accent)),
+            Span::styled(status_text, status_style),
+        ])
+    };
+
     Paragraph::new(status)
         .block(Block::default().borders(Borders::ALL).title("Status"))
         .alignment(ratatui::layout::Alignment::Left)
-} 
\ No newline at end of file
+}
+
+fn create_agent_dialog_widget(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let Some(request) = &app.agent_request else {
+        return Paragraph::new("");
+    };
+
+    let device_name = app.device_name(&request.adapter, &request.address);
+
+    let code_style = Style::default()
+        .fg(theme.label)
+        .add_modifier(Modifier::BOLD);
+    let input = Line::from(vec![
+        Span::styled("> ", Style::default().fg(theme.accent)),
+        Span::styled(app.agent_input.clone(), code_style),
+        Span::styled("_", Style::default().fg(theme.secondary)),
+    ]);
+
+    let (question, detail, keys) = match &request.prompt {
+        AgentPrompt::RequestPinCode => (
+            format!("Enter the PIN code for {}", device_name),
+            input,
+            "Enter - Submit   ESC - Cancel",
+        ),
+        AgentPrompt::RequestPasskey => (
+            format!("Enter the passkey shown on {}", device_name),
+            input,
+            "Enter - Submit   ESC - Cancel",
+        ),
+        AgentPrompt::DisplayPinCode(pin) => (
+            format!("Type this PIN code on {}", device_name),
+            Line::from(Span::styled(pin.clone(), code_style)),
+            "Enter/ESC - Close",
+        ),
+        AgentPrompt::DisplayPasskey { passkey, entered } => (
+            format!("Type this passkey on {} ({} typed)", device_name, entered),
+            Line::from(Span::styled(format!("{:06}", passkey), code_style)),
+            "Enter/ESC - Close",
+        ),
+        AgentPrompt::RequestConfirmation(passkey) => (
+            format!("Does {} show this passkey?", device_name),
+            Line::from(Span::styled(format!("{:06}", passkey), code_style)),
+            "y/Enter - Confirm   n/ESC - Reject",
+        ),
+        AgentPrompt::RequestAuthorization => (
+            format!("Allow {} to pair?", device_name),
+            Line::from(""),
+            "y/Enter - Allow   n/ESC - Reject",
+        ),
+        AgentPrompt::AuthorizeService(uuid) => (
+            format!("Allow {} to use this service?", device_name),
+            Line::from(Span::styled(uuid.clone(), code_style)),
+            "y/Enter - Allow   n/ESC - Reject",
+        ),
+        AgentPrompt::AuthorizePush { name, size } => (
+            format!("{} wants to send you a file", device_name),
+            Line::from(Span::styled(
+                match size {
+                    Some(size) => format!("{} ({})", name, format_size(*size)),
+                    None => name.clone(),
+                },
+                code_style,
+            )),
+            "y/Enter - Accept   n/ESC - Reject",
+        ),
+    };
+    let mut title = if matches!(request.prompt, AgentPrompt::AuthorizePush { .. }) {
+        "Incoming File".to_string()
+    } else {
+        "Pairing Request".to_string()
+    };
+    if !app.agent_queue.is_empty() {
+        title.push_str(&format!(" ({} more waiting)", app.agent_queue.len()));
+    }
+
+    let dialog_text = vec![
+        Line::from(Span::styled(question, Style::default().fg(theme.accent))),
+        Line::from(""),
+        detail,
+        Line::from(""),
+        Line::from(Span::styled(keys, Style::default().fg(theme.text))),
+    ];
+
+    Paragraph::new(dialog_text)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: true })
+}
+
+fn create_unblock_dialog_widget(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let dialog_text = vec![
+        Line::from(Span::styled(
+            "Bluetooth is soft-blocked by rfkill",
+            Style::default().fg(theme.accent),
+        )),
+        Line::from(""),
+        Line::from(Span::styled("Unblock it and power the adapter on?", Style::default().fg(theme.text))),
+        Line::from(""),
+        Line::from(Span::styled("y/Enter - Unblock   n/ESC - Cancel", Style::default().fg(theme.text))),
+    ];
+
+    Paragraph::new(dialog_text)
+        .block(Block::default().borders(Borders::ALL).title("RF Kill"))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentRequest;
+    use crate::rfkill::RfkillSwitch;
+    use crate::simulated::SimulatedBackend;
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::sync::Arc;
+
+    /// The demo backend as the TUI starts with it: hci0 selected and the
+    /// connected headphones in the list.
+    async fn demo_app() -> App {
+        let mut app = App::new();
+        let _events = app.attach_backend(Arc::new(SimulatedBackend::demo())).await;
+        app
+    }
+
+    /// Draws one frame the size of a small terminal and returns its text.
+    fn render(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 32)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[tokio::test]
+    async fn device_list_shows_the_devices_and_counts() {
+        let mut app = demo_app().await;
+        let screen = render(&mut app);
+
+        assert!(screen.contains("● hci0 | 1 devices (1 known, 0 available)"));
+        assert!(screen.contains("Name                 Address             Adapter"));
+        let row = screen
+            .lines()
+            .find(|line| line.contains("WH-1000XM4"))
+            .expect("headphones row");
+        for column in ["00:1B:66:A1:B2:C3", "Headphones", "Connected", "24%", "Yes"] {
+            assert!(row.contains(column), "{} missing from {}", column, row);
+        }
+        assert!(screen.contains("Status: Ready - Press 's' to start scanning"));
+    }
+
+    #[tokio::test]
+    async fn device_details_show_profiles_and_battery() {
+        let mut app = demo_app().await;
+        app.change_view(View::DeviceDetails);
+        let screen = render(&mut app);
+
+        assert!(screen.contains("Address: 00:1B:66:A1:B2:C3"));
+        assert!(screen.contains("Profiles - Active: Audio Sink (A2DP)"));
+        assert!(screen.contains("● Audio Sink (A2DP)"));
+        assert!(screen.contains("○ Handsfree (HFP)"));
+        assert!(screen.contains("Signal - out of range"));
+        assert!(screen.contains("24%"));
+        assert!(screen.contains("c - Connect/Disconnect"));
+    }
+
+    #[tokio::test]
+    async fn settings_list_adapters_filter_and_keys() {
+        let mut app = demo_app().await;
+        app.change_view(View::Settings);
+        let screen = render(&mut app);
+
+        assert!(screen.contains("▶ hci0   00:1A:7D:DA:71:13"));
+        assert!(screen.contains("  hci1   5C:F3:70:8B:12:04"));
+        assert!(screen.contains("Discoverable Timeout: 3 min"));
+        assert!(screen.contains("▶ Transport: Auto (classic and LE)"));
+        assert!(screen.contains("Enter - Device Details   s - Start/Stop Scanning"));
+    }
+
+    #[tokio::test]
+    async fn help_follows_the_configured_keys() {
+        let mut app = demo_app().await;
+        let dir = std::env::temp_dir()
+            .join(format!("bluetooth-manager-ui-keys-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "[keys.list]\nscan = \"F5\"\n").unwrap();
+        app.apply_config(Config::load(&path).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        app.change_view(View::Settings);
+        let screen = render(&mut app);
+
+        assert!(screen.contains("F5 - Start/Stop Scanning"));
+        assert!(!screen.contains("s - Start/Stop Scanning   t"));
+    }
+
+    #[tokio::test]
+    async fn event_log_shows_new_events() {
+        let mut app = demo_app().await;
+        app.change_view(View::Log);
+        assert!(render(&mut app).contains("No events yet"));
+
+        app.log(LogLevel::Warning, "Low battery: WH-1000XM4 is at 18%");
+        let screen = render(&mut app);
+        assert!(screen.contains("Event Log (1, following)"));
+        let row = screen
+            .lines()
+            .find(|line| line.contains("Low battery"))
+            .expect("log row");
+        assert!(row.contains(" warning Low battery"), "{}", row);
+    }
+
+    #[tokio::test]
+    async fn gatt_view_shows_the_attribute_tree() {
+        let mut app = demo_app().await;
+        app.change_view(View::DeviceDetails);
+        app.open_gatt_browser().await;
+        let screen = render(&mut app);
+
+        assert!(screen.contains("GATT - WH-1000XM4"));
+        assert!(screen.contains("▶ Generic Access"));
+        assert!(screen.contains("Battery Level [RN]"));
+        assert!(screen.contains("UUID: 00001800-0000-1000-8000-00805f9b34fb"));
+        assert!(screen.contains("Status: 4 GATT services on WH-1000XM4"));
+    }
+
+    #[tokio::test]
+    async fn pairing_dialog_is_drawn_over_the_current_view() {
+        let mut app = demo_app().await;
+        let (request, _reply) = AgentRequest::new(
+            "hci0".to_string(),
+            "00:1B:66:A1:B2:C3".to_string(),
+            AgentPrompt::RequestConfirmation(123_456),
+        );
+        app.agent_request = Some(request);
+        let screen = render(&mut app);
+
+        assert!(screen.contains("WH-1000XM4"));
+        assert!(screen.contains("123456"));
+        // The list stays visible around the dialog.
+        assert!(screen.contains("┌Devices"));
+
+        let (waiting, _reply) = AgentRequest::new(
+            "hci0".to_string(),
+            "11:22:33:44:55:66".to_string(),
+            AgentPrompt::RequestAuthorization,
+        );
+        app.agent_queue.push_back(waiting);
+        assert!(render(&mut app).contains("Pairing Request (1 more waiting)"));
+    }
+
+    #[tokio::test]
+    async fn unblock_dialog_asks_before_powering_on() {
+        let mut app = demo_app().await;
+        app.change_view(View::Settings);
+        app.unblock_prompt = true;
+        let screen = render(&mut app);
+
+        assert!(screen.contains("Bluetooth is soft-blocked by rfkill"));
+        assert!(screen.contains("y/Enter - Unblock   n/ESC - Cancel"));
+    }
+
+    #[tokio::test]
+    async fn rfkill_state_is_the_selected_adapters() {
+        let mut app = demo_app().await;
+        app.rfkill_switches = vec![
+            RfkillSwitch { index: 0, name: "hci0".to_string(), soft: false, hard: false },
+            RfkillSwitch { index: 1, name: "hci1".to_string(), soft: true, hard: false },
+        ];
+        app.change_view(View::Settings);
+        let screen = render(&mut app);
+        assert!(screen.contains("RF Kill: Unblocked"));
+        assert!(!screen.contains("[SOFT-BLOCKED]"));
+
+        app.select_adapter("hci1");
+        let screen = render(&mut app);
+        assert!(screen.contains("RF Kill: Soft-blocked (hci1 soft)"));
+        assert!(screen.contains("[SOFT-BLOCKED]"));
+    }
+}